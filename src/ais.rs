@@ -0,0 +1,481 @@
+use crate::Nmea0183Msg;
+use std::collections::HashMap;
+
+// incomplete multi sentence messages kept at the same time
+const MAX_PENDING: usize = 16;
+
+const LON_NOT_AVAILABLE: i64 = 181 * 600_000;
+const LAT_NOT_AVAILABLE: i64 = 91 * 600_000;
+const SPEED_NOT_AVAILABLE: u64 = 1023;
+const COURSE_NOT_AVAILABLE: u64 = 3600;
+const HEADING_NOT_AVAILABLE: u64 = 511;
+const TURN_NOT_AVAILABLE: i8 = -128;
+
+/// Message types 1, 2 and 3 - Class A position report
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PositionReport {
+    pub msg_type: u8,
+    pub repeat: u8,
+    pub mmsi: u32,
+    /// Navigation status, e.g. 0 under way using engine, 5 moored
+    pub status: u8,
+    /// Raw rate of turn indicator, see [`PositionReport::rate_of_turn`].
+    pub turn: i8,
+    /// Speed over ground in knots.
+    pub speed: Option<f64>,
+    pub accuracy: bool,
+    pub longitude: Option<f64>,
+    pub latitude: Option<f64>,
+    /// Course over ground in degrees.
+    pub course: Option<f64>,
+    pub heading: Option<u16>,
+    /// Second of the UTC minute of the report, 60 and above mean not available.
+    pub second: u8,
+    pub maneuver: u8,
+    pub raim: bool,
+}
+
+impl PositionReport {
+    /// Rate of turn in degrees per minute, `None` if not available or if the vessel turns
+    /// faster than 5 degrees per 30 seconds without a turn indicator.
+    pub fn rate_of_turn(&self) -> Option<f64> {
+        match self.turn {
+            TURN_NOT_AVAILABLE | 127 | -127 => None,
+            turn => {
+                let rate = (f64::from(turn) / 4.733).powi(2);
+                Some(if turn < 0 { -rate } else { rate })
+            }
+        }
+    }
+}
+
+/// Message type 5 - Static and voyage related data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StaticAndVoyage {
+    pub repeat: u8,
+    pub mmsi: u32,
+    pub ais_version: u8,
+    pub imo: u32,
+    pub callsign: String,
+    pub shipname: String,
+    pub shiptype: u8,
+    pub to_bow: u16,
+    pub to_stern: u16,
+    pub to_port: u8,
+    pub to_starboard: u8,
+    /// Type of position fixing device, e.g. 1 GPS
+    pub epfd: u8,
+    /// Estimated time of arrival in UTC, 0 means not available.
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    /// Draught in meters.
+    pub draught: f64,
+    pub destination: String,
+}
+
+/// Message type 18 - Standard class B position report
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassBPosition {
+    pub repeat: u8,
+    pub mmsi: u32,
+    pub speed: Option<f64>,
+    pub accuracy: bool,
+    pub longitude: Option<f64>,
+    pub latitude: Option<f64>,
+    pub course: Option<f64>,
+    pub heading: Option<u16>,
+    pub second: u8,
+    pub raim: bool,
+}
+
+/// Message type 24 - Static data report, part A carries the name, part B the rest.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StaticDataReport {
+    PartA {
+        repeat: u8,
+        mmsi: u32,
+        shipname: String,
+    },
+    PartB {
+        repeat: u8,
+        mmsi: u32,
+        shiptype: u8,
+        vendor_id: String,
+        callsign: String,
+        to_bow: u16,
+        to_stern: u16,
+        to_port: u8,
+        to_starboard: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AisMessage {
+    PositionReport(PositionReport),
+    StaticAndVoyage(StaticAndVoyage),
+    ClassBPosition(ClassBPosition),
+    StaticDataReport(StaticDataReport),
+    /// Message types without decoder.
+    Other {
+        msg_type: u8,
+        repeat: u8,
+        mmsi: u32,
+    },
+}
+
+impl AisMessage {
+    pub fn msg_type(&self) -> u8 {
+        match self {
+            AisMessage::PositionReport(report) => report.msg_type,
+            AisMessage::StaticAndVoyage(_) => 5,
+            AisMessage::ClassBPosition(_) => 18,
+            AisMessage::StaticDataReport(_) => 24,
+            AisMessage::Other { msg_type, .. } => *msg_type,
+        }
+    }
+
+    pub fn repeat(&self) -> u8 {
+        match self {
+            AisMessage::PositionReport(report) => report.repeat,
+            AisMessage::StaticAndVoyage(report) => report.repeat,
+            AisMessage::ClassBPosition(report) => report.repeat,
+            AisMessage::StaticDataReport(StaticDataReport::PartA { repeat, .. })
+            | AisMessage::StaticDataReport(StaticDataReport::PartB { repeat, .. })
+            | AisMessage::Other { repeat, .. } => *repeat,
+        }
+    }
+
+    pub fn mmsi(&self) -> u32 {
+        match self {
+            AisMessage::PositionReport(report) => report.mmsi,
+            AisMessage::StaticAndVoyage(report) => report.mmsi,
+            AisMessage::ClassBPosition(report) => report.mmsi,
+            AisMessage::StaticDataReport(StaticDataReport::PartA { mmsi, .. })
+            | AisMessage::StaticDataReport(StaticDataReport::PartB { mmsi, .. })
+            | AisMessage::Other { mmsi, .. } => *mmsi,
+        }
+    }
+}
+
+/// Reassembles `!AIVDM` / `!AIVDO` sentences and decodes the payload.
+#[derive(Default)]
+pub struct AisDecoder {
+    // fragments received so far, keyed on the sequential message id and channel
+    pending: HashMap<(String, String), Vec<String>>,
+}
+
+impl AisDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `Ok(None)` until the last sentence of a multi sentence message arrived.
+    pub fn decode(&mut self, msg: &Nmea0183Msg) -> Result<Option<AisMessage>, String> {
+        if !matches!(msg.msgtype(), "VDM" | "VDO") {
+            return Err(format!(
+                "Invalid message type {}, expected VDM or VDO",
+                msg.msgtype()
+            ));
+        }
+        let params = msg.params();
+        if params.len() < 6 {
+            return Err(format!("Missing fields in {}", msg.msgtype()));
+        }
+        let invalid = |idx: usize| {
+            format!(
+                "Invalid value '{}' in field {} of {}",
+                params[idx],
+                idx + 1,
+                msg.msgtype()
+            )
+        };
+        let total: usize = params[0].parse().map_err(|_| invalid(0))?;
+        let number: usize = params[1].parse().map_err(|_| invalid(1))?;
+        let fill_bits: u8 = params[5].parse().map_err(|_| invalid(5))?;
+        if number == 0 || number > total {
+            return Err(invalid(1));
+        }
+        if total == 1 {
+            return decode_payload(&params[4], fill_bits).map(Some);
+        }
+
+        let key = (params[2].clone(), params[3].clone());
+        if number == 1 {
+            if self.pending.len() >= MAX_PENDING {
+                // fragments of messages that never completed
+                self.pending.clear();
+            }
+            self.pending.insert(key.clone(), Vec::new());
+        }
+        let fragments = match self.pending.get_mut(&key) {
+            Some(fragments) if fragments.len() + 1 == number => fragments,
+            _ => {
+                self.pending.remove(&key);
+                return Err(format!(
+                    "Missing fragment before {} of {} in {}",
+                    number,
+                    total,
+                    msg.msgtype()
+                ));
+            }
+        };
+        fragments.push(params[4].clone());
+        if number < total {
+            return Ok(None);
+        }
+        let payload = self.pending.remove(&key).unwrap_or_default().concat();
+        decode_payload(&payload, fill_bits).map(Some)
+    }
+}
+
+struct Bits {
+    values: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    fn new(payload: &str, fill_bits: u8) -> Result<Self, String> {
+        let values = payload
+            .bytes()
+            .map(|byte| match byte {
+                b'0'..=b'W' => Ok(byte - b'0'),
+                b'`'..=b'w' => Ok(byte - b'0' - 8),
+                _ => Err(format!("Invalid AIS payload character '{}'", byte as char)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let len = (values.len() * 6).saturating_sub(usize::from(fill_bits));
+        Ok(Self { values, len })
+    }
+
+    fn unsigned(&self, start: usize, width: usize) -> u64 {
+        (start..start + width).fold(0, |value, bit| {
+            let set = bit < self.len && self.values[bit / 6] & (0x20 >> (bit % 6)) != 0;
+            (value << 1) | u64::from(set)
+        })
+    }
+
+    fn signed(&self, start: usize, width: usize) -> i64 {
+        let value = self.unsigned(start, width) as i64;
+        // sign extend
+        (value << (64 - width)) >> (64 - width)
+    }
+
+    fn flag(&self, start: usize) -> bool {
+        self.unsigned(start, 1) != 0
+    }
+
+    /// Six bit ASCII, trailing `@` padding and spaces are removed.
+    fn text(&self, start: usize, chars: usize) -> String {
+        let text = (0..chars)
+            .map(|idx| {
+                let value = self.unsigned(start + idx * 6, 6) as u8;
+                (if value < 32 { value + 64 } else { value }) as char
+            })
+            .collect::<String>();
+        text.trim_end_matches(['@', ' ']).to_string()
+    }
+
+    fn require(&self, bits: usize, msg_type: u64) -> Result<(), String> {
+        if self.len < bits {
+            Err(format!(
+                "AIS message type {} too short, {} of {} bits",
+                msg_type, self.len, bits
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn coordinate(value: i64, not_available: i64) -> Option<f64> {
+    if value == not_available {
+        None
+    } else {
+        Some(value as f64 / 600_000.0)
+    }
+}
+
+fn tenths(value: u64, not_available: u64) -> Option<f64> {
+    if value == not_available {
+        None
+    } else {
+        Some(value as f64 / 10.0)
+    }
+}
+
+fn heading(value: u64) -> Option<u16> {
+    if value == HEADING_NOT_AVAILABLE {
+        None
+    } else {
+        Some(value as u16)
+    }
+}
+
+/// Decodes the armored payload of a complete message.
+pub fn decode_payload(payload: &str, fill_bits: u8) -> Result<AisMessage, String> {
+    let bits = Bits::new(payload, fill_bits)?;
+    bits.require(38, 0)?;
+    let msg_type = bits.unsigned(0, 6);
+    let repeat = bits.unsigned(6, 2) as u8;
+    let mmsi = bits.unsigned(8, 30) as u32;
+    Ok(match msg_type {
+        1..=3 => {
+            bits.require(149, msg_type)?;
+            AisMessage::PositionReport(PositionReport {
+                msg_type: msg_type as u8,
+                repeat,
+                mmsi,
+                status: bits.unsigned(38, 4) as u8,
+                turn: bits.signed(42, 8) as i8,
+                speed: tenths(bits.unsigned(50, 10), SPEED_NOT_AVAILABLE),
+                accuracy: bits.flag(60),
+                longitude: coordinate(bits.signed(61, 28), LON_NOT_AVAILABLE),
+                latitude: coordinate(bits.signed(89, 27), LAT_NOT_AVAILABLE),
+                course: tenths(bits.unsigned(116, 12), COURSE_NOT_AVAILABLE),
+                heading: heading(bits.unsigned(128, 9)),
+                second: bits.unsigned(137, 6) as u8,
+                maneuver: bits.unsigned(143, 2) as u8,
+                raim: bits.flag(148),
+            })
+        }
+        5 => {
+            bits.require(420, msg_type)?;
+            AisMessage::StaticAndVoyage(StaticAndVoyage {
+                repeat,
+                mmsi,
+                ais_version: bits.unsigned(38, 2) as u8,
+                imo: bits.unsigned(40, 30) as u32,
+                callsign: bits.text(70, 7),
+                shipname: bits.text(112, 20),
+                shiptype: bits.unsigned(232, 8) as u8,
+                to_bow: bits.unsigned(240, 9) as u16,
+                to_stern: bits.unsigned(249, 9) as u16,
+                to_port: bits.unsigned(258, 6) as u8,
+                to_starboard: bits.unsigned(264, 6) as u8,
+                epfd: bits.unsigned(270, 4) as u8,
+                month: bits.unsigned(274, 4) as u8,
+                day: bits.unsigned(278, 5) as u8,
+                hour: bits.unsigned(283, 5) as u8,
+                minute: bits.unsigned(288, 6) as u8,
+                draught: bits.unsigned(294, 8) as f64 / 10.0,
+                destination: bits.text(302, 20),
+            })
+        }
+        18 => {
+            bits.require(148, msg_type)?;
+            AisMessage::ClassBPosition(ClassBPosition {
+                repeat,
+                mmsi,
+                speed: tenths(bits.unsigned(46, 10), SPEED_NOT_AVAILABLE),
+                accuracy: bits.flag(56),
+                longitude: coordinate(bits.signed(57, 28), LON_NOT_AVAILABLE),
+                latitude: coordinate(bits.signed(85, 27), LAT_NOT_AVAILABLE),
+                course: tenths(bits.unsigned(112, 12), COURSE_NOT_AVAILABLE),
+                heading: heading(bits.unsigned(124, 9)),
+                second: bits.unsigned(133, 6) as u8,
+                raim: bits.flag(147),
+            })
+        }
+        24 => match bits.unsigned(38, 2) {
+            0 => {
+                bits.require(160, msg_type)?;
+                AisMessage::StaticDataReport(StaticDataReport::PartA {
+                    repeat,
+                    mmsi,
+                    shipname: bits.text(40, 20),
+                })
+            }
+            1 => {
+                bits.require(162, msg_type)?;
+                AisMessage::StaticDataReport(StaticDataReport::PartB {
+                    repeat,
+                    mmsi,
+                    shiptype: bits.unsigned(40, 8) as u8,
+                    vendor_id: bits.text(48, 7),
+                    callsign: bits.text(90, 7),
+                    to_bow: bits.unsigned(132, 9) as u16,
+                    to_stern: bits.unsigned(141, 9) as u16,
+                    to_port: bits.unsigned(150, 6) as u8,
+                    to_starboard: bits.unsigned(156, 6) as u8,
+                })
+            }
+            part => return Err(format!("Invalid AIS message 24 part number {}", part)),
+        },
+        msg_type => AisMessage::Other {
+            msg_type: msg_type as u8,
+            repeat,
+            mmsi,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_position_report() {
+        let mut decoder = AisDecoder::new();
+        match decoder.decode(&msg("!AIVDM,1,1,,B,15M67FC000G?ufbE`FepT@3n00Sa,0*5C")) {
+            Ok(Some(AisMessage::PositionReport(report))) => {
+                assert_eq!(report.msg_type, 1);
+                assert_eq!(report.mmsi, 366053209);
+                assert_eq!(report.speed, Some(0.0));
+                assert!((report.longitude.unwrap() + 122.341618).abs() < 1e-6);
+                assert!((report.latitude.unwrap() - 37.802118).abs() < 1e-6);
+                assert_eq!(report.course, Some(219.3));
+                assert_eq!(report.heading, Some(1));
+                assert_eq!(report.second, 59);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_multi_sentence() {
+        let mut decoder = AisDecoder::new();
+        let first =
+            msg("!AIVDM,2,1,1,A,55?MbV02;H;s<HtKR20EHE:0@T4@Dn2222222216L961O5Gf0NSQEp6ClRp8,0*1C");
+        let second = msg("!AIVDM,2,2,1,A,88888888880,2*25");
+        assert_eq!(decoder.decode(&first), Ok(None));
+        match decoder.decode(&second) {
+            Ok(Some(AisMessage::StaticAndVoyage(report))) => {
+                assert_eq!(report.mmsi, 351759000);
+                assert_eq!(report.imo, 9134270);
+                assert_eq!(report.callsign, "3FOF8");
+                assert_eq!(report.shipname, "EVER DIADEM");
+                assert_eq!(report.shiptype, 70);
+                assert_eq!(report.to_bow, 225);
+                assert_eq!(report.draught, 12.2);
+                assert_eq!(report.destination, "NEW YORK");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        // a fragment without its predecessor
+        assert!(decoder.decode(&second).is_err());
+    }
+
+    #[test]
+    fn test_test_data() {
+        let content = std::fs::read_to_string("./test_data/nmea0183_1000.log").unwrap();
+        let mut decoder = AisDecoder::new();
+        let mut decoded = 0;
+        for line in content.lines().filter(|line| line.starts_with("!AIVD")) {
+            if decoder.decode(&msg(line)).unwrap().is_some() {
+                decoded += 1;
+            }
+        }
+        assert_eq!(decoded, 745 + 45);
+    }
+}
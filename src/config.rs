@@ -0,0 +1,658 @@
+use crate::endpoint::Endpoint;
+use crate::pipeline::{Pipeline, Stage};
+use crate::recorder::{RecorderConfig, Timestamp};
+use crate::serial::SerialConfig;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use toml::{Table, Value};
+
+/// Which sentences a sink, the recorder or a server receives: those of the listed
+/// sources, all if empty, passed through the stages of the listed filters in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Route {
+    pub sources: Vec<String>,
+    pub filters: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceConfig {
+    pub endpoint: Endpoint,
+    /// See [`crate::Nmea0183Codec::with_lenient`].
+    pub lenient: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SinkConfig {
+    pub endpoint: Endpoint,
+    pub route: Route,
+    /// See [`crate::rate_limit::RateLimit::for_serial`].
+    pub limit: Option<SerialConfig>,
+    pub priority: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordConfig {
+    pub recorder: RecorderConfig,
+    pub route: Route,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerKind {
+    /// [`crate::tcp::TcpServer`]
+    Tcp,
+    /// [`crate::websocket::WebSocketServer`]
+    WebSocket,
+    /// [`crate::signalk::SignalKServer::bind_tcp`]
+    SignalK,
+    /// [`crate::signalk::SignalKServer::bind_websocket`]
+    SignalKWebSocket,
+    /// [`crate::gpsd::GpsdServer`] with the device name.
+    Gpsd(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerConfig {
+    pub kind: ServerKind,
+    /// `host:port`, `:port` listens on all interfaces.
+    pub address: String,
+    pub route: Route,
+}
+
+/// Configuration of the binary as TOML file, see [`Config::from_str`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub sources: BTreeMap<String, SourceConfig>,
+    pub sinks: BTreeMap<String, SinkConfig>,
+    pub filters: BTreeMap<String, Vec<Stage>>,
+    pub recorder: Option<RecordConfig>,
+    pub servers: BTreeMap<String, ServerConfig>,
+}
+
+impl Config {
+    /// Reads and validates a file, see [`Config::from_str`].
+    pub async fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        tokio::fs::read_to_string(path)
+            .await?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// The stages of the filters of `route`.
+    pub fn pipeline(&self, route: &Route) -> Pipeline {
+        route
+            .filters
+            .iter()
+            .filter_map(|name| self.filters.get(name))
+            .flatten()
+            .fold(Pipeline::new(), |pipeline, stage| {
+                pipeline.with(stage.clone())
+            })
+    }
+
+    fn check_route(&self, path: &str, route: &Route) -> Result<(), String> {
+        for (idx, source) in route.sources.iter().enumerate() {
+            if !self.sources.contains_key(source) {
+                return Err(format!(
+                    "{}.sources[{}]: Unknown source '{}'",
+                    path, idx, source
+                ));
+            }
+        }
+        for (idx, filter) in route.filters.iter().enumerate() {
+            if !self.filters.contains_key(filter) {
+                return Err(format!(
+                    "{}.filters[{}]: Unknown filter '{}'",
+                    path, idx, filter
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses and validates a configuration, errors start with the offending key, e.g.
+/// `sources.gps.baud: Invalid baud rate 'fast'`. All sections are optional apart from
+/// at least one source:
+///
+/// ```toml
+/// [sources.gps]
+/// type = "serial"          # serial, tcp, udp or file
+/// path = "/dev/ttyUSB0"    # serial and file
+/// baud = 4800              # or "auto", default 4800
+/// data_bits = 8
+/// parity = "none"          # none, odd or even
+/// stop_bits = 1
+/// lenient = true           # accept sentences of non-conforming instruments
+///
+/// [sources.ais]
+/// type = "tcp"
+/// address = "192.168.1.20:10110"   # tcp and udp
+///
+/// [filters.nav]
+/// stages = ["drop sentence=GSV", "throttle interval=1 sentence=RMC"]
+///
+/// [sinks.plotter]
+/// type = "serial"
+/// path = "/dev/ttyUSB1"
+/// sources = ["gps", "ais"] # default all
+/// filters = ["nav"]
+/// limit = "4800,8N1"       # drop sentences above the bandwidth of the link
+/// priority = ["RMC", "GGA"]
+///
+/// [recorder]
+/// dir = "/var/log/nmea"
+/// prefix = "nmea"
+/// timestamp = "tag-block"  # tag-block, prefix or none
+/// max_size = 10000000      # bytes
+/// max_age = 3600           # seconds
+/// gzip = true
+///
+/// [servers.opencpn]
+/// type = "tcp"             # tcp, websocket, signalk, signalk-websocket or gpsd
+/// address = ":10110"
+/// ```
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let table = s.parse::<Table>().map_err(|error| error.to_string())?;
+        let mut root = Section::new(String::new(), &table);
+        let mut config = Config::default();
+        for (name, mut section) in root.sections("sources")? {
+            let source = parse_source(&mut section)?;
+            section.finish()?;
+            config.sources.insert(name, source);
+        }
+        for (name, mut section) in root.sections("filters")? {
+            let path = section.key("stages");
+            let stages = section
+                .strings("stages")?
+                .iter()
+                .enumerate()
+                .map(|(idx, stage)| {
+                    stage
+                        .parse()
+                        .map_err(|error| format!("{}[{}]: {}", path, idx, error))
+                })
+                .collect::<Result<_, _>>()?;
+            section.finish()?;
+            config.filters.insert(name, stages);
+        }
+        for (name, mut section) in root.sections("sinks")? {
+            let sink = parse_sink(&mut section)?;
+            section.finish()?;
+            config.check_route(&section.path, &sink.route)?;
+            config.sinks.insert(name, sink);
+        }
+        if let Some(mut section) = root.section("recorder")? {
+            let recorder = parse_recorder(&mut section)?;
+            section.finish()?;
+            config.check_route(&section.path, &recorder.route)?;
+            config.recorder = Some(recorder);
+        }
+        for (name, mut section) in root.sections("servers")? {
+            let server = parse_server(&mut section, &name)?;
+            section.finish()?;
+            config.check_route(&section.path, &server.route)?;
+            config.servers.insert(name, server);
+        }
+        root.finish()?;
+        if config.sources.is_empty() {
+            return Err("sources: At least one source is required".to_string());
+        }
+        Ok(config)
+    }
+}
+
+fn parse_source(section: &mut Section<'_>) -> Result<SourceConfig, String> {
+    Ok(SourceConfig {
+        endpoint: parse_endpoint(section, true)?,
+        lenient: section.bool("lenient")?.unwrap_or(false),
+    })
+}
+
+fn parse_sink(section: &mut Section<'_>) -> Result<SinkConfig, String> {
+    let endpoint = parse_endpoint(section, false)?;
+    let limit = match section.value("limit") {
+        Some(Value::Integer(baud_rate)) => {
+            Some(SerialConfig::new(u32::try_from(*baud_rate).map_err(
+                |_| section.error("limit", &format!("Invalid baud rate '{}'", baud_rate)),
+            )?))
+        }
+        Some(Value::String(limit)) => Some(
+            limit
+                .parse::<SerialConfig>()
+                .map_err(|error| section.error("limit", &error))?,
+        ),
+        Some(_) => {
+            return Err(section.error("limit", "Expected a baud rate or a string like '4800,8N1'"))
+        }
+        None => None,
+    };
+    Ok(SinkConfig {
+        endpoint,
+        route: parse_route(section)?,
+        limit,
+        priority: section.strings("priority")?,
+    })
+}
+
+fn parse_endpoint(section: &mut Section<'_>, input: bool) -> Result<Endpoint, String> {
+    Ok(match section.required_str("type")? {
+        "serial" => {
+            let path = section.required_str("path")?.to_string();
+            let config = match section.value("baud") {
+                Some(Value::String(auto)) if auto == "auto" => {
+                    if !input {
+                        return Err(
+                            section.error("baud", "Autodetection is not supported for output")
+                        );
+                    }
+                    None
+                }
+                Some(Value::Integer(baud_rate)) => {
+                    Some(SerialConfig::new(u32::try_from(*baud_rate).map_err(
+                        |_| section.error("baud", &format!("Invalid baud rate '{}'", baud_rate)),
+                    )?))
+                }
+                Some(_) => return Err(section.error("baud", "Expected a baud rate or 'auto'")),
+                None => Some(SerialConfig::default()),
+            };
+            let data_bits = match section.integer("data_bits")? {
+                Some(data_bits @ 5..=8) => data_bits,
+                Some(data_bits) => {
+                    return Err(
+                        section.error("data_bits", &format!("Invalid data bits '{}'", data_bits))
+                    )
+                }
+                None => 8,
+            };
+            let parity = match section.str("parity")? {
+                Some("none") | None => 'N',
+                Some("odd") => 'O',
+                Some("even") => 'E',
+                Some(parity) => {
+                    return Err(section.error("parity", &format!("Invalid parity '{}'", parity)))
+                }
+            };
+            let stop_bits = match section.integer("stop_bits")? {
+                Some(stop_bits @ 1..=2) => stop_bits,
+                Some(stop_bits) => {
+                    return Err(
+                        section.error("stop_bits", &format!("Invalid stop bits '{}'", stop_bits))
+                    )
+                }
+                None => 1,
+            };
+            let format = format!("{}{}{}", data_bits, parity, stop_bits);
+            if config.is_none() && format != "8N1" {
+                return Err(section.error("baud", "Line settings are autodetected with 'auto'"));
+            }
+            let config = config
+                .map(|config| config.with_format(&format))
+                .transpose()
+                .map_err(|error| section.error("type", &error))?;
+            Endpoint::Serial(path, config)
+        }
+        "tcp" => Endpoint::Tcp(section.required_str("address")?.to_string()),
+        "udp" => Endpoint::Udp(section.required_str("address")?.to_string()),
+        "file" => Endpoint::File(PathBuf::from(section.required_str("path")?)),
+        kind => return Err(section.error("type", &format!("Unsupported type '{}'", kind))),
+    })
+}
+
+fn parse_route(section: &mut Section<'_>) -> Result<Route, String> {
+    Ok(Route {
+        sources: section.strings("sources")?,
+        filters: section.strings("filters")?,
+    })
+}
+
+fn parse_recorder(section: &mut Section<'_>) -> Result<RecordConfig, String> {
+    let mut recorder = RecorderConfig::new(section.required_str("dir")?);
+    if let Some(prefix) = section.str("prefix")? {
+        recorder.prefix = prefix.to_string();
+    }
+    let separator = section.str("separator")?.unwrap_or(" ").to_string();
+    recorder.timestamp = match section.str("timestamp")? {
+        Some("tag-block") | None => Timestamp::TagBlock,
+        Some("prefix") => Timestamp::Prefix(separator),
+        Some("none") => Timestamp::None,
+        Some(timestamp) => {
+            return Err(section.error("timestamp", &format!("Invalid timestamp '{}'", timestamp)))
+        }
+    };
+    recorder.max_size = section.positive("max_size")?;
+    recorder.max_age = section.positive("max_age")?.map(Duration::from_secs);
+    recorder.gzip = section.bool("gzip")?.unwrap_or(false);
+    Ok(RecordConfig {
+        recorder,
+        route: parse_route(section)?,
+    })
+}
+
+fn parse_server(section: &mut Section<'_>, name: &str) -> Result<ServerConfig, String> {
+    let kind = match section.required_str("type")? {
+        "tcp" => ServerKind::Tcp,
+        "websocket" => ServerKind::WebSocket,
+        "signalk" => ServerKind::SignalK,
+        "signalk-websocket" => ServerKind::SignalKWebSocket,
+        "gpsd" => ServerKind::Gpsd(section.str("device")?.unwrap_or(name).to_string()),
+        kind => return Err(section.error("type", &format!("Unsupported type '{}'", kind))),
+    };
+    Ok(ServerConfig {
+        kind,
+        address: section.required_str("address")?.to_string(),
+        route: parse_route(section)?,
+    })
+}
+
+/// A table of the configuration that keeps track of the keys read to report unknown ones.
+struct Section<'a> {
+    path: String,
+    table: &'a Table,
+    read: Vec<&'a str>,
+}
+
+impl<'a> Section<'a> {
+    fn new(path: String, table: &'a Table) -> Self {
+        Self {
+            path,
+            table,
+            read: Vec::new(),
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        if self.path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", self.path, key)
+        }
+    }
+
+    fn error(&self, key: &str, message: &str) -> String {
+        format!("{}: {}", self.key(key), message)
+    }
+
+    fn value(&mut self, key: &'a str) -> Option<&'a Value> {
+        self.read.push(key);
+        self.table.get(key)
+    }
+
+    fn str(&mut self, key: &'a str) -> Result<Option<&'a str>, String> {
+        match self.value(key) {
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(_) => Err(self.error(key, "Expected a string")),
+            None => Ok(None),
+        }
+    }
+
+    fn required_str(&mut self, key: &'a str) -> Result<&'a str, String> {
+        self.str(key)?.ok_or_else(|| self.error(key, "Missing key"))
+    }
+
+    fn integer(&mut self, key: &'a str) -> Result<Option<i64>, String> {
+        match self.value(key) {
+            Some(Value::Integer(value)) => Ok(Some(*value)),
+            Some(_) => Err(self.error(key, "Expected an integer")),
+            None => Ok(None),
+        }
+    }
+
+    fn positive(&mut self, key: &'a str) -> Result<Option<u64>, String> {
+        match self.integer(key)? {
+            Some(value) if value > 0 => Ok(Some(value as u64)),
+            Some(value) => {
+                Err(self.error(key, &format!("Expected a positive integer, got {}", value)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn bool(&mut self, key: &'a str) -> Result<Option<bool>, String> {
+        match self.value(key) {
+            Some(Value::Boolean(value)) => Ok(Some(*value)),
+            Some(_) => Err(self.error(key, "Expected true or false")),
+            None => Ok(None),
+        }
+    }
+
+    /// An array of strings, empty if missing.
+    fn strings(&mut self, key: &'a str) -> Result<Vec<String>, String> {
+        match self.value(key) {
+            Some(Value::Array(values)) => values
+                .iter()
+                .enumerate()
+                .map(|(idx, value)| match value {
+                    Value::String(value) => Ok(value.clone()),
+                    _ => Err(format!("{}[{}]: Expected a string", self.key(key), idx)),
+                })
+                .collect(),
+            Some(_) => Err(self.error(key, "Expected an array of strings")),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn section(&mut self, key: &'a str) -> Result<Option<Section<'a>>, String> {
+        match self.value(key) {
+            Some(Value::Table(table)) => Ok(Some(Section::new(self.key(key), table))),
+            Some(_) => Err(self.error(key, "Expected a table")),
+            None => Ok(None),
+        }
+    }
+
+    /// The tables of a table of named tables, e.g. `[sources.gps]`.
+    fn sections(&mut self, key: &'a str) -> Result<Vec<(String, Section<'a>)>, String> {
+        let Some(section) = self.section(key)? else {
+            return Ok(Vec::new());
+        };
+        section
+            .table
+            .iter()
+            .map(|(name, value)| match value {
+                Value::Table(table) => Ok((name.clone(), Section::new(section.key(name), table))),
+                _ => Err(section.error(name, "Expected a table")),
+            })
+            .collect()
+    }
+
+    /// Fails on the first key that was not read.
+    fn finish(&self) -> Result<(), String> {
+        match self
+            .table
+            .keys()
+            .find(|key| !self.read.contains(&key.as_str()))
+        {
+            Some(key) => Err(self.error(key, "Unknown key")),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[sources.gps]
+type = "serial"
+path = "/dev/ttyUSB0"
+baud = 38400
+parity = "even"
+stop_bits = 2
+data_bits = 7
+lenient = true
+
+[sources.ais]
+type = "tcp"
+address = "192.168.1.20:10110"
+
+[sources.auto]
+type = "serial"
+path = "/dev/ttyS0"
+baud = "auto"
+
+[filters.nav]
+stages = ["drop sentence=GSV", "throttle interval=1 sentence=RMC"]
+
+[sinks.plotter]
+type = "serial"
+path = "/dev/ttyUSB1"
+sources = ["gps", "ais"]
+filters = ["nav"]
+limit = 4800
+priority = ["RMC", "GGA"]
+
+[sinks.log]
+type = "file"
+path = "out.log"
+
+[recorder]
+dir = "/var/log/nmea"
+timestamp = "prefix"
+max_age = 3600
+gzip = true
+sources = ["gps"]
+
+[servers.opencpn]
+type = "tcp"
+address = ":10110"
+
+[servers.chrony]
+type = "gpsd"
+address = "127.0.0.1:2947"
+sources = ["gps"]
+"#;
+
+    #[test]
+    fn test_parse() {
+        let config: Config = CONFIG.parse().unwrap();
+        assert_eq!(
+            config.sources["gps"],
+            SourceConfig {
+                endpoint: Endpoint::Serial(
+                    "/dev/ttyUSB0".to_string(),
+                    Some(SerialConfig::new(38400).with_format("7E2").unwrap())
+                ),
+                lenient: true,
+            }
+        );
+        assert_eq!(
+            config.sources["ais"].endpoint,
+            Endpoint::Tcp("192.168.1.20:10110".to_string())
+        );
+        assert_eq!(
+            config.sources["auto"].endpoint,
+            Endpoint::Serial("/dev/ttyS0".to_string(), None)
+        );
+        let plotter = &config.sinks["plotter"];
+        assert_eq!(
+            plotter.endpoint,
+            Endpoint::Serial("/dev/ttyUSB1".to_string(), Some(SerialConfig::new(4800)))
+        );
+        assert_eq!(plotter.route.sources, ["gps", "ais"]);
+        assert_eq!(plotter.limit, Some(SerialConfig::new(4800)));
+        assert_eq!(plotter.priority, ["RMC", "GGA"]);
+        assert_eq!(config.pipeline(&plotter.route).stages().len(), 2);
+        assert_eq!(config.sinks["log"].route, Route::default());
+
+        let recorder = config.recorder.as_ref().unwrap();
+        assert_eq!(
+            recorder.recorder.timestamp,
+            Timestamp::Prefix(" ".to_string())
+        );
+        assert_eq!(recorder.recorder.max_age, Some(Duration::from_secs(3600)));
+        assert_eq!(recorder.recorder.max_size, None);
+        assert!(recorder.recorder.gzip);
+        assert_eq!(config.servers["opencpn"].kind, ServerKind::Tcp);
+        assert_eq!(
+            config.servers["chrony"].kind,
+            ServerKind::Gpsd("chrony".to_string())
+        );
+        assert_eq!(CONFIG.parse::<Config>().unwrap(), config);
+    }
+
+    #[test]
+    fn test_errors() {
+        for (replace, with, error) in [
+            (
+                "baud = 38400",
+                "baud = 'fast'",
+                "sources.gps.baud: Expected a baud rate or 'auto'",
+            ),
+            (
+                "parity = \"even\"",
+                "parity = 'mark'",
+                "sources.gps.parity: Invalid parity 'mark'",
+            ),
+            (
+                "lenient = true",
+                "lenient = 1",
+                "sources.gps.lenient: Expected true or false",
+            ),
+            (
+                "address = \"192",
+                "adress = \"192",
+                "sources.ais.address: Missing key",
+            ),
+            (
+                "type = \"tcp\"\naddress = \"192",
+                "type = \"tcp\"\nport = 1\naddress = \"192",
+                "sources.ais.port: Unknown key",
+            ),
+            (
+                "\"ais\"]",
+                "\"nmea\"]",
+                "sinks.plotter.sources[1]: Unknown source 'nmea'",
+            ),
+            (
+                "filters = [\"nav\"]",
+                "filters = [\"gsv\"]",
+                "sinks.plotter.filters[0]: Unknown filter 'gsv'",
+            ),
+            (
+                "drop sentence=GSV",
+                "remove sentence=GSV",
+                "filters.nav.stages[0]: Invalid stage 'remove'",
+            ),
+            (
+                "limit = 4800",
+                "limit = '4800,9N1'",
+                "sinks.plotter.limit: Invalid data bits in format '9N1'",
+            ),
+            (
+                "path = \"/dev/ttyUSB1\"",
+                "path = \"/dev/ttyUSB1\"\nbaud = 'auto'",
+                "sinks.plotter.baud: Autodetection is not supported for output",
+            ),
+            (
+                "type = \"file\"",
+                "type = \"pipe\"",
+                "sinks.log.type: Unsupported type 'pipe'",
+            ),
+            (
+                "max_age = 3600",
+                "max_age = 0",
+                "recorder.max_age: Expected a positive integer, got 0",
+            ),
+            (
+                "[servers.opencpn]",
+                "[server.opencpn]",
+                "server: Unknown key",
+            ),
+        ] {
+            assert!(CONFIG.contains(replace), "{}", replace);
+            let result = CONFIG.replacen(replace, with, 1).parse::<Config>();
+            assert_eq!(result.unwrap_err(), error);
+        }
+        assert_eq!(
+            "".parse::<Config>().unwrap_err(),
+            "sources: At least one source is required"
+        );
+        assert!("[sources".parse::<Config>().unwrap_err().contains("line 1"));
+    }
+}
@@ -0,0 +1,242 @@
+use log::kv::{Error, Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write as _;
+use std::time::Duration;
+
+/// Writes log records to stderr, one line per record. Plain lines are the message
+/// followed by the key-values of the record, e.g. `Connection refused source=ais`.
+/// Structured lines are logfmt, e.g.
+/// `level=warn target=nmea0183_feed::service msg="Connection refused" source=ais`.
+///
+/// Lines get a syslog priority prefix like `<4>` when stderr is connected to the
+/// journal, so systemd keeps the level.
+pub struct Logger {
+    level: LevelFilter,
+    structured: bool,
+    journal: bool,
+}
+
+impl Logger {
+    pub fn new(level: LevelFilter) -> Self {
+        Self {
+            level,
+            structured: false,
+            journal: env::var_os("JOURNAL_STREAM").is_some(),
+        }
+    }
+
+    pub fn with_structured(mut self, structured: bool) -> Self {
+        self.structured = structured;
+        self
+    }
+
+    /// Overrides the detection by `$JOURNAL_STREAM`.
+    pub fn with_journal(mut self, journal: bool) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Installs the logger for the `log` macros, fails if one is already installed.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+
+    /// The line of `record` without line end.
+    pub fn format(&self, record: &Record<'_>) -> String {
+        let mut line = String::new();
+        if self.journal {
+            let priority = match record.level() {
+                Level::Error => 3,
+                Level::Warn => 4,
+                Level::Info => 6,
+                Level::Debug | Level::Trace => 7,
+            };
+            let _ = write!(line, "<{}>", priority);
+        }
+        if self.structured {
+            let _ = write!(
+                line,
+                "level={} target={} msg={}",
+                record.level().as_str().to_lowercase(),
+                logfmt_value(record.target()),
+                logfmt_value(&record.args().to_string())
+            );
+        } else {
+            let _ = write!(line, "{}", record.args());
+        }
+        let _ = record.key_values().visit(&mut KeyValues(&mut line));
+        line
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(io::stderr().lock(), "{}", self.format(record));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+struct KeyValues<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for KeyValues<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        write!(self.0, " {}={}", key, logfmt_value(&value.to_string()))?;
+        Ok(())
+    }
+}
+
+/// `value` in double quotes if it is empty or contains spaces, quotes, `=` or control
+/// characters.
+fn logfmt_value(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| !c.is_whitespace() && !c.is_control() && c != '"' && c != '=');
+    if plain {
+        return value.to_string();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{{{:x}}}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Sends a state like `READY=1` or `WATCHDOG=1` to the service manager, see
+/// sd_notify(3). Returns `false` if `$NOTIFY_SOCKET` is not set, i.e. the process was
+/// not started by systemd with `Type=notify`.
+pub fn notify(state: &str) -> io::Result<bool> {
+    match env::var_os("NOTIFY_SOCKET") {
+        Some(socket) => notify_socket(&socket, state).map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// Sends `state` to the datagram socket at `path`, a leading `@` names an abstract
+/// socket.
+#[cfg(unix)]
+pub fn notify_socket(path: &OsStr, state: &str) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    match path.as_bytes().strip_prefix(b"@") {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn notify_socket(_path: &OsStr, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Notification sockets are not supported",
+    ))
+}
+
+/// How often to send `WATCHDOG=1`, half the timeout of `$WATCHDOG_USEC`. `None` if the
+/// watchdog is disabled or meant for another process.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let logger = Logger::new(LevelFilter::Info).with_journal(false);
+        let format = |logger: &Logger| {
+            logger.format(
+                &Record::builder()
+                    .args(format_args!("Connection refused"))
+                    .level(Level::Warn)
+                    .target("nmea0183_feed::service")
+                    .key_values(&[("source", "ais"), ("address", "tcp host:10110")])
+                    .build(),
+            )
+        };
+        assert_eq!(
+            format(&logger),
+            r#"Connection refused source=ais address="tcp host:10110""#
+        );
+        let logger = logger.with_structured(true);
+        assert_eq!(
+            format(&logger),
+            r#"level=warn target=nmea0183_feed::service msg="Connection refused" source=ais address="tcp host:10110""#
+        );
+        assert!(format(&logger.with_journal(true)).starts_with("<4>level=warn "));
+        assert_eq!(logfmt_value(""), r#""""#);
+        assert_eq!(logfmt_value("a=\"b\"\n"), r#""a=\"b\"\n""#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_notify() {
+        use std::os::unix::net::UnixDatagram;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        notify_socket(path.as_os_str(), "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let len = socket.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let name = format!("nmea0183_feed-{}", std::process::id());
+            let addr = SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+            let socket = UnixDatagram::bind_addr(&addr).unwrap();
+            notify_socket(OsStr::new(&format!("@{}", name)), "WATCHDOG=1").unwrap();
+            let len = socket.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..len], b"WATCHDOG=1");
+        }
+
+        assert!(notify_socket(dir.path().join("missing").as_os_str(), "READY=1").is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::sentence::{iso8601, Gga, Gsv, GsvSatellite, Rmc};
+use crate::stats::Stats;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::time::Duration;
+
+const RAW_LINES: usize = 200;
+const REFRESH: Duration = Duration::from_millis(250);
+
+enum RawLine {
+    Valid(String),
+    InvalidChecksum(String),
+    Error(String),
+}
+
+/// Live view of the bus: fix, satellites, sentence counters and the raw sentences.
+#[derive(Default)]
+pub struct Dashboard {
+    rmc: Option<Rmc>,
+    gga: Option<Gga>,
+    /// Complete GSV groups per talker.
+    satellites: BTreeMap<String, Vec<GsvSatellite>>,
+    pending: BTreeMap<String, Vec<GsvSatellite>>,
+    stats: Stats,
+    raw: VecDeque<RawLine>,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, result: &Result<Nmea0183Msg, io::Error>) {
+        self.stats.update(result);
+        let raw = match result {
+            Ok(msg) if msg.chksum_valid() == Some(false) => {
+                RawLine::InvalidChecksum(msg.to_string())
+            }
+            Ok(msg) => RawLine::Valid(msg.to_string()),
+            Err(error) => RawLine::Error(error.to_string()),
+        };
+        if self.raw.len() == RAW_LINES {
+            self.raw.pop_front();
+        }
+        self.raw.push_back(raw);
+
+        let msg = match result {
+            Ok(msg) if msg.chksum_valid() != Some(false) => msg,
+            _ => return,
+        };
+        match msg.msgtype() {
+            "RMC" => self.rmc = Rmc::try_from(msg).ok().or(self.rmc.take()),
+            "GGA" => self.gga = Gga::try_from(msg).ok().or(self.gga.take()),
+            "GSV" => {
+                if let Ok(gsv) = Gsv::try_from(msg) {
+                    let pending = self.pending.entry(msg.talker().to_string()).or_default();
+                    if gsv.message_number == Some(1) {
+                        pending.clear();
+                    }
+                    pending.extend(gsv.satellites.iter().cloned());
+                    if gsv.is_last() {
+                        let satellites = std::mem::take(pending);
+                        self.satellites.insert(msg.talker().to_string(), satellites);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn render(&self, frame: &mut Frame<'_>) {
+        let [top, counters, raw] = Layout::vertical([
+            Constraint::Length(12),
+            Constraint::Min(6),
+            Constraint::Percentage(40),
+        ])
+        .areas(frame.area());
+        let [fix, satellites] =
+            Layout::horizontal([Constraint::Length(40), Constraint::Min(30)]).areas(top);
+        let [sentences, talkers] =
+            Layout::horizontal([Constraint::Min(40), Constraint::Length(34)]).areas(counters);
+        self.render_fix(frame, fix);
+        self.render_satellites(frame, satellites);
+        self.render_sentences(frame, sentences);
+        self.render_talkers(frame, talkers);
+        self.render_raw(frame, raw);
+    }
+
+    fn render_fix(&self, frame: &mut Frame<'_>, area: Rect) {
+        let value = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        let rmc = self.rmc.as_ref();
+        let gga = self.gga.as_ref();
+        let latitude = rmc
+            .and_then(|rmc| rmc.latitude)
+            .or(gga.and_then(|gga| gga.latitude));
+        let longitude = rmc
+            .and_then(|rmc| rmc.longitude)
+            .or(gga.and_then(|gga| gga.longitude));
+        let quality = gga.and_then(|gga| gga.quality).map(|quality| {
+            match quality {
+                0 => "invalid",
+                1 => "GPS",
+                2 => "DGPS",
+                4 => "RTK fixed",
+                5 => "RTK float",
+                6 => "estimated",
+                _ => "other",
+            }
+            .to_string()
+        });
+        let lines = vec![
+            format!(
+                "Time      {}",
+                value(rmc.and_then(Rmc::unix_time).map(iso8601))
+            ),
+            format!(
+                "Status    {}",
+                value(rmc.and_then(|rmc| rmc.status).map(String::from))
+            ),
+            format!(
+                "Latitude  {}",
+                value(latitude.map(|lat| format!("{:.6}", lat)))
+            ),
+            format!(
+                "Longitude {}",
+                value(longitude.map(|lon| format!("{:.6}", lon)))
+            ),
+            format!(
+                "SOG       {}",
+                value(
+                    rmc.and_then(|rmc| rmc.speed_knots)
+                        .map(|sog| format!("{:.1} kn", sog))
+                )
+            ),
+            format!(
+                "COG       {}",
+                value(
+                    rmc.and_then(|rmc| rmc.course)
+                        .map(|cog| format!("{:.1}°", cog))
+                )
+            ),
+            format!("Fix       {}", value(quality)),
+            format!(
+                "Sats      {}",
+                value(
+                    gga.and_then(|gga| gga.satellites)
+                        .map(|sats| sats.to_string())
+                )
+            ),
+            format!(
+                "HDOP      {}",
+                value(gga.and_then(|gga| gga.hdop).map(|hdop| hdop.to_string()))
+            ),
+            format!(
+                "Altitude  {}",
+                value(
+                    gga.and_then(|gga| gga.altitude)
+                        .map(|alt| format!("{} m", alt))
+                )
+            ),
+        ];
+        let lines = lines.into_iter().map(Line::from).collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Position")),
+            area,
+        );
+    }
+
+    fn render_satellites(&self, frame: &mut Frame<'_>, area: Rect) {
+        let field = |value: Option<String>| value.unwrap_or_default();
+        let rows = self.satellites.iter().flat_map(|(talker, satellites)| {
+            satellites.iter().map(move |satellite| {
+                let snr = satellite.snr.unwrap_or_default();
+                Row::new(vec![
+                    talker.clone(),
+                    field(satellite.prn.map(|prn| prn.to_string())),
+                    field(satellite.elevation.map(|elevation| elevation.to_string())),
+                    field(satellite.azimuth.map(|azimuth| azimuth.to_string())),
+                    field(satellite.snr.map(|snr| snr.to_string())),
+                    "|".repeat(usize::from(snr / 5)),
+                ])
+            })
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(4),
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Length(4),
+                Constraint::Min(10),
+            ],
+        )
+        .header(header(["talker", "prn", "elev", "azim", "snr", ""]))
+        .block(Block::bordered().title("Satellites"));
+        frame.render_widget(table, area);
+    }
+
+    fn render_sentences(&self, frame: &mut Frame<'_>, area: Rect) {
+        let rows = self.stats.sentences().map(|(id, counter)| {
+            let row = Row::new(vec![
+                id.to_string(),
+                counter.count.to_string(),
+                counter.invalid_checksum.to_string(),
+                format!("{:.2}", self.stats.rate(counter.count)),
+            ]);
+            if counter.invalid_checksum > 0 {
+                row.style(Style::default().fg(Color::Yellow))
+            } else {
+                row
+            }
+        });
+        let title = format!(
+            "Sentences - {} errors, {} invalid checksums, {:.2}% error rate",
+            self.stats.errors(),
+            self.stats.invalid_checksums(),
+            self.stats.error_rate() * 100.0
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header(["sentence", "count", "invalid", "rate/s"]))
+        .block(Block::bordered().title(title));
+        frame.render_widget(table, area);
+    }
+
+    fn render_talkers(&self, frame: &mut Frame<'_>, area: Rect) {
+        let rows = self.stats.talkers().map(|(id, counter)| {
+            Row::new(vec![
+                id.to_string(),
+                counter.count.to_string(),
+                format!("{:.2}", self.stats.rate(counter.count)),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header(["talker", "count", "rate/s"]))
+        .block(Block::bordered().title("Talkers"));
+        frame.render_widget(table, area);
+    }
+
+    fn render_raw(&self, frame: &mut Frame<'_>, area: Rect) {
+        // newest at the bottom
+        let height = usize::from(area.height.saturating_sub(2));
+        let lines = self
+            .raw
+            .iter()
+            .skip(self.raw.len().saturating_sub(height))
+            .map(|line| match line {
+                RawLine::Valid(line) => Line::raw(line.as_str()),
+                RawLine::InvalidChecksum(line) => {
+                    Line::styled(line.as_str(), Style::default().fg(Color::Red))
+                }
+                RawLine::Error(error) => Line::styled(
+                    format!("error: {}", error),
+                    Style::default().fg(Color::Magenta),
+                ),
+            })
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Raw (q to quit)")),
+            area,
+        );
+    }
+
+    /// Takes over the terminal and shows `stream` until it ends or `q` or Esc is pressed.
+    pub async fn run<S>(mut self, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut terminal = ratatui::init();
+        let result = self.run_terminal(&mut terminal, stream).await;
+        ratatui::restore();
+        result
+    }
+
+    async fn run_terminal<S>(&mut self, terminal: &mut DefaultTerminal, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        let mut ticker = tokio::time::interval(REFRESH);
+        let mut ended = false;
+        loop {
+            tokio::select! {
+                result = stream.next(), if !ended => match result {
+                    Some(result) => self.update(&result),
+                    // keep showing the final state
+                    None => ended = true,
+                },
+                _ = ticker.tick() => {
+                    terminal.draw(|frame| self.render(frame))?;
+                    while event::poll(Duration::ZERO)? {
+                        if let Event::Key(key) = event::read()? {
+                            if key.kind == KeyEventKind::Press
+                                && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                            {
+                                return Ok(());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn header<const N: usize>(titles: [&str; N]) -> Row<'_> {
+    Row::new(titles).style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_codec;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use tokio::fs::File;
+
+    const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
+
+    #[test]
+    fn test_dashboard() {
+        tokio_test::block_on(async {
+            let mut dashboard = Dashboard::new();
+            let mut stream = get_codec(File::open(TEST_FILE).await.unwrap());
+            while let Some(result) = stream.next().await {
+                dashboard.update(&result);
+            }
+            dashboard.update(&Ok(concat!("$GPZDA,160012.71,11,03,2004,-1,00*", "00")
+                .parse()
+                .unwrap()));
+            assert!(dashboard.rmc.is_some());
+            assert!(dashboard.gga.is_some());
+            assert!(!dashboard.satellites["GP"].is_empty());
+            assert_eq!(dashboard.raw.len(), RAW_LINES);
+            assert_eq!(dashboard.stats().invalid_checksums(), 1);
+
+            let mut terminal = Terminal::new(TestBackend::new(120, 50)).unwrap();
+            terminal.draw(|frame| dashboard.render(frame)).unwrap();
+            let screen = terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>();
+            assert!(screen.contains("Latitude  8.9"));
+            assert!(screen.contains("GPRMC"));
+            assert!(screen.contains("$GPZDA,160012.71,11,03,2004,-1,00*00"));
+        })
+    }
+}
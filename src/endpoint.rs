@@ -0,0 +1,262 @@
+use crate::serial::{Autodetect, SerialConfig};
+use crate::tcp::{Backoff, TcpClient, TcpServer};
+use crate::udp::{UdpSink, UdpSource};
+use crate::{get_codec, Nmea0183Codec, Nmea0183Msg, ResumeOnError};
+use futures::{Sink, Stream};
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::str::FromStr;
+use tokio::fs::File;
+use tokio::net::{lookup_host, TcpStream};
+use tokio_util::codec::{Decoder, FramedWrite};
+
+pub type MsgStream = Pin<Box<dyn Stream<Item = Result<Nmea0183Msg, io::Error>> + Send>>;
+pub type MsgSink = Pin<Box<dyn Sink<Nmea0183Msg, Error = io::Error> + Send>>;
+
+/// Where sentences are read from or written to, written as URL:
+///
+/// - `serial:///dev/ttyUSB0?baud=38400&format=8N1`, `baud=auto` detects the settings
+/// - `tcp://host:10110` connects, `tcp://:10110` listens when used as output
+/// - `udp://:10110` binds when used as input, sends to the address when used as output
+/// - `file://log` or a plain path
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    /// `None` autodetects the line settings.
+    Serial(String, Option<SerialConfig>),
+    Tcp(String),
+    Udp(String),
+    File(PathBuf),
+}
+
+impl Endpoint {
+    pub async fn open_stream(&self) -> io::Result<MsgStream> {
+        self.open(false).await
+    }
+
+    /// Decodes with a lenient codec, see [`Nmea0183Codec::with_lenient`].
+    pub async fn open_lenient_stream(&self) -> io::Result<MsgStream> {
+        self.open(true).await
+    }
+
+    /// Decode errors are passed on, the stream continues after them.
+    async fn open(&self, lenient: bool) -> io::Result<MsgStream> {
+        let codec = Nmea0183Codec::new().with_lenient(lenient);
+        Ok(match self {
+            Endpoint::Serial(path, config) => {
+                let mut port = config.unwrap_or_default().open(path)?;
+                if config.is_none() {
+                    Autodetect::new().detect(&mut port).await?;
+                }
+                Box::pin(ResumeOnError::new(codec.framed(port)))
+            }
+            Endpoint::Tcp(addr) if lenient => {
+                Box::pin(TcpClient::connect_lenient(addr, Backoff::default()))
+            }
+            Endpoint::Tcp(addr) => Box::pin(TcpClient::connect(addr, Backoff::default())),
+            Endpoint::Udp(addr) => Box::pin(
+                UdpSource::bind(with_host(addr, "0.0.0.0"))
+                    .await?
+                    .with_lenient(lenient),
+            ),
+            Endpoint::File(path) => {
+                Box::pin(ResumeOnError::new(codec.framed(File::open(path).await?)))
+            }
+        })
+    }
+
+    pub async fn open_sink(&self) -> io::Result<MsgSink> {
+        Ok(match self {
+            Endpoint::Serial(path, config) => {
+                let config = config.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Autodetection is not supported for output",
+                    )
+                })?;
+                Box::pin(get_codec(config.open(path)?))
+            }
+            Endpoint::Tcp(addr) if addr.starts_with(':') => {
+                Box::pin(TcpServer::bind(with_host(addr, "0.0.0.0")).await?)
+            }
+            Endpoint::Tcp(addr) => Box::pin(FramedWrite::new(
+                TcpStream::connect(addr).await?,
+                Nmea0183Codec::default(),
+            )),
+            Endpoint::Udp(addr) => {
+                let target = resolve(addr).await?;
+                let local = if target.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                Box::pin(UdpSink::bind(local, target).await?)
+            }
+            Endpoint::File(path) => Box::pin(FramedWrite::new(
+                File::create(path).await?,
+                Nmea0183Codec::default(),
+            )),
+        })
+    }
+}
+
+pub(crate) fn with_host(addr: &str, host: &str) -> String {
+    if addr.starts_with(':') {
+        format!("{}{}", host, addr)
+    } else {
+        addr.to_string()
+    }
+}
+
+async fn resolve(addr: &str) -> io::Result<SocketAddr> {
+    lookup_host(addr).await?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Cannot resolve '{}'", addr),
+        )
+    })
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = match s.split_once("://") {
+            Some((scheme, rest)) => (scheme, rest),
+            None => ("file", s),
+        };
+        let (location, query) = match rest.split_once('?') {
+            Some((location, query)) => (location, Some(query)),
+            None => (rest, None),
+        };
+        if location.is_empty() {
+            return Err(format!("Missing address in '{}'", s));
+        }
+        if query.is_some() && scheme != "serial" {
+            return Err(format!("Unexpected query in '{}'", s));
+        }
+        match scheme {
+            "serial" => {
+                let mut config = Some(SerialConfig::default());
+                for pair in query.unwrap_or_default().split('&') {
+                    match pair.split_once('=').unwrap_or((pair, "")) {
+                        ("", _) => {}
+                        ("baud", "auto") => config = None,
+                        ("baud", baud) => {
+                            let baud_rate = baud
+                                .parse()
+                                .map_err(|_| format!("Invalid baud rate '{}'", baud))?;
+                            config = Some(SerialConfig {
+                                baud_rate,
+                                ..config.unwrap_or_default()
+                            });
+                        }
+                        ("format", format) => {
+                            config = Some(config.unwrap_or_default().with_format(format)?)
+                        }
+                        (key, _) => return Err(format!("Invalid parameter '{}'", key)),
+                    }
+                }
+                Ok(Endpoint::Serial(location.to_string(), config))
+            }
+            "tcp" => Ok(Endpoint::Tcp(location.to_string())),
+            "udp" => Ok(Endpoint::Udp(location.to_string())),
+            "file" => Ok(Endpoint::File(PathBuf::from(location))),
+            _ => Err(format!("Unsupported scheme '{}'", scheme)),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Serial(path, Some(config)) => {
+                let (baud_rate, format) = config
+                    .to_string()
+                    .split_once(',')
+                    .map(|(baud, format)| (baud.to_string(), format.to_string()))
+                    .unwrap_or_default();
+                write!(f, "serial://{}?baud={}&format={}", path, baud_rate, format)
+            }
+            Endpoint::Serial(path, None) => write!(f, "serial://{}?baud=auto", path),
+            Endpoint::Tcp(addr) => write!(f, "tcp://{}", addr),
+            Endpoint::Udp(addr) => write!(f, "udp://{}", addr),
+            Endpoint::File(path) => write!(f, "file://{}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+
+    #[test]
+    fn test_parse() {
+        let endpoint: Endpoint = "serial:///dev/ttyUSB0?baud=38400".parse().unwrap();
+        assert_eq!(
+            endpoint,
+            Endpoint::Serial("/dev/ttyUSB0".to_string(), Some(SerialConfig::new(38400)))
+        );
+        assert_eq!(
+            endpoint.to_string(),
+            "serial:///dev/ttyUSB0?baud=38400&format=8N1"
+        );
+        assert_eq!(
+            "serial://COM1?baud=auto".parse::<Endpoint>().unwrap(),
+            Endpoint::Serial("COM1".to_string(), None)
+        );
+        assert_eq!(
+            "tcp://host:10110".parse::<Endpoint>().unwrap(),
+            Endpoint::Tcp("host:10110".to_string())
+        );
+        assert_eq!(
+            "udp://:10110".parse::<Endpoint>().unwrap(),
+            Endpoint::Udp(":10110".to_string())
+        );
+        assert_eq!(
+            "file://log".parse::<Endpoint>().unwrap(),
+            Endpoint::File(PathBuf::from("log"))
+        );
+        assert_eq!(
+            "test_data/nmea0183_1000.log".parse::<Endpoint>().unwrap(),
+            Endpoint::File(PathBuf::from("test_data/nmea0183_1000.log"))
+        );
+        assert!("serial:///dev/ttyS0?baud=fast".parse::<Endpoint>().is_err());
+        assert!("http://host".parse::<Endpoint>().is_err());
+        assert!("tcp://".parse::<Endpoint>().is_err());
+    }
+
+    #[test]
+    fn test_file() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("out.log");
+            let endpoint = Endpoint::File(path.clone());
+            let mut sink = endpoint.open_sink().await.unwrap();
+            let msg: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*7D".parse().unwrap();
+            sink.send(msg.clone()).await.unwrap();
+            sink.close().await.unwrap();
+
+            let mut stream = endpoint.open_stream().await.unwrap();
+            assert_eq!(stream.next().await.unwrap().unwrap(), msg);
+            assert!(stream.next().await.is_none());
+
+            // decode errors do not end the stream
+            tokio::fs::write(&path, format!("{}\r\ngarbage\r\n{}\r\n", msg, msg))
+                .await
+                .unwrap();
+            let results = endpoint
+                .open_stream()
+                .await
+                .unwrap()
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(results.len(), 3);
+            assert!(results[1].is_err());
+            assert_eq!(results[2].as_ref().unwrap(), &msg);
+        })
+    }
+}
@@ -0,0 +1,208 @@
+use crate::sentence::{Rmc, Zda};
+use crate::Nmea0183Msg;
+use std::collections::VecDeque;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_WINDOW: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockSample {
+    /// Monotonic arrival instant of the sentence.
+    pub arrival: Instant,
+    /// GPS time in seconds since the unix epoch.
+    pub gps_time: f64,
+    /// GPS time minus system time at arrival, in seconds.
+    pub offset: f64,
+}
+
+/// Estimates the offset between GPS time and the system clock from ZDA and RMC sentences.
+/// The system clock is never modified.
+pub struct GpsClock {
+    ref_instant: Instant,
+    ref_system: f64,
+    window: usize,
+    samples: VecDeque<ClockSample>,
+    fix_valid: Option<bool>,
+    last_fix: Option<Instant>,
+}
+
+impl Default for GpsClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+impl GpsClock {
+    /// `window` is the number of samples used for the offset and jitter estimates.
+    pub fn new(window: usize) -> Self {
+        Self::with_reference(window, Instant::now(), SystemTime::now())
+    }
+
+    /// Uses the given pair of monotonic and system time to map arrival instants to system time.
+    pub fn with_reference(window: usize, instant: Instant, system: SystemTime) -> Self {
+        let ref_system = match system.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs_f64(),
+            Err(error) => -error.duration().as_secs_f64(),
+        };
+        Self {
+            ref_instant: instant,
+            ref_system,
+            window: window.max(1),
+            samples: VecDeque::new(),
+            fix_valid: None,
+            last_fix: None,
+        }
+    }
+
+    pub fn update(&mut self, msg: &Nmea0183Msg) -> Option<ClockSample> {
+        self.update_at(msg, Instant::now())
+    }
+
+    /// Feeds a sentence that arrived at `arrival`, returns the new sample if the sentence
+    /// carried a complete time and date for a new second. Sentences with an invalid
+    /// checksum are ignored.
+    pub fn update_at(&mut self, msg: &Nmea0183Msg, arrival: Instant) -> Option<ClockSample> {
+        if msg.chksum_valid() == Some(false) {
+            return None;
+        }
+        let gps_time = match msg.msgtype() {
+            "ZDA" => Zda::try_from(msg).ok()?.unix_time(),
+            "RMC" => {
+                let rmc = Rmc::try_from(msg).ok()?;
+                self.fix_valid = Some(rmc.is_valid());
+                self.last_fix = Some(arrival);
+                rmc.unix_time()
+            }
+            _ => None,
+        }?;
+
+        // receivers send several time sentences per second, only the first one of a
+        // burst is used as it has the least transmission delay
+        if let Some(last) = self.samples.back() {
+            if (last.gps_time - gps_time).abs() < 1e-3 {
+                return None;
+            }
+        }
+
+        let sample = ClockSample {
+            arrival,
+            gps_time,
+            offset: gps_time - self.system_time_at(arrival),
+        };
+        if self.samples.len() >= self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        Some(sample)
+    }
+
+    /// System time in seconds since the unix epoch at the given monotonic instant.
+    pub fn system_time_at(&self, instant: Instant) -> f64 {
+        if instant >= self.ref_instant {
+            self.ref_system + (instant - self.ref_instant).as_secs_f64()
+        } else {
+            self.ref_system - (self.ref_instant - instant).as_secs_f64()
+        }
+    }
+
+    pub fn last_sample(&self) -> Option<&ClockSample> {
+        self.samples.back()
+    }
+
+    /// Mean offset of GPS time relative to system time in seconds.
+    pub fn offset(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            Some(
+                self.samples.iter().map(|sample| sample.offset).sum::<f64>()
+                    / self.samples.len() as f64,
+            )
+        }
+    }
+
+    /// Standard deviation of the offsets in seconds, needs at least two samples.
+    pub fn jitter(&self) -> Option<f64> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+        let mean = self.offset()?;
+        let variance = self
+            .samples
+            .iter()
+            .map(|sample| (sample.offset - mean).powi(2))
+            .sum::<f64>()
+            / (self.samples.len() - 1) as f64;
+        Some(variance.sqrt())
+    }
+
+    /// `true` if the last RMC reported status `A`.
+    pub fn is_trustworthy(&self) -> bool {
+        self.fix_valid == Some(true)
+    }
+
+    /// Monotonic arrival instant of the last RMC.
+    pub fn last_fix(&self) -> Option<Instant> {
+        self.last_fix
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_offset() {
+        let start = Instant::now();
+        // 2004-03-11 16:00:12 UTC, system clock 0.5 s behind GPS
+        let system = UNIX_EPOCH + Duration::from_millis(1_079_020_811_500);
+        let mut clock = GpsClock::with_reference(4, start, system);
+
+        let sample = clock
+            .update_at(&msg("$GPZDA,160012.00,11,03,2004,00,00*67"), start)
+            .expect("no sample");
+        assert!((sample.offset - 0.5).abs() < 1e-6);
+
+        // same second again is ignored
+        assert!(clock
+            .update_at(&msg("$GPZDA,160012.00,11,03,2004,00,00*67"), start)
+            .is_none());
+        // corrupted sentences are ignored
+        assert!(clock
+            .update_at(&msg("$GPZDA,160013.00,11,03,2004,00,00*67"), start)
+            .is_none());
+
+        clock
+            .update_at(
+                &msg("$GPZDA,160013.00,11,03,2004,00,00*66"),
+                start + Duration::from_millis(1100),
+            )
+            .expect("no sample");
+        assert!((clock.offset().unwrap() - 0.45).abs() < 1e-6);
+        assert!((clock.jitter().unwrap() - 0.0707107).abs() < 1e-6);
+        assert!(!clock.is_trustworthy());
+    }
+
+    #[test]
+    fn test_rmc_status() {
+        let mut clock = GpsClock::default();
+        clock.update(&msg(
+            "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E",
+        ));
+        assert!(clock.is_trustworthy());
+        assert!(clock.last_sample().is_some());
+        clock.update(&msg(
+            "$GPRMC,184907.000,V,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,N*67",
+        ));
+        assert!(!clock.is_trustworthy());
+        clock.update(&msg(
+            "$GPRMC,184908.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E",
+        ));
+        assert!(!clock.is_trustworthy());
+    }
+}
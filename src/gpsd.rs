@@ -0,0 +1,692 @@
+use crate::ais::{AisDecoder, AisMessage, StaticDataReport};
+use crate::json::{array, parse_object, quote, JsonObject, JsonValue};
+use crate::sentence::{iso8601, Gga, Gsa, Gsv, GsvSatellite, Rmc, Zda};
+use crate::tcp::accept_while;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Port of the gpsd protocol.
+pub const GPSD_PORT: u16 = 2947;
+
+const PROTO_MAJOR: i64 = 3;
+const PROTO_MINOR: i64 = 14;
+const RELEASE: &str = "3.25";
+const SERVER_QUEUE_SIZE: usize = 1024;
+// clients sending longer commands are disconnected
+const MAX_COMMAND_SIZE: usize = 4096;
+const KNOTS_TO_MPS: f64 = 1852.0 / 3600.0;
+
+/// Turns sentences into gpsd JSON reports: TPV on RMC (or GGA if the receiver sends no
+/// RMC), SKY on the last GSV of a cycle and AIS on every complete AIVDM/AIVDO.
+pub struct GpsdReporter {
+    device: String,
+    rmc_seen: bool,
+    time: Option<f64>,
+    date_time: Option<f64>,
+    status_valid: bool,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    altitude: Option<f64>,
+    geoid_separation: Option<f64>,
+    speed: Option<f64>,
+    track: Option<f64>,
+    magnetic_variation: Option<f64>,
+    gsa: Option<Gsa>,
+    gsv: Vec<GsvSatellite>,
+    ais: AisDecoder,
+}
+
+impl GpsdReporter {
+    /// `device` is reported as the device path, e.g. `/dev/ttyUSB0` or `tcp://host:10110`.
+    pub fn new(device: &str) -> Self {
+        Self {
+            device: device.to_string(),
+            rmc_seen: false,
+            time: None,
+            date_time: None,
+            status_valid: false,
+            latitude: None,
+            longitude: None,
+            altitude: None,
+            geoid_separation: None,
+            speed: None,
+            track: None,
+            magnetic_variation: None,
+            gsa: None,
+            gsv: Vec::new(),
+            ais: AisDecoder::new(),
+        }
+    }
+
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Returns the reports triggered by the sentence, sentences that cannot be decoded
+    /// or have an invalid checksum are ignored.
+    pub fn update(&mut self, msg: &Nmea0183Msg) -> Vec<String> {
+        if msg.chksum_valid() == Some(false) {
+            return Vec::new();
+        }
+        match msg.msgtype() {
+            "RMC" => match Rmc::try_from(msg) {
+                Ok(rmc) => {
+                    self.rmc_seen = true;
+                    self.status_valid = rmc.is_valid();
+                    self.time = rmc.unix_time().or(self.time);
+                    self.date_time = rmc.unix_time();
+                    self.latitude = rmc.latitude;
+                    self.longitude = rmc.longitude;
+                    self.speed = rmc.speed_knots.map(|speed| speed * KNOTS_TO_MPS);
+                    self.track = rmc.course;
+                    self.magnetic_variation = rmc.magnetic_variation;
+                    vec![self.tpv()]
+                }
+                Err(_) => Vec::new(),
+            },
+            "GGA" => match Gga::try_from(msg) {
+                Ok(gga) => {
+                    self.altitude = gga.altitude;
+                    self.geoid_separation = gga.geoid_separation;
+                    if self.rmc_seen {
+                        return Vec::new();
+                    }
+                    self.status_valid = gga.is_valid();
+                    // GGA carries no date, use the one of the last ZDA
+                    if let (Some(date_time), Some(time)) = (self.date_time, gga.time) {
+                        let midnight = date_time - date_time.rem_euclid(86400.0);
+                        self.time = Some(midnight + time.seconds_of_day());
+                    }
+                    self.latitude = gga.latitude;
+                    self.longitude = gga.longitude;
+                    vec![self.tpv()]
+                }
+                Err(_) => Vec::new(),
+            },
+            "ZDA" => {
+                if let Some(time) = Zda::try_from(msg).ok().and_then(|zda| zda.unix_time()) {
+                    self.date_time = Some(time);
+                }
+                Vec::new()
+            }
+            "GSA" => {
+                if let Ok(gsa) = Gsa::try_from(msg) {
+                    self.gsa = Some(gsa);
+                }
+                Vec::new()
+            }
+            "GSV" => match Gsv::try_from(msg) {
+                Ok(gsv) => {
+                    if gsv.message_number == Some(1) {
+                        self.gsv.clear();
+                    }
+                    self.gsv.extend(gsv.satellites.iter().cloned());
+                    if gsv.is_last() {
+                        vec![self.sky()]
+                    } else {
+                        Vec::new()
+                    }
+                }
+                Err(_) => Vec::new(),
+            },
+            "VDM" | "VDO" => match self.ais.decode(msg) {
+                Ok(Some(ais)) => vec![self.ais_report(&ais)],
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    fn mode(&self) -> i64 {
+        match self.gsa.as_ref().and_then(|gsa| gsa.fix_mode) {
+            Some(mode) if self.status_valid => i64::from(mode),
+            _ if self.status_valid => {
+                if self.altitude.is_some() {
+                    3
+                } else {
+                    2
+                }
+            }
+            _ => 1,
+        }
+    }
+
+    fn tpv(&self) -> String {
+        let mode = self.mode();
+        let mut tpv = JsonObject::new()
+            .str("class", "TPV")
+            .str("device", &self.device)
+            .int("mode", mode);
+        if let Some(time) = self.time {
+            tpv = tpv.str("time", &iso8601(time));
+        }
+        if mode >= 2 {
+            tpv = tpv
+                .opt_num("lat", self.latitude)
+                .opt_num("lon", self.longitude);
+        }
+        if mode >= 3 {
+            // height above the ellipsoid, rounded as the sum of two decimal fields
+            let hae = self
+                .altitude
+                .zip(self.geoid_separation)
+                .map(|(altitude, separation)| ((altitude + separation) * 1000.0).round() / 1000.0);
+            tpv = tpv
+                .opt_num("altMSL", self.altitude)
+                .opt_num("altHAE", hae)
+                .opt_num("alt", hae)
+                .opt_num("geoidSep", self.geoid_separation);
+        }
+        tpv.opt_num("magvar", self.magnetic_variation)
+            .opt_num("speed", self.speed)
+            .opt_num("track", self.track)
+            .finish()
+    }
+
+    fn sky(&self) -> String {
+        let used = self
+            .gsa
+            .as_ref()
+            .map(|gsa| gsa.satellites.clone())
+            .unwrap_or_default();
+        let satellites = self.gsv.iter().filter_map(|satellite| {
+            let prn = satellite.prn?;
+            Some(
+                JsonObject::new()
+                    .int("PRN", i64::from(prn))
+                    .opt_num("el", satellite.elevation.map(f64::from))
+                    .opt_num("az", satellite.azimuth.map(f64::from))
+                    .opt_num("ss", satellite.snr.map(f64::from))
+                    .bool("used", used.contains(&prn))
+                    .finish(),
+            )
+        });
+        let mut sky = JsonObject::new()
+            .str("class", "SKY")
+            .str("device", &self.device);
+        if let Some(time) = self.time {
+            sky = sky.str("time", &iso8601(time));
+        }
+        if let Some(gsa) = &self.gsa {
+            sky = sky
+                .opt_num("hdop", gsa.hdop)
+                .opt_num("vdop", gsa.vdop)
+                .opt_num("pdop", gsa.pdop);
+        }
+        sky.int("nSat", self.gsv.len() as i64)
+            .int("uSat", used.len() as i64)
+            .raw("satellites", &array(satellites))
+            .finish()
+    }
+
+    /// Scaled AIS report, unavailable values are reported with the AIS sentinel values
+    /// like gpsd does.
+    fn ais_report(&self, ais: &AisMessage) -> String {
+        let report = JsonObject::new()
+            .str("class", "AIS")
+            .str("device", &self.device)
+            .int("type", i64::from(ais.msg_type()))
+            .int("repeat", i64::from(ais.repeat()))
+            .int("mmsi", i64::from(ais.mmsi()))
+            .bool("scaled", true);
+        match ais {
+            AisMessage::PositionReport(position) => {
+                let report = report.int("status", i64::from(position.status));
+                let report = match (position.rate_of_turn(), position.turn) {
+                    (Some(turn), _) => report.num("turn", (turn * 10.0).round() / 10.0),
+                    (None, 127) => report.str("turn", "fastright"),
+                    (None, -127) => report.str("turn", "fastleft"),
+                    (None, _) => report.str("turn", "nan"),
+                };
+                report
+                    .num("speed", position.speed.unwrap_or(102.3))
+                    .bool("accuracy", position.accuracy)
+                    .num("lon", position.longitude.unwrap_or(181.0))
+                    .num("lat", position.latitude.unwrap_or(91.0))
+                    .num("course", position.course.unwrap_or(360.0))
+                    .int("heading", i64::from(position.heading.unwrap_or(511)))
+                    .int("second", i64::from(position.second))
+                    .int("maneuver", i64::from(position.maneuver))
+                    .bool("raim", position.raim)
+                    .finish()
+            }
+            AisMessage::StaticAndVoyage(voyage) => report
+                .int("imo", i64::from(voyage.imo))
+                .int("ais_version", i64::from(voyage.ais_version))
+                .str("callsign", &voyage.callsign)
+                .str("shipname", &voyage.shipname)
+                .int("shiptype", i64::from(voyage.shiptype))
+                .int("to_bow", i64::from(voyage.to_bow))
+                .int("to_stern", i64::from(voyage.to_stern))
+                .int("to_port", i64::from(voyage.to_port))
+                .int("to_starboard", i64::from(voyage.to_starboard))
+                .int("epfd", i64::from(voyage.epfd))
+                .str(
+                    "eta",
+                    &format!(
+                        "{:02}-{:02}T{:02}:{:02}Z",
+                        voyage.month, voyage.day, voyage.hour, voyage.minute
+                    ),
+                )
+                .num("draught", voyage.draught)
+                .str("destination", &voyage.destination)
+                .finish(),
+            AisMessage::ClassBPosition(position) => report
+                .num("speed", position.speed.unwrap_or(102.3))
+                .bool("accuracy", position.accuracy)
+                .num("lon", position.longitude.unwrap_or(181.0))
+                .num("lat", position.latitude.unwrap_or(91.0))
+                .num("course", position.course.unwrap_or(360.0))
+                .int("heading", i64::from(position.heading.unwrap_or(511)))
+                .int("second", i64::from(position.second))
+                .bool("raim", position.raim)
+                .finish(),
+            AisMessage::StaticDataReport(StaticDataReport::PartA { shipname, .. }) => {
+                report.int("partno", 0).str("shipname", shipname).finish()
+            }
+            AisMessage::StaticDataReport(StaticDataReport::PartB {
+                shiptype,
+                vendor_id,
+                callsign,
+                to_bow,
+                to_stern,
+                to_port,
+                to_starboard,
+                ..
+            }) => report
+                .int("partno", 1)
+                .int("shiptype", i64::from(*shiptype))
+                .str("vendorid", vendor_id)
+                .str("callsign", callsign)
+                .int("to_bow", i64::from(*to_bow))
+                .int("to_stern", i64::from(*to_stern))
+                .int("to_port", i64::from(*to_port))
+                .int("to_starboard", i64::from(*to_starboard))
+                .finish(),
+            AisMessage::Other { .. } => report.finish(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Update {
+    sentence: String,
+    reports: Vec<String>,
+}
+
+#[derive(Default)]
+struct Latest {
+    tpv: Option<String>,
+    sky: Option<String>,
+}
+
+/// Serves the gpsd JSON protocol, e.g. to cgps or chrony via gpsd clients. Supports
+/// `?VERSION;`, `?DEVICES;`, `?WATCH={..};` with `enable`, `json` and `nmea`, and `?POLL;`.
+pub struct GpsdServer {
+    listener: TcpListener,
+    device: String,
+    tx: broadcast::Sender<Update>,
+    latest: Arc<Mutex<Latest>>,
+}
+
+impl GpsdServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, device: &str) -> io::Result<Self> {
+        let (tx, _) = broadcast::channel(SERVER_QUEUE_SIZE);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            device: device.to_string(),
+            tx,
+            latest: Arc::new(Mutex::new(Latest::default())),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Accepts clients and reports the sentences of `stream`, e.g. from
+    /// [`crate::get_codec`], until it ends. Decode errors are skipped.
+    pub async fn serve<S>(self, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut reporter = GpsdReporter::new(&self.device);
+        let tx = self.tx.clone();
+        let latest = Arc::clone(&self.latest);
+        let forward = async move {
+            let mut stream = ResumeOnError::new(stream);
+            while let Some(result) = stream.next().await {
+                let msg = match result {
+                    Ok(msg) => msg,
+                    Err(_) => continue,
+                };
+                let reports = reporter.update(&msg);
+                if let Ok(mut latest) = latest.lock() {
+                    for report in &reports {
+                        if report.starts_with(r#"{"class":"TPV""#) {
+                            latest.tpv = Some(report.clone());
+                        } else if report.starts_with(r#"{"class":"SKY""#) {
+                            latest.sky = Some(report.clone());
+                        }
+                    }
+                }
+                // no receivers is not an error, clients come and go
+                let _ = tx.send(Update {
+                    sentence: msg.to_string(),
+                    reports,
+                });
+            }
+            Ok(())
+        };
+        accept_while(self.run(), forward).await
+    }
+
+    async fn run(self) -> io::Result<()> {
+        loop {
+            let (socket, _) = self.listener.accept().await?;
+            tokio::spawn(serve_client(
+                socket,
+                self.device.clone(),
+                self.tx.subscribe(),
+                Arc::clone(&self.latest),
+            ));
+        }
+    }
+}
+
+#[derive(Default)]
+struct Watch {
+    enable: bool,
+    json: bool,
+    nmea: bool,
+}
+
+impl Watch {
+    fn update(&mut self, params: &str) -> Result<(), String> {
+        if params.is_empty() {
+            self.enable = true;
+            return Ok(());
+        }
+        let object = parse_object(params)?;
+        let flag = |key: &str, default: bool| match object.get(key) {
+            Some(JsonValue::Bool(value)) => *value,
+            _ => default,
+        };
+        self.enable = flag("enable", true);
+        self.json = flag("json", self.json);
+        self.nmea = flag("nmea", self.nmea);
+        Ok(())
+    }
+
+    fn to_json(&self) -> String {
+        JsonObject::new()
+            .str("class", "WATCH")
+            .bool("enable", self.enable)
+            .bool("json", self.json)
+            .bool("nmea", self.nmea)
+            .bool("raw", false)
+            .bool("scaled", true)
+            .finish()
+    }
+}
+
+fn version() -> String {
+    JsonObject::new()
+        .str("class", "VERSION")
+        .str("release", RELEASE)
+        .str(
+            "rev",
+            concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION")),
+        )
+        .int("proto_major", PROTO_MAJOR)
+        .int("proto_minor", PROTO_MINOR)
+        .finish()
+}
+
+fn devices(device: &str) -> String {
+    let device = JsonObject::new()
+        .str("class", "DEVICE")
+        .str("path", device)
+        .str("driver", "NMEA0183")
+        .finish();
+    JsonObject::new()
+        .str("class", "DEVICES")
+        .raw("devices", &array([device]))
+        .finish()
+}
+
+fn error(message: &str) -> String {
+    JsonObject::new()
+        .str("class", "ERROR")
+        .str("message", message)
+        .finish()
+}
+
+/// Answers a command like `?WATCH={"enable":true}`, the trailing `;` has been removed.
+fn handle_command(
+    command: &str,
+    device: &str,
+    watch: &mut Watch,
+    latest: &Mutex<Latest>,
+) -> Vec<String> {
+    let (name, params) = match command.find(['=', ';']) {
+        Some(idx) => (&command[..idx], command[idx..].trim_start_matches('=')),
+        None => (command, ""),
+    };
+    match name {
+        "?VERSION" => vec![version()],
+        "?DEVICES" => vec![devices(device)],
+        "?WATCH" => match watch.update(params) {
+            Ok(()) => vec![devices(device), watch.to_json()],
+            Err(message) => vec![error(&message)],
+        },
+        "?POLL" => {
+            let latest = match latest.lock() {
+                Ok(latest) => latest,
+                Err(_) => return vec![error("Internal error")],
+            };
+            let tpv = latest.tpv.iter().cloned().collect::<Vec<_>>();
+            let sky = latest.sky.iter().cloned().collect::<Vec<_>>();
+            vec![JsonObject::new()
+                .str("class", "POLL")
+                .int("active", 1)
+                .raw("tpv", &array(tpv))
+                .raw("sky", &array(sky))
+                .finish()]
+        }
+        _ => vec![error(&format!("Unrecognized request {}", quote(command)))],
+    }
+}
+
+async fn serve_client(
+    socket: TcpStream,
+    device: String,
+    mut rx: broadcast::Receiver<Update>,
+    latest: Arc<Mutex<Latest>>,
+) {
+    let (reader, mut writer) = socket.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut watch = Watch::default();
+    let mut command = Vec::new();
+    if writer
+        .write_all(format!("{}\r\n", version()).as_bytes())
+        .await
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        let mut limited = (&mut reader).take((MAX_COMMAND_SIZE - command.len()) as u64);
+        let lines = tokio::select! {
+            read = limited.read_until(b';', &mut command) => {
+                match read {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) if command.len() >= MAX_COMMAND_SIZE && command.last() != Some(&b';') => {
+                        break
+                    }
+                    Ok(_) => {
+                        let text = String::from_utf8_lossy(&command).to_string();
+                        command.clear();
+                        let text = text.trim().trim_end_matches(';');
+                        handle_command(text, &device, &mut watch, &latest)
+                    }
+                }
+            }
+            update = rx.recv() => match update {
+                Ok(update) if watch.enable => {
+                    let mut lines = Vec::new();
+                    if watch.json {
+                        lines.extend(update.reports);
+                    }
+                    if watch.nmea {
+                        lines.push(update.sentence);
+                    }
+                    lines
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        };
+        for line in lines {
+            if writer
+                .write_all(format!("{}\r\n", line).as_bytes())
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use futures::SinkExt;
+    use tokio::io::AsyncBufReadExt;
+
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E";
+    const GGA: &str = "$GPGGA,184906.000,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*43";
+    const GSA: &str = "$GPGSA,A,3,29,31,01,27,16,32,22,20,14,18,03,,1.4,0.7,1.2*35";
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_reporter() {
+        let mut reporter = GpsdReporter::new("/dev/ttyUSB0");
+        let corrupted = GGA.replace("*43", "*00");
+        assert!(reporter.update(&msg(&corrupted)).is_empty());
+        assert!(reporter.update(&msg(GSA)).is_empty());
+        let reports = reporter.update(&msg(GGA));
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].starts_with(r#"{"class":"TPV","device":"/dev/ttyUSB0","mode":3,"#));
+
+        let tpv = reporter.update(&msg(RMC)).remove(0);
+        assert!(
+            tpv.contains(r#""time":"2014-03-30T18:49:06.000Z""#),
+            "{}",
+            tpv
+        );
+        assert!(
+            tpv.contains(r#""altMSL":50.8,"altHAE":52.1,"alt":52.1,"geoidSep":1.3"#),
+            "{}",
+            tpv
+        );
+        assert!(tpv.contains(r#""track":222.3"#), "{}", tpv);
+        // RMC seen, GGA only updates the altitude
+        assert!(reporter.update(&msg(GGA)).is_empty());
+
+        for sentence in [
+            "$GPGSV,3,1,12,32,47,317,42,16,47,206,39,22,40,154,44,31,36,355,34*74",
+            "$GPGSV,3,2,12,14,35,065,45,06,19,181,,27,13,188,38,20,09,319,30*72",
+        ] {
+            assert!(reporter.update(&msg(sentence)).is_empty());
+        }
+        let sky = reporter
+            .update(&msg(
+                "$GPGSV,3,3,12,29,06,075,43,01,06,277,34,03,06,202,,18,04,146,40*7F",
+            ))
+            .remove(0);
+        assert!(sky.contains(r#""nSat":12,"uSat":11"#), "{}", sky);
+        assert!(
+            sky.contains(r#"{"PRN":6,"el":19,"az":181,"used":false}"#),
+            "{}",
+            sky
+        );
+
+        let ais = reporter
+            .update(&msg("!AIVDM,1,1,,B,15M67FC000G?ufbE`FepT@3n00Sa,0*5C"))
+            .remove(0);
+        assert!(ais.starts_with(
+            r#"{"class":"AIS","device":"/dev/ttyUSB0","type":1,"repeat":0,"mmsi":366053209,"#
+        ));
+    }
+
+    #[test]
+    fn test_server() {
+        tokio_test::block_on(async {
+            let server = GpsdServer::bind("127.0.0.1:0", "/dev/ttyUSB0")
+                .await
+                .unwrap();
+            let addr = server.local_addr().unwrap();
+            let (mut tx, rx) = mpsc::unbounded::<Result<Nmea0183Msg, io::Error>>();
+            tokio::spawn(server.serve(rx));
+
+            let mut socket = BufReader::new(TcpStream::connect(addr).await.unwrap());
+            let mut line = String::new();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with(r#"{"class":"VERSION""#), "{}", line);
+
+            socket
+                .get_mut()
+                .write_all(br#"?WATCH={"enable":true,"json":true};"#)
+                .await
+                .unwrap();
+            for class in ["DEVICES", "WATCH"] {
+                line.clear();
+                socket.read_line(&mut line).await.unwrap();
+                assert!(line.starts_with(&format!(r#"{{"class":"{}""#, class)));
+            }
+
+            for sentence in [GSA, RMC] {
+                tx.send(Ok(msg(sentence))).await.unwrap();
+            }
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with(r#"{"class":"TPV""#), "{}", line);
+
+            socket.get_mut().write_all(b"?POLL;\n").await.unwrap();
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with(r#"{"class":"POLL","active":1,"tpv":[{"class":"TPV""#));
+
+            socket.get_mut().write_all(b"?FOO;").await.unwrap();
+            line.clear();
+            socket.read_line(&mut line).await.unwrap();
+            assert!(line.starts_with(r#"{"class":"ERROR""#), "{}", line);
+
+            // a command without end is not buffered forever
+            let _ = socket
+                .get_mut()
+                .write_all(&[b'x'; 2 * MAX_COMMAND_SIZE])
+                .await;
+            line.clear();
+            let read = socket.read_line(&mut line).await;
+            assert!(!matches!(read, Ok(len) if len > 0), "{}", line);
+        })
+    }
+}
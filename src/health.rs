@@ -0,0 +1,281 @@
+use crate::json::JsonObject;
+use std::collections::BTreeMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+const MAX_REQUEST_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceHealth {
+    pub messages: u64,
+    pub decode_errors: u64,
+    /// Failed connection attempts and lost connections, each is followed by a reconnect
+    /// attempt, see [`crate::tcp::TcpClient`].
+    pub reconnects: u64,
+    pub last_message: Option<Instant>,
+}
+
+impl SourceHealth {
+    fn age(&self, now: Instant) -> Option<Duration> {
+        self.last_message
+            .map(|last_message| now.saturating_duration_since(last_message))
+    }
+}
+
+/// Per source counters of a [`crate::service::Service`], shared with a [`HealthServer`].
+#[derive(Debug, Clone)]
+pub struct Health {
+    started: Instant,
+    sources: BTreeMap<String, SourceHealth>,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the sources that are not known yet and drops the ones not in `names`, e.g.
+    /// after reloading the configuration. Counters of the remaining sources are kept.
+    pub fn set_sources<'a, I: IntoIterator<Item = &'a str>>(&mut self, names: I) {
+        let mut sources = BTreeMap::new();
+        for name in names {
+            let source = self.sources.remove(name).unwrap_or_default();
+            sources.insert(name.to_string(), source);
+        }
+        self.sources = sources;
+    }
+
+    pub fn message(&mut self, source: &str) {
+        let source = self.sources.entry(source.to_string()).or_default();
+        source.messages += 1;
+        source.last_message = Some(Instant::now());
+    }
+
+    /// Decode errors of the codec are [`io::ErrorKind::Other`], all other errors are
+    /// counted as connection errors.
+    pub fn error(&mut self, source: &str, error: &io::Error) {
+        let source = self.sources.entry(source.to_string()).or_default();
+        match error.kind() {
+            io::ErrorKind::Other | io::ErrorKind::InvalidData => source.decode_errors += 1,
+            _ => source.reconnects += 1,
+        }
+    }
+
+    pub fn source(&self, name: &str) -> Option<&SourceHealth> {
+        self.sources.get(name)
+    }
+
+    pub fn sources(&self) -> impl Iterator<Item = (&str, &SourceHealth)> {
+        self.sources
+            .iter()
+            .map(|(name, source)| (name.as_str(), source))
+    }
+
+    /// All sources sent a sentence within `stale_after`.
+    pub fn is_healthy(&self, stale_after: Duration, now: Instant) -> bool {
+        self.sources
+            .values()
+            .all(|source| source.age(now).is_some_and(|age| age <= stale_after))
+    }
+
+    /// `{"healthy":true,"uptime":12.5,"sources":{"gps":{"messages":12,
+    /// "last_message_age":0.2,"decode_errors":0,"reconnects":0}}}`, the age is missing
+    /// before the first sentence.
+    pub fn to_json(&self, stale_after: Duration, now: Instant) -> String {
+        let mut sources = JsonObject::new();
+        for (name, source) in &self.sources {
+            let json = JsonObject::new()
+                .int("messages", source.messages as i64)
+                .opt_num(
+                    "last_message_age",
+                    source.age(now).map(|age| age.as_secs_f64()),
+                )
+                .int("decode_errors", source.decode_errors as i64)
+                .int("reconnects", source.reconnects as i64)
+                .finish();
+            sources = sources.raw(name, &json);
+        }
+        JsonObject::new()
+            .bool("healthy", self.is_healthy(stale_after, now))
+            .num(
+                "uptime",
+                now.saturating_duration_since(self.started).as_secs_f64(),
+            )
+            .raw("sources", &sources.finish())
+            .finish()
+    }
+}
+
+/// Answers `GET /health` (or `/`) with [`Health::to_json`], the status is `200 OK` if
+/// all sources are healthy and `503 Service Unavailable` otherwise.
+pub struct HealthServer {
+    listener: TcpListener,
+    health: Arc<Mutex<Health>>,
+    stale_after: Duration,
+}
+
+impl HealthServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A, health: Arc<Mutex<Health>>) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            health,
+            stale_after: Duration::from_secs(10),
+        })
+    }
+
+    /// Sources without a sentence for longer are unhealthy, 10 s by default.
+    pub fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts clients until accepting fails.
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            let (socket, _) = self.listener.accept().await?;
+            let health = Arc::clone(&self.health);
+            let stale_after = self.stale_after;
+            tokio::spawn(async move {
+                if let Err(error) = respond(socket, health, stale_after).await {
+                    log::debug!("Health request failed: {}", error);
+                }
+            });
+        }
+    }
+}
+
+async fn respond(
+    mut socket: TcpStream,
+    health: Arc<Mutex<Health>>,
+    stale_after: Duration,
+) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 512];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let len = socket.read(&mut buf).await?;
+        if len == 0 || request.len() + len > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Incomplete request",
+            ));
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut words = request.split_whitespace();
+    let (status, body) = match (words.next(), words.next()) {
+        (Some("GET"), Some("/" | "/health")) => {
+            let health = health.lock().unwrap();
+            let now = Instant::now();
+            let status = if health.is_healthy(stale_after, now) {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (status, health.to_json(stale_after, now))
+        }
+        (Some("GET"), _) => ("404 Not Found", "{}".to_string()),
+        _ => ("405 Method Not Allowed", "{}".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    socket.write_all(response.as_bytes()).await?;
+    socket.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health() {
+        let mut health = Health::new();
+        health.set_sources(["gps", "ais"]);
+        let now = Instant::now();
+        assert!(!health.is_healthy(Duration::from_secs(10), now));
+        health.message("gps");
+        health.message("ais");
+        health.error("gps", &io::Error::other("Invalid event"));
+        health.error("ais", &io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert!(health.is_healthy(Duration::from_secs(10), Instant::now()));
+        assert!(!health.is_healthy(
+            Duration::from_secs(10),
+            Instant::now() + Duration::from_secs(11)
+        ));
+        let gps = health.source("gps").unwrap();
+        assert_eq!((gps.messages, gps.decode_errors, gps.reconnects), (1, 1, 0));
+        assert_eq!(health.source("ais").unwrap().reconnects, 1);
+
+        // counters survive a reload
+        health.set_sources(["gps", "tcp"]);
+        assert_eq!(
+            health.sources().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["gps", "tcp"]
+        );
+        assert_eq!(health.source("gps").unwrap().messages, 1);
+
+        let json = health.to_json(Duration::from_secs(10), Instant::now());
+        assert!(
+            json.starts_with(r#"{"healthy":false,"uptime":"#),
+            "{}",
+            json
+        );
+        assert!(
+            json.ends_with(r#""tcp":{"messages":0,"decode_errors":0,"reconnects":0}}}"#),
+            "{}",
+            json
+        );
+        assert!(json.contains(r#""gps":{"messages":1,"last_message_age":"#));
+
+        tokio_test::block_on(async {
+            let health = Arc::new(Mutex::new(health));
+            let server = HealthServer::bind("127.0.0.1:0", Arc::clone(&health))
+                .await
+                .unwrap();
+            let addr = server.local_addr().unwrap();
+            tokio::spawn(server.run());
+            let get = |path: &'static str| async move {
+                let mut socket = TcpStream::connect(addr).await.unwrap();
+                socket
+                    .write_all(
+                        format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                let mut response = String::new();
+                socket.read_to_string(&mut response).await.unwrap();
+                response
+            };
+
+            let response = get("/health").await;
+            assert!(response.starts_with("HTTP/1.1 503 "), "{}", response);
+            health.lock().unwrap().message("tcp");
+            let response = get("/").await;
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+            let (_, body) = response.split_once("\r\n\r\n").unwrap();
+            assert!(body.starts_with(r#"{"healthy":true,"#), "{}", body);
+            assert!(get("/metrics").await.starts_with("HTTP/1.1 404 "));
+        })
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Builds a JSON object field by field, `None` and non finite numbers are skipped.
+pub(crate) struct JsonObject {
+    json: String,
+}
+
+impl JsonObject {
+    pub fn new() -> Self {
+        Self {
+            json: "{".to_string(),
+        }
+    }
+
+    fn key(&mut self, key: &str) {
+        if self.json.len() > 1 {
+            self.json.push(',');
+        }
+        self.json.push_str(&quote(key));
+        self.json.push(':');
+    }
+
+    pub fn str(mut self, key: &str, value: &str) -> Self {
+        self.key(key);
+        self.json.push_str(&quote(value));
+        self
+    }
+
+    pub fn num(mut self, key: &str, value: f64) -> Self {
+        if value.is_finite() {
+            self.key(key);
+            let _ = write!(self.json, "{}", value);
+        }
+        self
+    }
+
+    pub fn int(mut self, key: &str, value: i64) -> Self {
+        self.key(key);
+        let _ = write!(self.json, "{}", value);
+        self
+    }
+
+    pub fn bool(mut self, key: &str, value: bool) -> Self {
+        self.key(key);
+        self.json.push_str(if value { "true" } else { "false" });
+        self
+    }
+
+    /// Inserts already serialized JSON, e.g. from [`array`].
+    pub fn raw(mut self, key: &str, json: &str) -> Self {
+        self.key(key);
+        self.json.push_str(json);
+        self
+    }
+
+    pub fn opt_num(self, key: &str, value: Option<f64>) -> Self {
+        match value {
+            Some(value) => self.num(key, value),
+            None => self,
+        }
+    }
+
+    pub fn opt_str(self, key: &str, value: Option<&str>) -> Self {
+        match value {
+            Some(value) => self.str(key, value),
+            None => self,
+        }
+    }
+
+    pub fn finish(mut self) -> String {
+        self.json.push('}');
+        self.json
+    }
+}
+
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub(crate) fn array<I: IntoIterator<Item = String>>(items: I) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+/// Parses a flat JSON object as sent with gpsd commands, nested values are rejected.
+pub(crate) fn parse_object(json: &str) -> Result<HashMap<String, JsonValue>, String> {
+    let invalid = || format!("Invalid JSON object '{}'", json);
+    let mut chars = json.trim().chars().peekable();
+    let mut object = HashMap::new();
+    if chars.next() != Some('{') {
+        return Err(invalid());
+    }
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some('}') if object.is_empty() => break,
+            Some('"') => {}
+            _ => return Err(invalid()),
+        }
+        let key = parse_string(&mut chars).ok_or_else(invalid)?;
+        skip_whitespace(&mut chars);
+        if chars.next() != Some(':') {
+            return Err(invalid());
+        }
+        skip_whitespace(&mut chars);
+        let value = match chars.peek() {
+            Some('"') => {
+                chars.next();
+                JsonValue::String(parse_string(&mut chars).ok_or_else(invalid)?)
+            }
+            _ => {
+                let mut literal = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || "+-.".contains(*c))
+                {
+                    literal.push(c);
+                }
+                match literal.as_str() {
+                    "null" => JsonValue::Null,
+                    "true" => JsonValue::Bool(true),
+                    "false" => JsonValue::Bool(false),
+                    number => JsonValue::Number(number.parse().map_err(|_| invalid())?),
+                }
+            }
+        };
+        object.insert(key, value);
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => break,
+            _ => return Err(invalid()),
+        }
+    }
+    skip_whitespace(&mut chars);
+    if chars.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(object)
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// Reads up to the closing quote, the opening quote has been consumed.
+fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> Option<String> {
+    let mut value = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let code = (0..4).map(|_| chars.next()).collect::<Option<String>>()?;
+                    value.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let json = JsonObject::new()
+            .str("class", "TPV")
+            .str("text", "a \"quoted\"\n\\")
+            .num("lat", 8.5)
+            .num("nan", f64::NAN)
+            .opt_num("none", None)
+            .int("mode", 3)
+            .bool("used", true)
+            .raw("list", &array(vec!["1".to_string(), "2".to_string()]))
+            .finish();
+        assert_eq!(
+            json,
+            r#"{"class":"TPV","text":"a \"quoted\"\n\\","lat":8.5,"mode":3,"used":true,"list":[1,2]}"#
+        );
+
+        let object = parse_object(
+            r#" {"enable":true, "json" : false,"device":"/dev/tty\"S0","n":-1.5e1,"x":null} "#,
+        )
+        .unwrap();
+        assert_eq!(object["enable"], JsonValue::Bool(true));
+        assert_eq!(object["json"], JsonValue::Bool(false));
+        assert_eq!(
+            object["device"],
+            JsonValue::String("/dev/tty\"S0".to_string())
+        );
+        assert_eq!(object["n"], JsonValue::Number(-15.0));
+        assert_eq!(object["x"], JsonValue::Null);
+        assert!(parse_object("{}").unwrap().is_empty());
+        assert!(parse_object(r#"{"a":{"b":1}}"#).is_err());
+        assert!(parse_object(r#"{"a":1"#).is_err());
+    }
+}
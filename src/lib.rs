@@ -1,8 +1,16 @@
 #![allow(dead_code)]
-#![feature(test)]
+#![cfg_attr(test, feature(test))]
 
-use crate::nmea0183_codec::Nmea0183Codec;
+use crate::nmea0183_codec::context::StateMachine;
+use crate::tag_block::TagBlock;
 use bytes::BytesMut;
+use futures::{ready, Sink, SinkExt, Stream, StreamExt};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Duration;
 // use crate::state::{Checksum, Invalid, Linefeed, MsgType, Params, Start, State, Talker, LF};
 // use bytes::BytesMut;
 // use std::mem::take;
@@ -10,10 +18,49 @@ use bytes::BytesMut;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::{Decoder, Framed};
 
+pub mod ais;
+pub mod config;
+pub mod daemon;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+pub mod endpoint;
+pub mod gps_clock;
+pub mod gpsd;
+pub mod health;
+mod json;
+pub mod mux;
+#[cfg(feature = "serde")]
+pub mod ndjson;
 mod nmea0183_codec;
+pub use nmea0183_codec::Nmea0183Codec;
+pub mod pattern;
+pub mod pipeline;
+pub mod rate_limit;
+pub mod recorder;
+pub mod replay;
+pub mod sanitize;
+pub mod sentence;
+pub mod sentence_registry;
+pub mod serial;
+pub mod service;
+pub mod signalk;
+pub mod stats;
+pub mod table;
+pub mod tag_block;
+pub mod tcp;
+pub mod track;
+pub mod transducer_registry;
+pub mod udp;
+pub mod websocket;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SerdeMsg")
+)]
 pub struct Nmea0183Msg {
+    tag_block: Option<TagBlock>,
     encapsulation: bool,
     talker: String,
     msgtype: String,
@@ -22,9 +69,48 @@ pub struct Nmea0183Msg {
     chksum_valid: Option<bool>,
 }
 
+/// The deserialized fields of a [`Nmea0183Msg`], `chksum_valid` is recalculated instead
+/// of trusted.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerdeMsg {
+    tag_block: Option<TagBlock>,
+    encapsulation: bool,
+    talker: String,
+    msgtype: String,
+    params: Vec<String>,
+    chksum: String,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerdeMsg> for Nmea0183Msg {
+    type Error = String;
+
+    fn try_from(value: SerdeMsg) -> Result<Self, Self::Error> {
+        let mut msg = Self {
+            tag_block: value.tag_block,
+            encapsulation: value.encapsulation,
+            talker: value.talker,
+            msgtype: value.msgtype,
+            params: value.params,
+            chksum: value.chksum,
+            chksum_valid: None,
+        };
+        if !msg.chksum.is_empty() {
+            if msg.chksum.len() != 2 || !msg.chksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return Err(format!("Invalid checksum '{}'", msg.chksum));
+            }
+            let chksum = u8::from_str_radix(&msg.chksum, 16).unwrap_or_default();
+            msg.chksum_valid = Some(chksum == msg.calc_chksum());
+        }
+        Ok(msg)
+    }
+}
+
 impl Default for Nmea0183Msg {
     fn default() -> Self {
         Self {
+            tag_block: None,
             encapsulation: false,
             talker: "".to_string(),
             msgtype: "".to_string(),
@@ -35,14 +121,234 @@ impl Default for Nmea0183Msg {
     }
 }
 
-pub fn get_codec<T>(port: T) -> Framed<impl AsyncRead, Nmea0183Codec>
+impl Nmea0183Msg {
+    /// Creates a `$` sentence with a valid checksum.
+    pub fn new(talker: &str, msgtype: &str, params: Vec<String>) -> Self {
+        let mut msg = Self {
+            talker: talker.to_string(),
+            msgtype: msgtype.to_string(),
+            params,
+            ..Default::default()
+        };
+        msg.chksum = format!("{:02X}", msg.calc_chksum());
+        msg.chksum_valid = Some(true);
+        msg
+    }
+
+    /// Creates a `!` sentence with a valid checksum as used for AIS.
+    pub fn new_encapsulated(talker: &str, msgtype: &str, params: Vec<String>) -> Self {
+        Self {
+            encapsulation: true,
+            ..Self::new(talker, msgtype, params)
+        }
+    }
+
+    /// Checksum over talker, message type and parameters.
+    pub fn calc_chksum(&self) -> u8 {
+        let mut chksum = self
+            .talker
+            .bytes()
+            .chain(self.msgtype.bytes())
+            .fold(0, |chksum, byte| chksum ^ byte);
+        for param in self.params.iter() {
+            chksum = param
+                .bytes()
+                .fold(chksum ^ b',', |chksum, byte| chksum ^ byte);
+        }
+        chksum
+    }
+
+    pub fn tag_block(&self) -> Option<&TagBlock> {
+        self.tag_block.as_ref()
+    }
+
+    pub fn set_tag_block(&mut self, tag_block: Option<TagBlock>) {
+        self.tag_block = tag_block;
+    }
+
+    pub fn is_encapsulation(&self) -> bool {
+        self.encapsulation
+    }
+
+    pub fn talker(&self) -> &str {
+        self.talker.as_str()
+    }
+
+    pub fn msgtype(&self) -> &str {
+        self.msgtype.as_str()
+    }
+
+    pub fn params(&self) -> &[String] {
+        self.params.as_slice()
+    }
+
+    pub fn chksum(&self) -> &str {
+        self.chksum.as_str()
+    }
+
+    pub fn chksum_valid(&self) -> Option<bool> {
+        self.chksum_valid
+    }
+
+    /// `$P` sentences, the message type starts with the manufacturer mnemonic.
+    pub fn is_proprietary(&self) -> bool {
+        self.talker == "P"
+    }
+
+    pub fn manufacturer(&self) -> Option<&str> {
+        if self.is_proprietary() {
+            self.msgtype.get(0..3)
+        } else {
+            None
+        }
+    }
+}
+
+/// Formats the sentence including the tag block without CR LF. The checksum is written
+/// as received, so an invalid checksum stays invalid and a missing one stays missing, see
+/// [`sanitize::Sanitizer`] to repair them. The tag block checksum is recalculated.
+impl fmt::Display for Nmea0183Msg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(tag_block) = &self.tag_block {
+            write!(f, "{}", tag_block)?;
+        }
+        write!(
+            f,
+            "{}{}{}",
+            if self.encapsulation { '!' } else { '$' },
+            self.talker,
+            self.msgtype
+        )?;
+        for param in self.params.iter() {
+            write!(f, ",{}", param)?;
+        }
+        if self.chksum.is_empty() {
+            Ok(())
+        } else {
+            write!(f, "*{}", self.chksum)
+        }
+    }
+}
+
+/// Parses a single sentence, the trailing CR LF is optional.
+impl FromStr for Nmea0183Msg {
+    type Err = String;
+
+    fn from_str(sentence: &str) -> Result<Self, Self::Err> {
+        let mut ctx = StateMachine::new();
+        let sentence = sentence.trim_end_matches(['\r', '\n']);
+        for byte in sentence.bytes().chain(*b"\r\n") {
+            if let Some(msg) = ctx.handle_event(&byte)? {
+                return Ok(msg);
+            }
+        }
+        Err("Incomplete message".to_string())
+    }
+}
+
+/// Wraps a port as `Stream` of decoded sentences and `Sink` for sentences to send.
+pub fn get_codec<T>(port: T) -> Framed<T, Nmea0183Codec>
 where
     T: AsyncRead + AsyncWrite + Sized,
 {
-    // let mut codec = ;
     Nmea0183Codec::default().framed(port)
 }
 
+/// Sends `command` and waits for the first sentence accepted by `is_response`, e.g.
+/// [`pmtk_ack`]. Sentences received in between and decode errors are dropped.
+pub async fn request<S, F>(
+    link: &mut S,
+    command: &Nmea0183Msg,
+    is_response: F,
+    timeout: Duration,
+) -> io::Result<Nmea0183Msg>
+where
+    S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+    F: Fn(&Nmea0183Msg) -> bool,
+{
+    link.send(command.clone()).await?;
+    let mut responses = ResumeOnError::new(&mut *link);
+    tokio::time::timeout(timeout, async {
+        while let Some(result) = responses.next().await {
+            if let Ok(msg) = result {
+                if is_response(&msg) {
+                    return Ok(msg);
+                }
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Link closed while waiting for response",
+        ))
+    })
+    .await
+    .map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("No response to {}", command),
+        )
+    })?
+}
+
+/// Matches the `$PMTK001,<command>,<flag>` acknowledgement of a `$PMTK` command.
+pub fn pmtk_ack(command: &Nmea0183Msg) -> impl Fn(&Nmea0183Msg) -> bool {
+    let command = command
+        .msgtype()
+        .strip_prefix("MTK")
+        .unwrap_or_default()
+        .to_string();
+    move |msg| {
+        msg.is_proprietary()
+            && msg.msgtype() == "MTK001"
+            && msg.params().first().map(String::as_str) == Some(command.as_str())
+    }
+}
+
+/// `Framed` ends the stream after a decode error, this keeps reading from the
+/// underlying stream instead.
+pub struct ResumeOnError<S> {
+    stream: S,
+    errored: bool,
+}
+
+impl<S> ResumeOnError<S> {
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            errored: false,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, T, E> Stream for ResumeOnError<S>
+where
+    S: Stream<Item = Result<T, E>> + Unpin,
+{
+    type Item = Result<T, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let result = ready!(Pin::new(&mut self.stream).poll_next(cx));
+            match result {
+                // the first None after an error only pauses the stream
+                None if self.errored => self.errored = false,
+                result => {
+                    self.errored = matches!(result, Some(Err(_)));
+                    return Poll::Ready(result);
+                }
+            }
+        }
+    }
+}
+
 #[allow(dead_code)]
 fn byte_2_print(byte: &u8) -> String {
     format!(
@@ -66,13 +372,14 @@ fn to_string(buf: &BytesMut) -> String {
 
 #[cfg(test)]
 mod tests {
-    use crate::get_codec;
+    use crate::{get_codec, pmtk_ack, request, Nmea0183Msg};
     use futures::stream::StreamExt;
-    use rand;
+    use std::io;
+    use std::time::Duration;
+
     use tokio::fs::File;
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
     use tokio::task::yield_now;
-    use tokio_test;
 
     const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
 
@@ -87,7 +394,7 @@ mod tests {
         aw!(async {
             let file = File::open(TEST_FILE)
                 .await
-                .expect(format!("failed to open file {}", TEST_FILE).as_str());
+                .unwrap_or_else(|_| panic!("failed to open file {}", TEST_FILE));
 
             let mut reader = get_codec(file);
             let mut count = 0;
@@ -110,12 +417,12 @@ mod tests {
         aw!(async {
             let mut file = File::open(TEST_FILE)
                 .await
-                .expect(format!("failed to open file {}", TEST_FILE).as_str());
+                .unwrap_or_else(|_| panic!("failed to open file {}", TEST_FILE));
 
             let mut buf: [u8; 1] = [0];
-            file.read(&mut buf)
+            file.read_exact(&mut buf)
                 .await
-                .expect(format!("failed to read from file {}", TEST_FILE).as_str());
+                .unwrap_or_else(|_| panic!("failed to read from file {}", TEST_FILE));
 
             let mut reader = get_codec(file);
             let mut count = 0;
@@ -149,7 +456,7 @@ mod tests {
 
                 let mut file = File::open(TEST_FILE)
                     .await
-                    .expect(format!("failed to open file {}", TEST_FILE).as_str());
+                    .unwrap_or_else(|_| panic!("failed to open file {}", TEST_FILE));
 
                 let mut buf: [u8; 1] = [0];
                 while let Ok(num_bytes) = file.read(&mut buf).await {
@@ -173,4 +480,61 @@ mod tests {
             }
         });
     }
+
+    #[test]
+    fn test_request() {
+        aw!(async {
+            let (port, device) = tokio::io::duplex(256);
+            let mut link = get_codec(port);
+            tokio::spawn(async move {
+                let (reader, mut writer) = tokio::io::split(device);
+                let mut lines = BufReader::new(reader).lines();
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    if line == "$PMTK220,1000*1F" {
+                        writer
+                            .write_all(b"$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n$PMTK001,314,3*36\r\n$PMTK001,220,3*30\r\n")
+                            .await
+                            .unwrap();
+                    }
+                }
+            });
+
+            let command: Nmea0183Msg = "$PMTK220,1000*1F".parse().unwrap();
+            let ack = request(
+                &mut link,
+                &command,
+                pmtk_ack(&command),
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+            assert_eq!(ack.to_string(), "$PMTK001,220,3*30");
+
+            let command: Nmea0183Msg = "$PMTK314,0,1,0,1,1,5,0,0,0,0,0,0,0,0,0,0,0,0,0*2C"
+                .parse()
+                .unwrap();
+            let error = request(
+                &mut link,
+                &command,
+                pmtk_ack(&command),
+                Duration::from_millis(50),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        });
+    }
+
+    #[test]
+    fn test_display_keeps_checksum() {
+        let msg: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*00".parse().unwrap();
+        assert_eq!(msg.chksum_valid(), Some(false));
+        assert_eq!(msg.to_string(), "$GPZDA,160012.71,11,03,2004,-1,00*00");
+
+        let msg = Nmea0183Msg::new("GP", "ZDA", vec!["160012.71".to_string()]);
+        assert_eq!(
+            msg.to_string(),
+            format!("$GPZDA,160012.71*{:02X}", msg.calc_chksum())
+        );
+    }
 }
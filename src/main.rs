@@ -1,41 +1,519 @@
 #![warn(rust_2018_idioms)]
 
+use clap::{Parser, Subcommand, ValueEnum};
 use futures::stream::StreamExt;
-use std::{env, str};
+use futures::SinkExt;
+use log::LevelFilter;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
 
-use nmea0183_feed::get_codec;
+use nmea0183_feed::config::Config;
+#[cfg(unix)]
+use nmea0183_feed::daemon;
+use nmea0183_feed::daemon::Logger;
+use nmea0183_feed::endpoint::{Endpoint, MsgStream};
+use nmea0183_feed::health::{Health, HealthServer};
+use nmea0183_feed::pipeline::{Pipeline, Stage};
+use nmea0183_feed::rate_limit::RateLimit;
+use nmea0183_feed::recorder::{Recorder, RecorderConfig, Timestamp};
+use nmea0183_feed::replay::Replay;
+use nmea0183_feed::sanitize::Sanitizer;
+use nmea0183_feed::sentence::iso8601;
+use nmea0183_feed::serial::SerialConfig;
+use nmea0183_feed::service::Service;
+use nmea0183_feed::stats::Stats;
+use nmea0183_feed::table::{csv_field, CsvExporter};
+use nmea0183_feed::track::{to_geojson, to_gpx, to_kml, TrackBuilder};
+use nmea0183_feed::websocket::to_json;
+use nmea0183_feed::Nmea0183Msg;
 
-use tokio_serial::SerialPortBuilderExt;
+/// Inputs and outputs are given as `serial:///dev/ttyUSB0?baud=38400&format=8N1`
+/// (`baud=auto` detects the settings), `tcp://host:10110`, `udp://:10110` or
+/// `file://log`.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-#[cfg(unix)]
-const DEFAULT_TTY: &str = "/dev/ttyUSB0";
-#[cfg(windows)]
-const DEFAULT_TTY: &str = "COM1";
+#[derive(Subcommand)]
+enum Command {
+    /// Prints received sentences with receive time and checksum state
+    Monitor { input: Endpoint },
+    /// Shows fix, satellites, sentence counters and raw sentences in the terminal
+    #[cfg(feature = "tui")]
+    Dashboard { input: Endpoint },
+    /// Records received sentences to rotating log files
+    Record {
+        input: Endpoint,
+        /// Directory for the log files
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+        #[arg(long, default_value = "nmea")]
+        prefix: String,
+        #[arg(long, value_enum, default_value_t = TimestampArg::TagBlock)]
+        timestamp: TimestampArg,
+        /// Start a new file after this many bytes
+        #[arg(long)]
+        max_size: Option<u64>,
+        /// Start a new file after this many seconds
+        #[arg(long)]
+        max_age: Option<u64>,
+        /// Compress closed files
+        #[arg(long)]
+        gzip: bool,
+    },
+    /// Sends a recorded log to an output paced by the recorded time
+    Replay {
+        file: PathBuf,
+        output: Endpoint,
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Start over at the end of the log
+        #[arg(long = "loop")]
+        looping: bool,
+        /// Skip gaps longer than this many seconds
+        #[arg(long)]
+        max_gap: Option<u64>,
+    },
+    /// Forwards sentences from an input to an output through filter and rewrite stages
+    Forward {
+        input: Endpoint,
+        output: Endpoint,
+        /// File with one stage per line
+        #[arg(short, long)]
+        pipeline: Option<PathBuf>,
+        /// Stage appended to the pipeline, e.g. "drop sentence=GSV"
+        #[arg(short, long)]
+        stage: Vec<Stage>,
+        /// Drop sentences above the bandwidth of a serial line, e.g. 4800 or 4800,8N1
+        #[arg(long)]
+        limit: Option<SerialConfig>,
+        /// Sentences to keep first when over the limit, e.g. RMC,GGA,HDT
+        #[arg(long, value_delimiter = ',')]
+        priority: Vec<String>,
+    },
+    /// Counts sentences, errors and rates
+    Stats {
+        input: Endpoint,
+        /// Print the counters every this many seconds
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+    },
+    /// Converts sentences to another format
+    Convert {
+        input: Endpoint,
+        #[arg(long, value_enum)]
+        to: ConvertFormat,
+        /// Output file, standard output if missing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Add a track per AIS target to track formats
+        #[arg(long)]
+        ais: bool,
+        /// Start a new track segment after a gap of this many seconds
+        #[arg(long, default_value_t = 10.0)]
+        max_gap: f64,
+    },
+    /// Writes a CSV table per sentence type and a table merged per fix time
+    Tables {
+        input: Endpoint,
+        /// Directory for the CSV files
+        #[arg(short, long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Re-emits sentences of non-conforming instruments with valid checksums, without
+    /// illegal characters and terminated by CR LF
+    Sanitize { input: Endpoint, output: Endpoint },
+    /// Runs the sources, sinks, recorder and servers of a TOML configuration file,
+    /// SIGHUP reloads the file, SIGTERM and SIGINT stop it
+    Run {
+        config: PathBuf,
+        /// Only validate the configuration
+        #[arg(long)]
+        check: bool,
+        /// Logs logfmt lines for a service manager, readiness and the watchdog are
+        /// reported to systemd if $NOTIFY_SOCKET is set
+        #[arg(long)]
+        daemon: bool,
+        /// Serves the health of the sources as JSON, e.g. `127.0.0.1:8080`
+        #[arg(long)]
+        health: Option<String>,
+        /// Seconds without a sentence after which a source is unhealthy
+        #[arg(long, default_value_t = 10.0)]
+        stale_after: f64,
+        #[arg(long, default_value = "info")]
+        log_level: LevelFilter,
+    },
+}
 
-#[tokio::main(flavor = "current_thread")]
-async fn main() -> tokio_serial::Result<()> {
-    let mut args = env::args();
-    let tty_path = args.nth(1).unwrap_or_else(|| DEFAULT_TTY.into());
+#[derive(Clone, Copy, ValueEnum)]
+enum TimestampArg {
+    TagBlock,
+    Prefix,
+    None,
+}
 
-    let mut port = tokio_serial::new(tty_path, 4800).open_native_async()?;
+#[derive(Clone, Copy, ValueEnum)]
+enum ConvertFormat {
+    /// One JSON object per line
+    Json,
+    /// Talker, message type, checksum state and parameters per line
+    Csv,
+    /// GPX tracks from GGA, RMC and GLL positions
+    Gpx,
+    /// KML tracks
+    Kml,
+    /// GeoJSON tracks
+    Geojson,
+    /// Records with receive time, source and decoded sentence per line
+    #[cfg(feature = "serde")]
+    Ndjson,
+}
 
-    #[cfg(unix)]
-    port.set_exclusive(false)
-        .expect("Unable to set serial port exclusive to false");
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> io::Result<()> {
+    match Cli::parse().command {
+        Command::Monitor { input } => monitor(input.open_stream().await?).await,
+        #[cfg(feature = "tui")]
+        Command::Dashboard { input } => {
+            nmea0183_feed::dashboard::Dashboard::new()
+                .run(input.open_stream().await?)
+                .await
+        }
+        Command::Record {
+            input,
+            dir,
+            prefix,
+            timestamp,
+            max_size,
+            max_age,
+            gzip,
+        } => {
+            let config = RecorderConfig {
+                prefix,
+                timestamp: match timestamp {
+                    TimestampArg::TagBlock => Timestamp::TagBlock,
+                    TimestampArg::Prefix => Timestamp::Prefix(" ".to_string()),
+                    TimestampArg::None => Timestamp::None,
+                },
+                max_size,
+                max_age: max_age.map(Duration::from_secs),
+                gzip,
+                ..RecorderConfig::new(dir)
+            };
+            let mut recorder = Recorder::new(config);
+            recorder.record(input.open_stream().await?).await?;
+            recorder.close().await.map(|_| ())
+        }
+        Command::Replay {
+            file,
+            output,
+            speed,
+            looping,
+            max_gap,
+        } => {
+            let mut replay = Replay::open(file)
+                .await?
+                .with_speed(speed)
+                .map_err(io::Error::other)?
+                .with_looping(looping)
+                .with_max_gap(max_gap.map(Duration::from_secs));
+            let mut sink = output.open_sink().await?;
+            while let Some(result) = replay.next().await {
+                if let Ok(msg) = result {
+                    sink.send(msg).await?;
+                }
+            }
+            sink.close().await
+        }
+        Command::Forward {
+            input,
+            output,
+            pipeline,
+            stage,
+            limit,
+            priority,
+        } => {
+            let mut pipeline = match pipeline {
+                Some(path) => Pipeline::load(path).await?,
+                None => Pipeline::new(),
+            };
+            for stage in stage {
+                pipeline = pipeline.with(stage);
+            }
+            let stream = input.open_stream().await?;
+            let sink = output.open_sink().await?;
+            match limit {
+                Some(config) => {
+                    let priorities = priority.iter().map(String::as_str).collect::<Vec<_>>();
+                    let mut sink =
+                        RateLimit::for_serial(sink, &config).with_priorities(&priorities);
+                    pipeline.run(stream, &mut sink).await?;
+                    eprintln!("{}", sink);
+                    Ok(())
+                }
+                None => pipeline.run(stream, sink).await,
+            }
+        }
+        Command::Stats { input, interval } => stats(input.open_stream().await?, interval).await,
+        Command::Convert {
+            input,
+            to,
+            output,
+            ais,
+            max_gap,
+        } => {
+            let mut writer: Box<dyn AsyncWrite + Unpin> = match output {
+                Some(path) => Box::new(tokio::fs::File::create(path).await?),
+                None => Box::new(tokio::io::stdout()),
+            };
+            let source = input.to_string();
+            let stream = input.open_stream().await?;
+            let export = match to {
+                ConvertFormat::Gpx => to_gpx,
+                ConvertFormat::Kml => to_kml,
+                ConvertFormat::Geojson => to_geojson,
+                _ => return convert(stream, &source, to, writer).await,
+            };
+            let tracks = TrackBuilder::new(&source)
+                .with_ais(ais)
+                .with_max_gap(Some(max_gap))
+                .read(stream)
+                .await;
+            writer.write_all(export(&tracks).as_bytes()).await?;
+            writer.flush().await
+        }
+        Command::Tables { input, dir } => {
+            let files = CsvExporter::new(dir)
+                .export(input.open_stream().await?)
+                .await?;
+            for (path, rows) in files {
+                println!("{} {} rows", path.display(), rows);
+            }
+            Ok(())
+        }
+        Command::Sanitize { input, output } => {
+            let mut sanitizer = Sanitizer::new();
+            sanitizer
+                .run(
+                    input.open_lenient_stream().await?,
+                    output.open_sink().await?,
+                )
+                .await?;
+            eprintln!("{}", sanitizer);
+            Ok(())
+        }
+        Command::Run {
+            config,
+            check,
+            daemon,
+            health,
+            stale_after,
+            log_level,
+        } => {
+            let loaded = Config::load(&config).await.map_err(|error| {
+                io::Error::new(error.kind(), format!("{}: {}", config.display(), error))
+            })?;
+            if check {
+                return Ok(());
+            }
+            Logger::new(log_level)
+                .with_structured(daemon)
+                .init()
+                .map_err(io::Error::other)?;
+            let health_state = Arc::new(Mutex::new(Health::new()));
+            if let Some(address) = health {
+                let server = HealthServer::bind(address, Arc::clone(&health_state))
+                    .await?
+                    .with_stale_after(
+                        Duration::try_from_secs_f64(stale_after).map_err(io::Error::other)?,
+                    );
+                log::info!(address:% = server.local_addr()?; "Serving health");
+                tokio::spawn(server.run());
+            }
+            run(config, loaded, health_state).await
+        }
+    }
+}
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
 
-    let mut reader = get_codec(port);
-    let mut msg_count = 0;
-    while let Some(line_result) = reader.next().await {
-        match line_result {
-            Ok(line) => {
-                msg_count += 1;
-                println!("{:6} {:?}", msg_count, line)
+/// Runs the service until all sources have ended or SIGTERM or SIGINT stops it, a new
+/// configuration replaces it on SIGHUP. An invalid configuration or one that fails to
+/// start is reported and the previous one is kept. systemd is notified of the state and
+/// the watchdog is kicked while the service runs.
+#[cfg(unix)]
+async fn run(path: PathBuf, mut config: Config, health: Arc<Mutex<Health>>) -> io::Result<()> {
+    let mut hangup = signal(SignalKind::hangup())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut watchdog = daemon::watchdog_interval().map(tokio::time::interval);
+    let mut service = Service::start_with_health(&config, Arc::clone(&health)).await?;
+    log::info!(config:% = path.display(); "Started");
+    notify("READY=1");
+    loop {
+        tokio::select! {
+            result = service.run() => {
+                notify("STOPPING=1");
+                log::info!("All sources ended");
+                return result;
+            }
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+            _ = tick(&mut watchdog) => notify("WATCHDOG=1"),
+            _ = hangup.recv() => {
+                let reloaded = match Config::load(&path).await {
+                    Ok(reloaded) if reloaded == config => {
+                        log::info!(config:% = path.display(); "Unchanged");
+                        continue;
+                    }
+                    Ok(reloaded) => reloaded,
+                    Err(error) => {
+                        log::error!(config:% = path.display(); "{}, keeping the running configuration", error);
+                        continue;
+                    }
+                };
+                notify("RELOADING=1");
+                service.stop().await?;
+                service = match Service::start_with_health(&reloaded, Arc::clone(&health)).await {
+                    Ok(service) => {
+                        log::info!(config:% = path.display(); "Reloaded");
+                        config = reloaded;
+                        service
+                    }
+                    Err(error) => {
+                        log::error!(config:% = path.display(); "{}, restarting the previous configuration", error);
+                        Service::start_with_health(&config, Arc::clone(&health)).await?
+                    }
+                };
+                notify("READY=1");
             }
-            Err(err) => {
-                println!("{:6} Error {:?}", msg_count, err)
+        }
+    }
+    notify("STOPPING=1");
+    log::info!("Stopping");
+    service.stop().await
+}
+
+#[cfg(not(unix))]
+async fn run(_path: PathBuf, config: Config, health: Arc<Mutex<Health>>) -> io::Result<()> {
+    Service::start_with_health(&config, health)
+        .await?
+        .run()
+        .await
+}
+
+/// Ticks of the watchdog interval, never if the watchdog is disabled.
+#[cfg(unix)]
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Failing notifications are logged, the service keeps running.
+#[cfg(unix)]
+fn notify(state: &str) {
+    if let Err(error) = daemon::notify(state) {
+        log::warn!(state = state; "Notifying the service manager failed: {}", error);
+    }
+}
+
+async fn monitor(mut stream: MsgStream) -> io::Result<()> {
+    while let Some(result) = stream.next().await {
+        let time = iso8601(now());
+        // only the time of day
+        let time = &time[11..23];
+        match result {
+            Ok(msg) => {
+                let state = match msg.chksum_valid() {
+                    Some(true) => "ok",
+                    Some(false) => "BAD",
+                    None => "-",
+                };
+                println!(
+                    "{} {:<2} {:<6} {:<3} {}",
+                    time,
+                    msg.talker(),
+                    msg.msgtype(),
+                    state,
+                    msg.params().join(",")
+                );
             }
+            Err(error) => println!("{} error {}", time, error),
         }
     }
+    Ok(())
+}
 
+async fn stats(mut stream: MsgStream, interval: u64) -> io::Result<()> {
+    let mut stats = Stats::new();
+    let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+    // the first tick completes immediately
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            result = stream.next() => match result {
+                Some(result) => stats.update(&result),
+                None => break,
+            },
+            _ = ticker.tick() => println!("{}\n", stats),
+        }
+    }
+    println!("{}", stats);
     Ok(())
 }
+
+// the source is only part of NDJSON records
+#[cfg_attr(not(feature = "serde"), allow(unused_variables))]
+async fn convert(
+    mut stream: MsgStream,
+    source: &str,
+    format: ConvertFormat,
+    mut writer: Box<dyn AsyncWrite + Unpin>,
+) -> io::Result<()> {
+    while let Some(result) = stream.next().await {
+        let msg: Nmea0183Msg = match result {
+            Ok(msg) => msg,
+            Err(_) => continue,
+        };
+        let line = match format {
+            ConvertFormat::Json => to_json(&msg),
+            ConvertFormat::Csv => {
+                let mut fields = vec![
+                    msg.talker().to_string(),
+                    msg.msgtype().to_string(),
+                    msg.chksum_valid()
+                        .map(|valid| valid.to_string())
+                        .unwrap_or_default(),
+                ];
+                fields.extend(msg.params().iter().map(|param| csv_field(param)));
+                fields.join(",")
+            }
+            // written by the track exporters
+            ConvertFormat::Gpx | ConvertFormat::Kml | ConvertFormat::Geojson => continue,
+            #[cfg(feature = "serde")]
+            ConvertFormat::Ndjson => {
+                nmea0183_feed::ndjson::Record::new(&msg, source, now()).to_line()
+            }
+        };
+        writer.write_all(line.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+    }
+    writer.flush().await
+}
@@ -0,0 +1,308 @@
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::collections::HashMap;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+type SourceStream = Pin<Box<dyn Stream<Item = Result<Nmea0183Msg, io::Error>> + Send>>;
+type FilterFn = Box<dyn Fn(&Nmea0183Msg) -> bool + Send + Sync>;
+
+/// A message tagged with the id of the source it was received from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxMsg {
+    pub source: String,
+    pub msg: Nmea0183Msg,
+}
+
+#[derive(Debug)]
+pub struct MuxError {
+    pub source: String,
+    pub error: io::Error,
+}
+
+struct Source {
+    id: String,
+    stream: SourceStream,
+    filter: Option<FilterFn>,
+}
+
+/// Sentence ids, e.g. `RMC` and `GGA`, that are only forwarded from the most preferred
+/// source that is not stale.
+struct Priority {
+    msgtypes: Vec<String>,
+    sources: Vec<String>,
+    stale_after: Duration,
+    last_seen: HashMap<String, Instant>,
+}
+
+impl Priority {
+    fn accept(&mut self, source: &str, msgtype: &str, now: Instant) -> bool {
+        if !self.msgtypes.iter().any(|entry| entry == msgtype) {
+            return true;
+        }
+        self.last_seen.insert(source.to_string(), now);
+        // sources not in the list rank below all listed sources
+        let rank = self
+            .sources
+            .iter()
+            .position(|entry| entry == source)
+            .unwrap_or(self.sources.len());
+        !self.sources[..rank].iter().any(|preferred| {
+            self.last_seen
+                .get(preferred)
+                .is_some_and(|last_seen| now.duration_since(*last_seen) < self.stale_after)
+        })
+    }
+}
+
+/// Merges several sources into one stream. Sources are polled round robin so a busy
+/// source cannot starve the others, sources that end are dropped and the mux ends when
+/// all sources have ended.
+#[derive(Default)]
+pub struct NmeaMux {
+    sources: Vec<Source>,
+    next: usize,
+    priorities: Vec<Priority>,
+}
+
+impl NmeaMux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a stream of decoded sentences, e.g. from [`crate::get_codec`]. Decode errors
+    /// are yielded but do not end the source.
+    pub fn add_source<S>(&mut self, id: &str, stream: S)
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Send + Unpin + 'static,
+    {
+        self.sources.push(Source {
+            id: id.to_string(),
+            stream: Box::pin(ResumeOnError::new(stream)),
+            filter: None,
+        });
+    }
+
+    pub fn with_source<S>(mut self, id: &str, stream: S) -> Self
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Send + Unpin + 'static,
+    {
+        self.add_source(id, stream);
+        self
+    }
+
+    /// Adds a stream of messages that cannot fail.
+    pub fn add_msg_source<S>(&mut self, id: &str, stream: S)
+    where
+        S: Stream<Item = Nmea0183Msg> + Send + Unpin + 'static,
+    {
+        self.add_source(id, stream.map(Ok));
+    }
+
+    pub fn with_msg_source<S>(mut self, id: &str, stream: S) -> Self
+    where
+        S: Stream<Item = Nmea0183Msg> + Send + Unpin + 'static,
+    {
+        self.add_msg_source(id, stream);
+        self
+    }
+
+    /// Only messages of the source for which `filter` returns `true` are forwarded.
+    /// Returns `false` if there is no source with the id.
+    pub fn set_filter<F>(&mut self, id: &str, filter: F) -> bool
+    where
+        F: Fn(&Nmea0183Msg) -> bool + Send + Sync + 'static,
+    {
+        match self.sources.iter_mut().find(|source| source.id == id) {
+            Some(source) => {
+                source.filter = Some(Box::new(filter));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn with_filter<F>(mut self, id: &str, filter: F) -> Self
+    where
+        F: Fn(&Nmea0183Msg) -> bool + Send + Sync + 'static,
+    {
+        self.set_filter(id, filter);
+        self
+    }
+
+    /// Forwards the sentence ids in `msgtypes` only from the first source in `sources`
+    /// that sent one of them within `stale_after`, e.g. prefer `GPS1` positions and fall
+    /// back to `GPS2` when `GPS1` goes quiet.
+    pub fn set_priority(&mut self, msgtypes: &[&str], sources: &[&str], stale_after: Duration) {
+        self.priorities.push(Priority {
+            msgtypes: msgtypes.iter().map(|msgtype| msgtype.to_string()).collect(),
+            sources: sources.iter().map(|source| source.to_string()).collect(),
+            stale_after,
+            last_seen: HashMap::new(),
+        });
+    }
+
+    pub fn with_priority(
+        mut self,
+        msgtypes: &[&str],
+        sources: &[&str],
+        stale_after: Duration,
+    ) -> Self {
+        self.set_priority(msgtypes, sources, stale_after);
+        self
+    }
+
+    pub fn source_ids(&self) -> impl Iterator<Item = &str> {
+        self.sources.iter().map(|source| source.id.as_str())
+    }
+
+    fn accept(&mut self, idx: usize, msg: &Nmea0183Msg, now: Instant) -> bool {
+        let source = &self.sources[idx];
+        if let Some(filter) = &source.filter {
+            if !filter(msg) {
+                return false;
+            }
+        }
+        // every priority group has to see the message to keep track of stale sources
+        let mut accept = true;
+        for priority in self.priorities.iter_mut() {
+            accept &= priority.accept(&source.id, msg.msgtype(), now);
+        }
+        accept
+    }
+
+    /// Writes all messages to `writer`, e.g. a [`tokio_util::codec::FramedWrite`] or a
+    /// [`crate::udp::UdpSink`], until all sources have ended. Source errors are skipped.
+    pub async fn forward<W>(mut self, mut writer: W) -> io::Result<()>
+    where
+        W: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+    {
+        while let Some(result) = self.next().await {
+            if let Ok(mux_msg) = result {
+                writer.send(mux_msg.msg).await?;
+            }
+        }
+        writer.close().await
+    }
+}
+
+impl Stream for NmeaMux {
+    type Item = Result<MuxMsg, MuxError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut polled = 0;
+        let mut filtered = false;
+        while polled < this.sources.len() {
+            let idx = this.next % this.sources.len();
+            match this.sources[idx].stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(msg))) => {
+                    if this.accept(idx, &msg, Instant::now()) {
+                        this.next = idx + 1;
+                        return Poll::Ready(Some(Ok(MuxMsg {
+                            source: this.sources[idx].id.clone(),
+                            msg,
+                        })));
+                    }
+                    // the source may have more, but give the others a turn first
+                    this.next = idx + 1;
+                    polled += 1;
+                    filtered = true;
+                }
+                Poll::Ready(Some(Err(error))) => {
+                    this.next = idx + 1;
+                    return Poll::Ready(Some(Err(MuxError {
+                        source: this.sources[idx].id.clone(),
+                        error,
+                    })));
+                }
+                Poll::Ready(None) => {
+                    this.sources.remove(idx);
+                    this.next = idx;
+                }
+                Poll::Pending => {
+                    this.next = idx + 1;
+                    polled += 1;
+                }
+            }
+        }
+        if this.sources.is_empty() {
+            Poll::Ready(None)
+        } else {
+            if filtered {
+                // a filtered message consumed the turn without registering a waker
+                cx.waker().wake_by_ref();
+            }
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    fn msgs(sentences: &[&str]) -> Vec<Nmea0183Msg> {
+        sentences
+            .iter()
+            .map(|sentence| sentence.parse().expect("failed to parse message"))
+            .collect()
+    }
+
+    const ZDA: &str = "$GPZDA,160012.71,11,03,2004,-1,00*7D";
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E";
+    const VTG: &str = "$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09";
+
+    #[test]
+    fn test_merge() {
+        tokio_test::block_on(async {
+            let mux = NmeaMux::new()
+                .with_msg_source("gps", stream::iter(msgs(&[ZDA, ZDA, ZDA])))
+                .with_msg_source("log", stream::iter(msgs(&[VTG])))
+                .with_source("ais", stream::iter(vec![Ok(msgs(&[RMC]).remove(0))]));
+            let sources = mux
+                .map(|result| result.unwrap().source)
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(sources, ["gps", "log", "ais", "gps", "gps"]);
+        })
+    }
+
+    #[test]
+    fn test_filter() {
+        tokio_test::block_on(async {
+            let mux = NmeaMux::new()
+                .with_msg_source("gps", stream::iter(msgs(&[ZDA, RMC, VTG])))
+                .with_filter("gps", |msg| msg.msgtype() != "RMC");
+            let msgtypes = mux
+                .map(|result| result.unwrap().msg.msgtype().to_string())
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(msgtypes, ["ZDA", "VTG"]);
+        })
+    }
+
+    #[test]
+    fn test_priority() {
+        let mut mux = NmeaMux::new()
+            .with_msg_source("gps1", stream::empty())
+            .with_msg_source("gps2", stream::empty())
+            .with_priority(&["RMC"], &["gps1", "gps2"], Duration::from_secs(2));
+        let rmc = &msgs(&[RMC])[0];
+        let zda = &msgs(&[ZDA])[0];
+        let start = Instant::now();
+
+        assert!(mux.accept(1, rmc, start));
+        assert!(mux.accept(0, rmc, start));
+        // gps1 is fresh, gps2 positions are dropped but other sentences pass
+        assert!(!mux.accept(1, rmc, start + Duration::from_secs(1)));
+        assert!(mux.accept(1, zda, start + Duration::from_secs(1)));
+        // gps1 went stale
+        assert!(mux.accept(1, rmc, start + Duration::from_secs(3)));
+        assert!(mux.accept(0, rmc, start + Duration::from_secs(4)));
+        assert!(!mux.accept(1, rmc, start + Duration::from_secs(4)));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::sentence::{
+    iso8601, Dbt, Dpt, Gga, Gll, Gsa, Gsv, Hsc, Osd, Rmc, Rot, Rpm, Rsa, Sentence, Ttm, Vbw, Vhw,
+    Vlw, Xdr, Zda,
+};
+use crate::tag_block::TagBlock;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+/// Incremented on incompatible changes of [`Record`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Typed sentences as `{"type":"RMC","data":{...}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "UPPERCASE")]
+pub enum Decoded {
+    Dbt(Dbt),
+    Dpt(Dpt),
+    Gga(Gga),
+    Gll(Gll),
+    Gsa(Gsa),
+    Gsv(Gsv),
+    Hsc(Hsc),
+    Osd(Osd),
+    Rmc(Rmc),
+    Rot(Rot),
+    Rpm(Rpm),
+    Rsa(Rsa),
+    Ttm(Ttm),
+    Vbw(Vbw),
+    Vhw(Vhw),
+    Vlw(Vlw),
+    Xdr(Xdr),
+    Zda(Zda),
+}
+
+impl Decoded {
+    /// `None` for message types without typed decoder.
+    pub fn decode(msg: &Nmea0183Msg) -> Option<Result<Self, String>> {
+        Some(match msg.msgtype() {
+            Dbt::MSGTYPE => Dbt::try_from(msg).map(Decoded::Dbt),
+            Dpt::MSGTYPE => Dpt::try_from(msg).map(Decoded::Dpt),
+            Gga::MSGTYPE => Gga::try_from(msg).map(Decoded::Gga),
+            Gll::MSGTYPE => Gll::try_from(msg).map(Decoded::Gll),
+            Gsa::MSGTYPE => Gsa::try_from(msg).map(Decoded::Gsa),
+            Gsv::MSGTYPE => Gsv::try_from(msg).map(Decoded::Gsv),
+            Hsc::MSGTYPE => Hsc::try_from(msg).map(Decoded::Hsc),
+            Osd::MSGTYPE => Osd::try_from(msg).map(Decoded::Osd),
+            Rmc::MSGTYPE => Rmc::try_from(msg).map(Decoded::Rmc),
+            Rot::MSGTYPE => Rot::try_from(msg).map(Decoded::Rot),
+            Rpm::MSGTYPE => Rpm::try_from(msg).map(Decoded::Rpm),
+            Rsa::MSGTYPE => Rsa::try_from(msg).map(Decoded::Rsa),
+            Ttm::MSGTYPE => Ttm::try_from(msg).map(Decoded::Ttm),
+            Vbw::MSGTYPE => Vbw::try_from(msg).map(Decoded::Vbw),
+            Vhw::MSGTYPE => Vhw::try_from(msg).map(Decoded::Vhw),
+            Vlw::MSGTYPE => Vlw::try_from(msg).map(Decoded::Vlw),
+            Xdr::MSGTYPE => Xdr::try_from(msg).map(Decoded::Xdr),
+            Zda::MSGTYPE => Zda::try_from(msg).map(Decoded::Zda),
+            _ => return None,
+        })
+    }
+
+    /// Encodes the sentence with a valid checksum.
+    pub fn to_msg(&self, talker: &str) -> Nmea0183Msg {
+        match self {
+            Decoded::Dbt(sentence) => sentence.to_msg(talker),
+            Decoded::Dpt(sentence) => sentence.to_msg(talker),
+            Decoded::Gga(sentence) => sentence.to_msg(talker),
+            Decoded::Gll(sentence) => sentence.to_msg(talker),
+            Decoded::Gsa(sentence) => sentence.to_msg(talker),
+            Decoded::Gsv(sentence) => sentence.to_msg(talker),
+            Decoded::Hsc(sentence) => sentence.to_msg(talker),
+            Decoded::Osd(sentence) => sentence.to_msg(talker),
+            Decoded::Rmc(sentence) => sentence.to_msg(talker),
+            Decoded::Rot(sentence) => sentence.to_msg(talker),
+            Decoded::Rpm(sentence) => sentence.to_msg(talker),
+            Decoded::Rsa(sentence) => sentence.to_msg(talker),
+            Decoded::Ttm(sentence) => sentence.to_msg(talker),
+            Decoded::Vbw(sentence) => sentence.to_msg(talker),
+            Decoded::Vhw(sentence) => sentence.to_msg(talker),
+            Decoded::Vlw(sentence) => sentence.to_msg(talker),
+            Decoded::Xdr(sentence) => sentence.to_msg(talker),
+            Decoded::Zda(sentence) => sentence.to_msg(talker),
+        }
+    }
+}
+
+/// One NDJSON line per received sentence, e.g.
+/// `{"schema":1,"received":"2024-...Z","source":"gps","sentence":"$GPZDA,...*7D",
+/// "checksum_valid":true,"talker":"GP","msgtype":"ZDA","decoded":{"type":"ZDA","data":{...}}}`.
+/// `tag_block` and `decoded` are left out when missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub schema: u32,
+    /// Receive time as ISO 8601.
+    pub received: String,
+    pub source: String,
+    /// The sentence without tag block and with the checksum as received, so it agrees
+    /// with `checksum_valid`.
+    pub sentence: String,
+    pub checksum_valid: Option<bool>,
+    pub talker: String,
+    pub msgtype: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_block: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<Decoded>,
+}
+
+impl Record {
+    /// `received` in seconds since the unix epoch.
+    pub fn new(msg: &Nmea0183Msg, source: &str, received: f64) -> Self {
+        let mut sentence = msg.clone();
+        sentence.set_tag_block(None);
+        Self {
+            schema: SCHEMA_VERSION,
+            received: iso8601(received),
+            source: source.to_string(),
+            sentence: sentence.to_string(),
+            checksum_valid: msg.chksum_valid(),
+            talker: msg.talker().to_string(),
+            msgtype: msg.msgtype().to_string(),
+            tag_block: msg.tag_block().map(TagBlock::to_string),
+            // corrupted sentences are only kept as received
+            decoded: match msg.chksum_valid() {
+                Some(false) => None,
+                _ => Decoded::decode(msg).and_then(Result::ok),
+            },
+        }
+    }
+
+    pub fn to_line(&self) -> String {
+        serde_json::to_string(self).expect("records serialize to JSON")
+    }
+
+    pub fn from_line(line: &str) -> Result<Self, String> {
+        serde_json::from_str(line).map_err(|error| format!("Invalid record: {}", error))
+    }
+
+    /// Encodes `decoded` if present and the checksum was not invalid, otherwise parses
+    /// `sentence`. The tag block is kept.
+    pub fn to_msg(&self) -> Result<Nmea0183Msg, String> {
+        let mut msg = match &self.decoded {
+            Some(decoded) if self.checksum_valid != Some(false) => decoded.to_msg(&self.talker),
+            _ => self.sentence.parse()?,
+        };
+        if let Some(tag_block) = &self.tag_block {
+            msg.set_tag_block(Some(tag_block.parse()?));
+        }
+        Ok(msg)
+    }
+}
+
+/// Writes one record per decoded sentence of `stream`, e.g. from [`crate::get_codec`],
+/// until it ends. Decode errors are skipped.
+pub async fn write_ndjson<S, W>(stream: S, source: &str, mut writer: W) -> io::Result<()>
+where
+    S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut stream = ResumeOnError::new(stream);
+    while let Some(result) = stream.next().await {
+        if let Ok(msg) = result {
+            let received = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64();
+            let mut line = Record::new(&msg, source, received).to_line();
+            line.push('\n');
+            writer.write_all(line.as_bytes()).await?;
+        }
+    }
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E";
+
+    #[test]
+    fn test_record() {
+        let mut msg: Nmea0183Msg = RMC.parse().unwrap();
+        msg.set_tag_block(Some("\\s:GP0001,n:7*10\\".parse().unwrap()));
+        let record = Record::new(&msg, "gps", 1396205346.5);
+        let line = record.to_line();
+        assert!(line.starts_with(
+            r#"{"schema":1,"received":"2014-03-30T18:49:06.500Z","source":"gps","sentence":"$GPRMC,"#
+        ), "{}", line);
+        assert!(line.contains(r#""tag_block":"\\s:GP0001,n:7*10\\","decoded":{"type":"RMC","data":{"time":{"hour":18,"minute":49,"second":6.0},"#));
+
+        let parsed = Record::from_line(&line).unwrap();
+        assert_eq!(parsed, record);
+        let encoded = parsed.to_msg().unwrap();
+        assert_eq!(encoded.tag_block(), msg.tag_block());
+        assert_eq!(
+            Rmc::try_from(&encoded).unwrap(),
+            Rmc::try_from(&msg).unwrap()
+        );
+
+        let mut record: Record = Record::from_line(
+            r#"{"schema":1,"received":"","source":"","sentence":"$GPZDA,160012.71,11,03,2004,-1,00*7D","checksum_valid":true,"talker":"GP","msgtype":"ZDA"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            record.to_msg().unwrap().to_string(),
+            "$GPZDA,160012.71,11,03,2004,-1,00*7D"
+        );
+        record.decoded = Some(Decoded::Dpt(Dpt {
+            depth: Some(10.7),
+            offset: Some(-1.2),
+            range: None,
+        }));
+        record.talker = "SD".to_string();
+        assert!(record
+            .to_msg()
+            .unwrap()
+            .to_string()
+            .starts_with("$SDDPT,10.7,-1.2"));
+
+        let invalid: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*00".parse().unwrap();
+        let mut record = Record::new(&invalid, "gps", 0.0);
+        assert_eq!(record.sentence, "$GPZDA,160012.71,11,03,2004,-1,00*00");
+        assert_eq!(record.checksum_valid, Some(false));
+        assert_eq!(record.decoded, None);
+        // not re-encoded with a valid checksum
+        record.decoded = Decoded::decode(&invalid).and_then(Result::ok);
+        assert_eq!(record.to_msg().unwrap(), invalid);
+    }
+
+    #[test]
+    fn test_deserialize_msg() {
+        let msg: Nmea0183Msg = RMC.parse().unwrap();
+        let json = serde_json::to_string(&msg).unwrap();
+        assert_eq!(serde_json::from_str::<Nmea0183Msg>(&json).unwrap(), msg);
+        // the validity is recalculated
+        let json = json.replace(r#""chksum":"7E""#, r#""chksum":"00""#);
+        let invalid = serde_json::from_str::<Nmea0183Msg>(&json).unwrap();
+        assert_eq!(invalid.chksum_valid(), Some(false));
+        assert_eq!(invalid.chksum(), "00");
+        let json = json.replace(r#""chksum":"00""#, r#""chksum":"+0""#);
+        assert!(serde_json::from_str::<Nmea0183Msg>(&json).is_err());
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        tokio_test::block_on(async {
+            let stream = futures::stream::iter(vec![
+                Ok(RMC.parse::<Nmea0183Msg>().unwrap()),
+                Err(io::Error::other("Invalid event")),
+            ]);
+            let mut output = Vec::new();
+            write_ndjson(stream, "file", &mut output).await.unwrap();
+            let output = String::from_utf8(output).unwrap();
+            assert_eq!(output.lines().count(), 1);
+            assert_eq!(Record::from_line(output.trim_end()).unwrap().msgtype, "RMC");
+        })
+    }
+}
@@ -3,7 +3,7 @@ use crate::Nmea0183Msg;
 use bytes::BytesMut;
 use tokio_util::codec::{Decoder, Encoder};
 
-mod context;
+pub(crate) mod context;
 
 pub struct Nmea0183Codec {
     ctx: StateMachine,
@@ -19,6 +19,25 @@ impl Default for Nmea0183Codec {
     }
 }
 
+impl Nmea0183Codec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes sentences of instruments that do not follow the standard: LF without CR,
+    /// lowercase, incomplete or missing checksums are accepted and characters outside of
+    /// printable ASCII as well as empty lines are skipped. Invalid checksums are reported
+    /// as `chksum_valid() == Some(false)` in both modes, missing ones as `None`.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.ctx = StateMachine::new().with_lenient(lenient);
+        self
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.ctx.is_lenient()
+    }
+}
+
 impl Decoder for Nmea0183Codec {
     type Item = Nmea0183Msg;
     type Error = std::io::Error;
@@ -28,37 +47,39 @@ impl Decoder for Nmea0183Codec {
         // eprintln!("decode({}, offset: {})", to_string(src), offset);
         loop {
             let offset = self.ctx.get_event_count();
-            let position =
-                src[offset..]
-                    .as_ref()
-                    .iter()
-                    .position(|b| match self.ctx.handle_event(b) {
-                        Ok(result) => {
-                            if let Some(result) = result {
-                                rc = Ok(Some(result));
-                                true
-                            } else {
-                                false
-                            }
-                        }
-                        Err(error) => {
-                            rc = Err(std::io::Error::new(std::io::ErrorKind::Other, error));
+            let position = src[offset..]
+                .iter()
+                .position(|b| match self.ctx.handle_event(b) {
+                    Ok(result) => {
+                        if let Some(result) = result {
+                            rc = Ok(Some(result));
                             true
+                        } else {
+                            false
                         }
-                    });
+                    }
+                    Err(error) => {
+                        rc = Err(std::io::Error::other(error));
+                        true
+                    }
+                });
 
             if let Some(position) = position {
                 // we only get here if we have a result or an error
                 _ = src.split_to(offset + position + 1);
-                if self.first == true {
+                if self.first {
                     self.first = false;
                     if rc.is_err() {
                         // this must be an error
-                        eprintln!("decode() ignoring first error: {:?}", rc);
+                        log::debug!("decode() ignoring first error: {:?}", rc);
                         rc = Ok(None);
                         continue;
                     }
                 }
+            } else if self.ctx.is_idle() {
+                // only skipped characters, nothing to keep for the next call
+                src.clear();
+                self.ctx.reset();
             }
             break;
         }
@@ -66,9 +87,29 @@ impl Decoder for Nmea0183Codec {
     }
 }
 
+/// Writes a preformatted sentence, CR LF is appended if missing.
 impl Encoder<String> for Nmea0183Codec {
     type Error = std::io::Error;
-    fn encode(&mut self, _item: String, _dst: &mut BytesMut) -> Result<(), Self::Error> {
+    fn encode(&mut self, item: String, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(item.as_bytes());
+        if !item.ends_with("\r\n") {
+            dst.extend_from_slice(b"\r\n");
+        }
+        Ok(())
+    }
+}
+
+impl Encoder<Nmea0183Msg> for Nmea0183Codec {
+    type Error = std::io::Error;
+    fn encode(&mut self, item: Nmea0183Msg, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode(&item, dst)
+    }
+}
+
+impl Encoder<&Nmea0183Msg> for Nmea0183Codec {
+    type Error = std::io::Error;
+    fn encode(&mut self, item: &Nmea0183Msg, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(format!("{}\r\n", item).as_bytes());
         Ok(())
     }
 }
@@ -1,16 +1,18 @@
 use crate::nmea0183_codec::context::state::{
-    Checksum, Invalid, Linefeed, MsgType, Params, Start, State, Talker, LF,
+    Checksum, Invalid, Linefeed, MsgType, Params, Start, State, TagBlock, Talker, CR, LF,
 };
 use crate::Nmea0183Msg;
 use std::mem::take;
-use std::rc::Rc;
+use std::sync::Arc;
 
 mod state;
 
 const MAX_MSG_SIZE: usize = 82;
+// vendors like u-blox exceed the NMEA limit with their proprietary sentences
+const MAX_PROPRIETARY_MSG_SIZE: usize = 256;
 
 pub struct StateMachine {
-    current_state: Rc<Box<dyn State>>,
+    current_state: Arc<Box<dyn State>>,
     inner: InnerContext,
 }
 
@@ -19,11 +21,38 @@ impl StateMachine {
         let inner = InnerContext::new();
 
         Self {
-            current_state: Rc::clone(&inner.states.start),
+            current_state: Arc::clone(&inner.states.start),
             inner,
         }
     }
 
+    /// Accepts LF without CR, lowercase and incomplete checksums and skips illegal
+    /// characters as well as empty lines.
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.inner.lenient = lenient;
+        self
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.inner.lenient
+    }
+
+    /// Characters outside of printable ASCII, skipped in lenient mode.
+    fn is_illegal(event: &u8) -> bool {
+        !matches!(*event, b' '..=b'~' | CR | LF)
+    }
+
+    /// No sentence started yet, only skipped characters were seen.
+    pub fn is_idle(&self) -> bool {
+        Arc::ptr_eq(&self.current_state, &self.inner.states.start)
+            && self.inner.msg.tag_block.is_none()
+    }
+
+    pub fn reset(&mut self) {
+        self.inner.reset();
+        self.current_state = Arc::clone(&self.inner.states.start);
+    }
+
     pub fn handle_event(&mut self, event: &u8) -> Result<Option<Nmea0183Msg>, String> {
         /*eprintln!(
             "handle_event({}) in state {}",
@@ -31,14 +60,26 @@ impl StateMachine {
             self.current_state.name()
         );*/
         self.inner.event_count += 1;
-        if self.inner.event_count > MAX_MSG_SIZE {
+        if self.inner.lenient
+            && (Self::is_illegal(event) || self.is_idle() && event.is_ascii_whitespace())
+        {
+            self.inner.skipped += 1;
+            return Ok(None);
+        }
+        let max_msg_size = if self.inner.msg.is_proprietary() {
+            MAX_PROPRIETARY_MSG_SIZE
+        } else {
+            MAX_MSG_SIZE
+        };
+        // the tag block and skipped characters do not count towards the sentence length
+        if self.inner.event_count - self.inner.tag_block_len - self.inner.skipped > max_msg_size {
             let result = if self.inner.error.is_empty() {
                 Err("Message too long".to_string())
             } else {
-                Err(format!("{} + Message too long", &self.inner.error))
+                Err(format!("{} + Message too long", self.inner.error))
             };
             self.inner.reset();
-            self.current_state = Rc::clone(&self.inner.states.start);
+            self.current_state = Arc::clone(&self.inner.states.start);
             result
         } else {
             self.current_state = self.current_state.handle_event(event, &mut self.inner);
@@ -49,7 +90,7 @@ impl StateMachine {
                     Err(take(&mut self.inner.error))
                 };
                 self.inner.reset();
-                self.current_state = Rc::clone(&self.inner.states.start);
+                self.current_state = Arc::clone(&self.inner.states.start);
                 result
             } else {
                 Ok(None)
@@ -63,6 +104,9 @@ impl StateMachine {
 
 struct InnerContext {
     event_count: usize,
+    tag_block_len: usize,
+    skipped: usize,
+    lenient: bool,
     error: String,
     msg: Nmea0183Msg,
     states: StateList,
@@ -74,6 +118,9 @@ impl InnerContext {
     fn new() -> Self {
         Self {
             event_count: 0,
+            tag_block_len: 0,
+            skipped: 0,
+            lenient: false,
             error: String::new(),
             msg: Nmea0183Msg::default(),
             states: StateList::new(),
@@ -85,31 +132,36 @@ impl InnerContext {
     fn reset(&mut self) {
         self.msg = Nmea0183Msg::default();
         self.error.clear();
+        self.collect.clear();
         self.event_count = 0;
+        self.tag_block_len = 0;
+        self.skipped = 0;
         self.chksum = 0;
     }
 }
 
 struct StateList {
-    start: Rc<Box<dyn State>>,
-    talker: Rc<Box<dyn State>>,
-    invalid: Rc<Box<dyn State>>,
-    msgtype: Rc<Box<dyn State>>,
-    params: Rc<Box<dyn State>>,
-    chksum: Rc<Box<dyn State>>,
-    linefeed: Rc<Box<dyn State>>,
+    start: Arc<Box<dyn State>>,
+    tag_block: Arc<Box<dyn State>>,
+    talker: Arc<Box<dyn State>>,
+    invalid: Arc<Box<dyn State>>,
+    msgtype: Arc<Box<dyn State>>,
+    params: Arc<Box<dyn State>>,
+    chksum: Arc<Box<dyn State>>,
+    linefeed: Arc<Box<dyn State>>,
 }
 
 impl StateList {
     fn new() -> Self {
         Self {
-            start: Rc::new(Box::new(Start)),
-            talker: Rc::new(Box::new(Talker)),
-            invalid: Rc::new(Box::new(Invalid)),
-            msgtype: Rc::new(Box::new(MsgType)),
-            params: Rc::new(Box::new(Params)),
-            chksum: Rc::new(Box::new(Checksum)),
-            linefeed: Rc::new(Box::new(Linefeed)),
+            start: Arc::new(Box::new(Start)),
+            tag_block: Arc::new(Box::new(TagBlock)),
+            talker: Arc::new(Box::new(Talker)),
+            invalid: Arc::new(Box::new(Invalid)),
+            msgtype: Arc::new(Box::new(MsgType)),
+            params: Arc::new(Box::new(Params)),
+            chksum: Arc::new(Box::new(Checksum)),
+            linefeed: Arc::new(Box::new(Linefeed)),
         }
     }
 }
@@ -172,13 +224,14 @@ mod tests {
                         match ctx.handle_event(&LF) {
                             Ok(res) => {
                                 assert!(
-                                    res.expect(
-                                        format!("empty result @{}, {}", idx + 1, line).as_str()
-                                    )
+                                    res.unwrap_or_else(|| panic!(
+                                        "empty result @{}, {}",
+                                        idx + 1,
+                                        line
+                                    ))
                                     .chksum_valid
-                                    .expect(
-                                        format!("checksum not calculated @{} {}", idx + 1, line)
-                                            .as_str()
+                                    .unwrap_or_else(
+                                        || panic!("checksum not calculated @{} {}", idx + 1, line)
                                     ),
                                     "checksum does not match @{} {}",
                                     idx + 1,
@@ -198,11 +251,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lenient_tag_block() {
+        let mut ctx = StateMachine::new().with_lenient(true);
+        let input = b"\r\n\\s:GP0001*2F\\$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n";
+        let msgs = input
+            .iter()
+            .filter_map(|event| ctx.handle_event(event).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 1);
+        assert!(msgs[0].tag_block().is_some());
+        assert_eq!(msgs[0].chksum_valid(), Some(true));
+    }
+
     #[bench]
     fn bench_data(b: &mut Bencher) {
         const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
-        let test_data =
-            read_to_string(TEST_FILE).expect(format!("failed to open file {}", TEST_FILE).as_str());
+        let test_data = read_to_string(TEST_FILE)
+            .unwrap_or_else(|_| panic!("failed to open file {}", TEST_FILE));
 
         b.iter(|| {
             let mut ctx = StateMachine::new();
@@ -3,8 +3,9 @@
 // use std::rc::Rc;
 
 use crate::nmea0183_codec::context::InnerContext;
+use crate::tag_block;
 use std::mem::take;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub const LF: u8 = 0xA;
 pub const CR: u8 = 0xD;
@@ -12,12 +13,17 @@ pub const AST: u8 = b'*';
 pub const XCL: u8 = b'!';
 pub const START: u8 = b'$';
 pub const FIELD: u8 = b',';
+pub const PROPRIETARY: u8 = b'P';
+// manufacturer mnemonic + sentence id of proprietary sentences
+const MAX_PROPRIETARY_TYPE: usize = 9;
+pub const TAG: u8 = b'\\';
+const MAX_TAG_BLOCK_SIZE: usize = 80;
 //const RES: u8 = b'~';
-// const TAG: u8 = b'\\';
 // const HEX: u8 = b'^';
 
-pub trait State {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>>;
+// states are shared between threads when the codec is moved into a task
+pub trait State: Send + Sync {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>>;
     fn is_term(&self) -> bool {
         false
     }
@@ -26,13 +32,14 @@ pub trait State {
 
 pub struct Start;
 impl State for Start {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
         match *event {
             XCL => {
                 ctx.msg.encapsulation = true;
-                Rc::clone(&ctx.states.talker)
+                Arc::clone(&ctx.states.talker)
             }
-            START => Rc::clone(&ctx.states.talker),
+            START => Arc::clone(&ctx.states.talker),
+            TAG if ctx.msg.tag_block.is_none() => Arc::clone(&ctx.states.tag_block),
             _ => {
                 ctx.error = format!(
                     "Invalid event {} @{} in state {}",
@@ -40,7 +47,7 @@ impl State for Start {
                     ctx.event_count,
                     self.name()
                 );
-                Rc::clone(&ctx.states.invalid)
+                Arc::clone(&ctx.states.invalid)
             }
         }
     }
@@ -50,19 +57,72 @@ impl State for Start {
     }
 }
 
+pub struct TagBlock;
+
+impl State for TagBlock {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
+        match *event {
+            TAG => match take(&mut ctx.collect).parse::<tag_block::TagBlock>() {
+                Ok(tag_block) => {
+                    ctx.msg.tag_block = Some(tag_block);
+                    // includes the characters skipped so far
+                    ctx.tag_block_len = ctx.event_count;
+                    ctx.skipped = 0;
+                    Arc::clone(&ctx.states.start)
+                }
+                Err(error) => {
+                    ctx.error = format!("{} @{} in state {}", error, ctx.event_count, self.name());
+                    Arc::clone(&ctx.states.invalid)
+                }
+            },
+            CR | LF => {
+                ctx.error = format!(
+                    "Invalid event {} @{} in state {}",
+                    byte_2_print(event),
+                    ctx.event_count,
+                    self.name()
+                );
+                Arc::clone(&ctx.states.invalid)
+            }
+            _ => {
+                if ctx.collect.len() < MAX_TAG_BLOCK_SIZE {
+                    ctx.collect.push(*event as char);
+                    Arc::clone(&ctx.states.tag_block)
+                } else {
+                    ctx.error = format!(
+                        "Tag block too long @{} in state {}",
+                        ctx.event_count,
+                        self.name()
+                    );
+                    Arc::clone(&ctx.states.invalid)
+                }
+            }
+        }
+    }
+
+    fn name(&self) -> &str {
+        "TagBlock"
+    }
+}
+
 pub struct Talker;
 
 impl State for Talker {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
         match *event {
+            PROPRIETARY if ctx.collect.is_empty() => {
+                ctx.chksum ^= event;
+                ctx.msg.talker = (PROPRIETARY as char).to_string();
+                Arc::clone(&ctx.states.msgtype)
+            }
             b'A'..=b'Z' => {
                 ctx.chksum ^= event;
                 ctx.collect.push(*event as char);
                 if ctx.collect.len() > 1 {
                     ctx.msg.talker = take(&mut ctx.collect);
-                    Rc::clone(&ctx.states.msgtype)
+                    Arc::clone(&ctx.states.msgtype)
                 } else {
-                    Rc::clone(&ctx.states.talker)
+                    Arc::clone(&ctx.states.talker)
                 }
             }
             _ => {
@@ -72,7 +132,7 @@ impl State for Talker {
                     ctx.event_count,
                     self.name()
                 );
-                Rc::clone(&ctx.states.invalid)
+                Arc::clone(&ctx.states.invalid)
             }
         }
     }
@@ -85,13 +145,18 @@ impl State for Talker {
 pub struct MsgType;
 
 impl State for MsgType {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
+        let max_len = if ctx.msg.is_proprietary() {
+            MAX_PROPRIETARY_TYPE
+        } else {
+            3
+        };
         match *event {
-            b'A'..=b'Z' => {
-                if ctx.collect.len() < 3 {
+            b'A'..=b'Z' | b'0'..=b'9' => {
+                if ctx.collect.len() < max_len {
                     ctx.chksum ^= event;
                     ctx.collect.push(*event as char);
-                    Rc::clone(&ctx.states.msgtype)
+                    Arc::clone(&ctx.states.msgtype)
                 } else {
                     ctx.error = format!(
                         "Invalid event @{} in state {}, expected ',' or '*' or CR, got {}",
@@ -99,22 +164,22 @@ impl State for MsgType {
                         self.name(),
                         byte_2_print(event)
                     );
-                    Rc::clone(&ctx.states.invalid)
+                    Arc::clone(&ctx.states.invalid)
                 }
             }
             FIELD => {
-                if ctx.collect.len() == 3 {
+                if ctx.collect.len() >= 3 {
                     ctx.chksum ^= event;
                     ctx.msg.msgtype = take(&mut ctx.collect);
-                    Rc::clone(&ctx.states.params)
+                    Arc::clone(&ctx.states.params)
                 } else {
                     ctx.error = format!(
-                        "Invalid event @{} in state {}, expected 'A'-'Z', got {}",
+                        "Invalid event @{} in state {}, expected 'A'-'Z' or '0'-'9', got {}",
                         ctx.event_count,
                         self.name(),
                         byte_2_print(event)
                     );
-                    Rc::clone(&ctx.states.invalid)
+                    Arc::clone(&ctx.states.invalid)
                 }
             }
             // TODO: handle CR event ?
@@ -125,7 +190,7 @@ impl State for MsgType {
                     ctx.event_count,
                     self.name()
                 );
-                Rc::clone(&ctx.states.invalid)
+                Arc::clone(&ctx.states.invalid)
             }
         }
     }
@@ -137,20 +202,24 @@ impl State for MsgType {
 pub struct Params;
 
 impl State for Params {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
         match *event {
             FIELD => {
                 ctx.chksum ^= event;
                 ctx.msg.params.push(take(&mut ctx.collect));
-                Rc::clone(&ctx.states.params)
+                Arc::clone(&ctx.states.params)
             }
             AST => {
                 ctx.msg.params.push(take(&mut ctx.collect));
-                Rc::clone(&ctx.states.chksum)
+                Arc::clone(&ctx.states.chksum)
             }
             CR => {
                 ctx.msg.params.push(take(&mut ctx.collect));
-                Rc::clone(&ctx.states.linefeed)
+                Arc::clone(&ctx.states.linefeed)
+            }
+            LF if ctx.lenient => {
+                ctx.msg.params.push(take(&mut ctx.collect));
+                Arc::clone(&ctx.states.start)
             }
             LF => {
                 ctx.error = format!(
@@ -159,12 +228,12 @@ impl State for Params {
                     ctx.event_count,
                     self.name()
                 );
-                Rc::clone(&ctx.states.invalid)
+                Arc::clone(&ctx.states.invalid)
             }
             _ => {
                 ctx.chksum ^= event;
                 ctx.collect.push(*event as char);
-                Rc::clone(&ctx.states.params)
+                Arc::clone(&ctx.states.params)
             }
         }
     }
@@ -177,12 +246,14 @@ impl State for Params {
 pub struct Checksum;
 
 impl State for Checksum {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
         match *event {
-            b'A'..=b'F' | b'0'..=b'9' => {
+            b'A'..=b'F' | b'0'..=b'9' | b'a'..=b'f'
+                if ctx.lenient || !event.is_ascii_lowercase() =>
+            {
                 if ctx.collect.len() < 2 {
                     ctx.collect.push(*event as char);
-                    Rc::clone(&ctx.states.chksum)
+                    Arc::clone(&ctx.states.chksum)
                 } else {
                     ctx.error = format!(
                         "Invalid event @{} in state {}, expected CR, got {}",
@@ -190,15 +261,13 @@ impl State for Checksum {
                         self.name(),
                         byte_2_print(event)
                     );
-                    Rc::clone(&ctx.states.invalid)
+                    Arc::clone(&ctx.states.invalid)
                 }
             }
             CR => {
-                if ctx.collect.len() == 2 {
-                    ctx.msg.chksum = take(&mut ctx.collect);
-                    ctx.msg.chksum_valid =
-                        Some(format!("{:02X}", ctx.chksum).eq(ctx.msg.chksum.as_str()));
-                    Rc::clone(&ctx.states.linefeed)
+                if ctx.collect.len() == 2 || ctx.lenient {
+                    finish_chksum(ctx);
+                    Arc::clone(&ctx.states.linefeed)
                 } else {
                     ctx.error = format!(
                         "Invalid event {} @{} in state {}, expected CR",
@@ -206,10 +275,13 @@ impl State for Checksum {
                         ctx.event_count,
                         self.name()
                     );
-                    Rc::clone(&ctx.states.invalid)
+                    Arc::clone(&ctx.states.invalid)
                 }
             }
-
+            LF if ctx.lenient => {
+                finish_chksum(ctx);
+                Arc::clone(&ctx.states.start)
+            }
             _ => {
                 ctx.error = format!(
                     "Invalid event {} @{} in state {}",
@@ -217,7 +289,7 @@ impl State for Checksum {
                     ctx.event_count,
                     self.name()
                 );
-                Rc::clone(&ctx.states.invalid)
+                Arc::clone(&ctx.states.invalid)
             }
         }
     }
@@ -226,12 +298,25 @@ impl State for Checksum {
     }
 }
 
+/// In lenient mode a missing checksum after `*` is reported as `None`, a single digit as
+/// invalid.
+fn finish_chksum(ctx: &mut InnerContext) {
+    ctx.msg.chksum = take(&mut ctx.collect);
+    ctx.msg.chksum_valid = if ctx.msg.chksum.is_empty() {
+        None
+    } else {
+        Some(format!("{:02X}", ctx.chksum).eq_ignore_ascii_case(&ctx.msg.chksum))
+    };
+}
+
 pub struct Linefeed;
 
 impl State for Linefeed {
-    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
+    fn handle_event(&self, event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
         if *event == LF {
-            Rc::clone(&ctx.states.start)
+            Arc::clone(&ctx.states.start)
+        } else if *event == CR && ctx.lenient {
+            Arc::clone(&ctx.states.linefeed)
         } else {
             ctx.error = format!(
                 "Invalid event {} @{} in state {}",
@@ -239,7 +324,7 @@ impl State for Linefeed {
                 ctx.event_count,
                 self.name()
             );
-            Rc::clone(&ctx.states.invalid)
+            Arc::clone(&ctx.states.invalid)
         }
     }
 
@@ -251,8 +336,8 @@ impl State for Linefeed {
 pub struct Invalid;
 
 impl State for Invalid {
-    fn handle_event(&self, _event: &u8, ctx: &mut InnerContext) -> Rc<Box<dyn State>> {
-        Rc::clone(&ctx.states.invalid)
+    fn handle_event(&self, _event: &u8, ctx: &mut InnerContext) -> Arc<Box<dyn State>> {
+        Arc::clone(&ctx.states.invalid)
     }
 
     fn name(&self) -> &str {
@@ -0,0 +1,80 @@
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// A regular expression for matching sentences, see the [`regex`] crate for the syntax.
+/// Matching takes linear time in the length of the sentence for any pattern. Without
+/// anchors a pattern matches anywhere in the text.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    regex: Regex,
+}
+
+impl Pattern {
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+}
+
+/// Patterns are equal if their sources are.
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Regex::new(source)
+            .map(|regex| Self { regex })
+            .map_err(|error| format!("Invalid pattern '{}': {}", source, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern() {
+        let pattern: Pattern = r"^\$(GP|GN)(RMC|GGA),".parse().unwrap();
+        assert!(pattern.is_match("$GPRMC,184906.000,A"));
+        assert!(pattern.is_match("$GNGGA,184906.000"));
+        assert!(!pattern.is_match("$GPGSV,3,2"));
+        assert!(!pattern.is_match("x$GPRMC,"));
+
+        let pattern: Pattern = r",[AV],\d{4}\.\d+,[NS]".parse().unwrap();
+        assert!(pattern.is_match("$GPRMC,184906.000,A,0856.1964,N,07933.3281,W"));
+        assert!(!pattern.is_match("$GPRMC,184906.000,A,,,,"));
+
+        let pattern: Pattern = "^[^,]*,,".parse().unwrap();
+        assert!(pattern.is_match("$GPGLL,,,"));
+        assert!(!pattern.is_match("$GPGLL,0856.1964,N"));
+        assert!("a*b?c+$".parse::<Pattern>().unwrap().is_match("bccc"));
+        assert!("(a*)*b".parse::<Pattern>().unwrap().is_match("aaab"));
+        assert!("x{2,3}y".parse::<Pattern>().unwrap().is_match("xxxy"));
+        assert!(!"^x{2,3}y".parse::<Pattern>().unwrap().is_match("xxxxy"));
+
+        for invalid in ["(RMC", "RMC)", "*", "[a", "x{3,2}"] {
+            assert!(invalid.parse::<Pattern>().is_err(), "{}", invalid);
+        }
+
+        // backtracking would take exponential time
+        let pattern: Pattern = "(a|a)*b".parse().unwrap();
+        let started = std::time::Instant::now();
+        assert!(!pattern.is_match(&"a".repeat(64)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+}
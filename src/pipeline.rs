@@ -0,0 +1,527 @@
+use crate::pattern::Pattern;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Selects sentences by talker, message type and a pattern on the sentence without tag
+/// block, e.g. `$GPRMC,...*7E`. Empty lists and a missing pattern match any sentence.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector {
+    pub talkers: Vec<String>,
+    pub sentences: Vec<String>,
+    pub pattern: Option<Pattern>,
+}
+
+impl Selector {
+    pub fn matches(&self, msg: &Nmea0183Msg) -> bool {
+        (self.talkers.is_empty() || self.talkers.iter().any(|talker| talker == msg.talker()))
+            && (self.sentences.is_empty()
+                || self
+                    .sentences
+                    .iter()
+                    .any(|msgtype| msgtype == msg.msgtype()))
+            && self.pattern.as_ref().is_none_or(|pattern| {
+                let mut sentence = msg.clone();
+                sentence.set_tag_block(None);
+                pattern.is_match(&sentence.to_string())
+            })
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<bool, String> {
+        let list = || value.split(',').map(str::to_string).collect();
+        match key {
+            "talker" => self.talkers = list(),
+            "sentence" => self.sentences = list(),
+            "regex" => self.pattern = Some(value.parse()?),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if !self.talkers.is_empty() {
+            options.push(format!("talker={}", self.talkers.join(",")));
+        }
+        if !self.sentences.is_empty() {
+            options.push(format!("sentence={}", self.sentences.join(",")));
+        }
+        // the pattern takes the rest of the line
+        if let Some(pattern) = &self.pattern {
+            options.push(format!("regex={}", pattern));
+        }
+        f.write_str(&options.join(" "))
+    }
+}
+
+/// A step of a [`Pipeline`], written as one line of a pipeline file:
+///
+/// - `keep talker=GP,GN sentence=RMC,GGA regex=^\$..RMC,\d+` drops all other sentences
+/// - `drop sentence=GSV`
+/// - `throttle interval=1 sentence=RMC` forwards a sentence id, e.g. `GPRMC`, once per
+///   interval in seconds, a sentence up to a tenth of the interval early still counts
+/// - `talker from=GP to=GN`
+/// - `field index=12 value=A sentence=RMC` sets the 1-based field
+/// - `strip-tag-block`
+/// - `checksum` recalculates the checksum, also of sentences received with an invalid one
+///
+/// `regex` must be the last option.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    Keep(Selector),
+    Drop(Selector),
+    Throttle(Selector, Duration),
+    Talker { from: String, to: String },
+    Field(Selector, NonZeroUsize, String),
+    StripTagBlock,
+    Checksum,
+}
+
+/// Splits `key=value` options at whitespace, `regex` takes the rest of the line.
+fn options(line: &str) -> Result<Vec<(&str, &str)>, String> {
+    let mut options = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let option = if rest.starts_with("regex=") {
+            std::mem::take(&mut rest)
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let (option, tail) = rest.split_at(end);
+            rest = tail.trim_start();
+            option
+        };
+        options.push(
+            option
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid option '{}'", option))?,
+        );
+    }
+    Ok(options)
+}
+
+impl FromStr for Stage {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (kind, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let mut selector = Selector::default();
+        let mut values = HashMap::new();
+        for (key, value) in options(rest)? {
+            if !selector.set(key, value)? {
+                values.insert(key, value);
+            }
+        }
+        let mut value = |key: &str| {
+            values
+                .remove(key)
+                .ok_or_else(|| format!("Missing option '{}' in '{}'", key, line))
+        };
+        let stage = match kind {
+            "keep" => Stage::Keep(selector.clone()),
+            "drop" => Stage::Drop(selector.clone()),
+            "throttle" => {
+                let interval = value("interval")?;
+                let interval = interval
+                    .parse()
+                    .ok()
+                    .and_then(|interval| Duration::try_from_secs_f64(interval).ok())
+                    .ok_or_else(|| format!("Invalid interval '{}'", interval))?;
+                Stage::Throttle(selector.clone(), interval)
+            }
+            "talker" => Stage::Talker {
+                from: value("from")?.to_string(),
+                to: value("to")?.to_string(),
+            },
+            "field" => {
+                let index = value("index")?;
+                let index = index
+                    .parse()
+                    .ok()
+                    .ok_or_else(|| format!("Invalid field index '{}'", index))?;
+                Stage::Field(selector.clone(), index, value("value")?.to_string())
+            }
+            "strip-tag-block" => Stage::StripTagBlock,
+            "checksum" => Stage::Checksum,
+            _ => return Err(format!("Invalid stage '{}'", kind)),
+        };
+        let selects = matches!(
+            stage,
+            Stage::Keep(_) | Stage::Drop(_) | Stage::Throttle(..) | Stage::Field(..)
+        );
+        match values.keys().next() {
+            Some(key) => Err(format!("Invalid option '{}' in '{}'", key, line)),
+            None if !selects && selector != Selector::default() => {
+                Err(format!("Unexpected selector in '{}'", line))
+            }
+            None => Ok(stage),
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, options, selector) = match self {
+            Stage::Keep(selector) => ("keep", String::new(), Some(selector)),
+            Stage::Drop(selector) => ("drop", String::new(), Some(selector)),
+            Stage::Throttle(selector, interval) => (
+                "throttle",
+                format!("interval={}", interval.as_secs_f64()),
+                Some(selector),
+            ),
+            Stage::Talker { from, to } => ("talker", format!("from={} to={}", from, to), None),
+            Stage::Field(selector, index, value) => (
+                "field",
+                format!("index={} value={}", index, value),
+                Some(selector),
+            ),
+            Stage::StripTagBlock => ("strip-tag-block", String::new(), None),
+            Stage::Checksum => ("checksum", String::new(), None),
+        };
+        let selector = selector.map(Selector::to_string).unwrap_or_default();
+        let line = [kind, &options, &selector]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        f.write_str(&line)
+    }
+}
+
+/// Copy of `msg` with another talker or parameters and a valid checksum.
+fn rebuild_valid(msg: &Nmea0183Msg, talker: &str, params: Vec<String>) -> Nmea0183Msg {
+    let mut rebuilt = if msg.is_encapsulation() {
+        Nmea0183Msg::new_encapsulated(talker, msg.msgtype(), params)
+    } else {
+        Nmea0183Msg::new(talker, msg.msgtype(), params)
+    };
+    rebuilt.set_tag_block(msg.tag_block().cloned());
+    rebuilt
+}
+
+/// Like [`rebuild_valid`], but a sentence received with an invalid checksum keeps the
+/// difference to the valid one and a sentence without checksum stays without.
+fn rebuild(msg: &Nmea0183Msg, talker: &str, params: Vec<String>) -> Nmea0183Msg {
+    let mut rebuilt = rebuild_valid(msg, talker, params);
+    match msg.chksum_valid {
+        Some(true) => {}
+        Some(false) => {
+            let received = u8::from_str_radix(&msg.chksum, 16).unwrap_or(!msg.calc_chksum());
+            let chksum = rebuilt.calc_chksum() ^ received ^ msg.calc_chksum();
+            rebuilt.chksum = format!("{:02X}", chksum);
+            rebuilt.chksum_valid = Some(false);
+        }
+        None => {
+            rebuilt.chksum.clear();
+            rebuilt.chksum_valid = None;
+        }
+    }
+    rebuilt
+}
+
+/// Stages applied in order to each sentence between a source and a sink, e.g. a
+/// [`crate::get_codec`] stream and an encoder. Rewritten sentences get a valid checksum
+/// unless they were received with an invalid one or without, only the `checksum` stage
+/// repairs those.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    // time slot of the last forwarded sentence per throttle stage and sentence id
+    forwarded: HashMap<(usize, String), Instant>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// Reads stages from a file, see [`Pipeline::from_str`].
+    pub async fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        tokio::fs::read_to_string(path)
+            .await?
+            .parse()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+
+    /// `None` if a stage dropped the sentence.
+    pub fn apply(&mut self, msg: Nmea0183Msg) -> Option<Nmea0183Msg> {
+        self.apply_at(msg, Instant::now())
+    }
+
+    /// Like [`Pipeline::apply`] with the receive time for throttling.
+    pub fn apply_at(&mut self, mut msg: Nmea0183Msg, now: Instant) -> Option<Nmea0183Msg> {
+        for (idx, stage) in self.stages.iter().enumerate() {
+            match stage {
+                Stage::Keep(selector) if !selector.matches(&msg) => return None,
+                Stage::Drop(selector) if selector.matches(&msg) => return None,
+                Stage::Throttle(selector, interval) if selector.matches(&msg) => {
+                    let id = format!("{}{}", msg.talker(), msg.msgtype());
+                    let slot = match self.forwarded.get(&(idx, id.clone())) {
+                        Some(last) => {
+                            let elapsed = now.saturating_duration_since(*last);
+                            if elapsed + *interval / 10 < *interval {
+                                return None;
+                            }
+                            // advance by the interval so jitter does not add up, unless
+                            // the sentence id paused
+                            if elapsed < *interval * 2 {
+                                *last + *interval
+                            } else {
+                                now
+                            }
+                        }
+                        None => now,
+                    };
+                    self.forwarded.insert((idx, id), slot);
+                }
+                Stage::Talker { from, to } if msg.talker() == from => {
+                    msg = rebuild(&msg, to, msg.params().to_vec());
+                }
+                Stage::Field(selector, index, value) if selector.matches(&msg) => {
+                    let index = index.get();
+                    let mut params = msg.params().to_vec();
+                    if params.len() < index {
+                        params.resize(index, String::new());
+                    }
+                    params[index - 1] = value.clone();
+                    msg = rebuild(&msg, msg.talker(), params);
+                }
+                Stage::StripTagBlock => msg.set_tag_block(None),
+                Stage::Checksum if msg.chksum_valid() != Some(true) => {
+                    msg = rebuild_valid(&msg, msg.talker(), msg.params().to_vec());
+                }
+                _ => {}
+            }
+        }
+        Some(msg)
+    }
+
+    /// Forwards the sentences of `stream` that pass all stages to `sink` until the stream
+    /// ends. Decode errors are skipped.
+    pub async fn run<S, K>(&mut self, stream: S, mut sink: K) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+        K: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        while let Some(result) = stream.next().await {
+            if let Some(msg) = result.ok().and_then(|msg| self.apply(msg)) {
+                sink.send(msg).await?;
+            }
+        }
+        sink.close().await
+    }
+}
+
+/// One [`Stage`] per line, empty lines and lines starting with `#` are ignored.
+impl FromStr for Pipeline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pipeline = Self::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                let stage = line
+                    .parse()
+                    .map_err(|error| format!("Line {}: {}", idx + 1, error))?;
+                pipeline = pipeline.with(stage);
+            }
+        }
+        Ok(pipeline)
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for stage in &self.stages {
+            writeln!(f, "{}", stage)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_block::TagBlock;
+
+    const CONFIG: &str = r"
+# gateway to the 4800 baud output
+drop sentence=GSV
+keep regex=^\$G[PN](RMC|GGA|GLL),\d{6}
+throttle interval=1 sentence=RMC
+talker from=GP to=GN
+field index=12 value=D talker=GN sentence=RMC
+strip-tag-block
+";
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let pipeline: Pipeline = CONFIG.parse().unwrap();
+        assert_eq!(pipeline.stages().len(), 6);
+        assert_eq!(
+            pipeline.stages()[2],
+            Stage::Throttle(
+                Selector {
+                    sentences: vec!["RMC".to_string()],
+                    ..Default::default()
+                },
+                Duration::from_secs(1)
+            )
+        );
+        let lines = CONFIG
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        assert_eq!(
+            pipeline.to_string(),
+            lines.collect::<Vec<_>>().join("\n") + "\n"
+        );
+
+        for (invalid, error) in [
+            (
+                "drop\nkeep tlker=GP",
+                "Line 2: Invalid option 'tlker' in 'keep tlker=GP'",
+            ),
+            (
+                "throttle sentence=RMC",
+                "Line 1: Missing option 'interval' in 'throttle sentence=RMC'",
+            ),
+            ("field index=0 value=A", "Line 1: Invalid field index '0'"),
+            (
+                "checksum talker=GP",
+                "Line 1: Unexpected selector in 'checksum talker=GP'",
+            ),
+            ("forward", "Line 1: Invalid stage 'forward'"),
+        ] {
+            assert_eq!(invalid.parse::<Pipeline>().unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut pipeline: Pipeline = CONFIG.parse().unwrap();
+        let start = Instant::now();
+        let mut rmc = msg("$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E");
+        rmc.set_tag_block(Some(TagBlock::new().with("c", "1396205346")));
+        let forwarded = pipeline.apply_at(rmc.clone(), start).unwrap();
+        assert_eq!(
+            forwarded.to_string(),
+            "$GNRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,D*65"
+        );
+        assert_eq!(forwarded.chksum_valid(), Some(true));
+        assert_eq!(
+            pipeline.apply_at(rmc.clone(), start + Duration::from_millis(500)),
+            None
+        );
+        assert!(pipeline
+            .apply_at(rmc.clone(), start + Duration::from_millis(1000))
+            .is_some());
+
+        // a jittering 1 Hz source passes, a 10 Hz one is reduced to 1 Hz
+        let mut throttle = Pipeline::new().with("throttle interval=1".parse().unwrap());
+        for millis in [0, 960, 2030, 2980, 3950, 5040] {
+            let now = start + Duration::from_millis(millis);
+            assert!(throttle.apply_at(rmc.clone(), now).is_some(), "{}", millis);
+        }
+        let mut throttle = Pipeline::new().with("throttle interval=1".parse().unwrap());
+        let forwarded = (0..100)
+            .filter_map(|i| {
+                let now = start + Duration::from_millis(i * 100);
+                throttle.apply_at(rmc.clone(), now)
+            })
+            .count();
+        // one per slot from 0 s to 10 s, the last one 0.1 s early
+        assert_eq!(forwarded, 11);
+
+        let gga = msg("$GPGGA,184906.000,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*43");
+        assert_eq!(pipeline.apply_at(gga, start).unwrap().talker(), "GN");
+        assert_eq!(
+            pipeline.apply_at(
+                msg("$GPGSV,3,2,12,14,35,065,45,06,19,181,,27,13,188,38,20,09,319,30*72"),
+                start
+            ),
+            None
+        );
+        assert_eq!(
+            pipeline.apply_at(msg("$GPGLL,,,,,184907.00,V*2B"), start),
+            None
+        );
+
+        let mut pipeline = Pipeline::new().with(Stage::Checksum);
+        let invalid = msg(concat!("$SDDPT,10.7,-1.2*", "00"));
+        assert_eq!(invalid.chksum_valid(), Some(false));
+        assert_eq!(
+            pipeline.apply(invalid.clone()).unwrap().chksum_valid(),
+            Some(true)
+        );
+
+        // rewrites keep sentences invalid or without checksum
+        let mut pipeline: Pipeline = "talker from=SD to=II\nfield index=2 value=-1.3"
+            .parse()
+            .unwrap();
+        let rewritten = pipeline.apply(invalid).unwrap();
+        assert_eq!(rewritten.to_string(), "$IIDPT,10.7,-1.3*16");
+        assert_eq!(rewritten.chksum_valid(), Some(false));
+        let rewritten = pipeline.apply(msg("$SDDPT,10.7,-1.2")).unwrap();
+        assert_eq!(rewritten.to_string(), "$IIDPT,10.7,-1.3");
+        assert_eq!(rewritten.chksum_valid(), None);
+        let rewritten = pipeline.apply(msg("$SDDPT,10.7,-1.2*4F")).unwrap();
+        assert_eq!(rewritten.chksum_valid(), Some(true));
+
+        let index = NonZeroUsize::new(4).unwrap();
+        let mut pipeline =
+            Pipeline::new().with(Stage::Field(Selector::default(), index, "70".to_string()));
+        let extended = pipeline.apply(msg("$SDDPT,10.7,-1.2*4F")).unwrap();
+        assert_eq!(extended.params(), ["10.7", "-1.2", "", "70"]);
+    }
+
+    #[test]
+    fn test_run() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("gateway.pipeline");
+            tokio::fs::write(
+                &path,
+                "drop sentence=GSV,GSA,VTG,VDM,VDO\ntalker from=GP to=GN\n",
+            )
+            .await
+            .unwrap();
+            let mut pipeline = Pipeline::load(&path).await.unwrap();
+            let file = tokio::fs::File::open("test_data/nmea0183_1000.log")
+                .await
+                .unwrap();
+            let (tx, rx) = futures::channel::mpsc::unbounded();
+            pipeline
+                .run(crate::get_codec(file), tx.sink_map_err(io::Error::other))
+                .await
+                .unwrap();
+            let forwarded = rx.collect::<Vec<_>>().await;
+            assert_eq!(forwarded.len(), 299);
+            assert!(forwarded
+                .iter()
+                .all(|msg| msg.talker() == "GN" && ["GGA", "GLL", "RMC"].contains(&msg.msgtype())));
+        })
+    }
+}
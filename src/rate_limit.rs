@@ -0,0 +1,352 @@
+use crate::serial::SerialConfig;
+use crate::Nmea0183Msg;
+use futures::Sink;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::time::Instant;
+
+// longest sentence including CR LF, reserved per window for a priority without history
+const MAX_SENTENCE_CHARS: f64 = 82.0;
+// first fragments whose group never completed are forgotten
+const MAX_GROUPS: usize = 64;
+
+/// Sink adapter that keeps the written bytes within the budget of a slow link by dropping
+/// sentences instead of letting the link overflow.
+///
+/// The budget refills continuously and can be saved up for bursts of one second by
+/// default. Sentences are prioritised by the order of [`RateLimit::with_priorities`]: a
+/// sentence may only use the part of the budget that higher priorities are expected to
+/// still need in the current window of the burst length, estimated from the previous
+/// window. So lower priorities are decimated first and dropped entirely once the higher
+/// priorities need the whole budget. Without such an estimate, after the start or a
+/// pause, one sentence of maximum length is reserved for each higher priority.
+///
+/// Multi sentence `VDM` / `VDO` messages are admitted or dropped as a whole on their
+/// first fragment, which takes the budget for all fragments estimated from its own
+/// length. Fragments without their first one are dropped.
+pub struct RateLimit<K> {
+    inner: K,
+    chars_per_second: f64,
+    burst: Duration,
+    budget: f64,
+    updated: Instant,
+    priorities: Vec<String>,
+    window_start: Instant,
+    // characters offered per priority in the current and the previous window
+    offered: Vec<f64>,
+    demand: Vec<f64>,
+    // the demand was measured in the previous window
+    demand_known: bool,
+    // admission of multi sentence messages, keyed on sentence id, sequential id and channel
+    groups: HashMap<(String, String, String), bool>,
+    sent: u64,
+    // dropped sentences per sentence id, e.g. `GPGSV`
+    dropped: BTreeMap<String, u64>,
+}
+
+impl<K> RateLimit<K> {
+    pub fn new(inner: K, chars_per_second: f64) -> Self {
+        Self {
+            inner,
+            chars_per_second,
+            burst: Duration::from_secs(1),
+            budget: chars_per_second,
+            updated: Instant::now(),
+            priorities: Vec::new(),
+            window_start: Instant::now(),
+            offered: vec![0.0],
+            demand: vec![0.0],
+            demand_known: false,
+            groups: HashMap::new(),
+            sent: 0,
+            dropped: BTreeMap::new(),
+        }
+    }
+
+    /// Budget of a serial line, e.g. 480 characters per second at 4800 baud 8N1.
+    pub fn for_serial(inner: K, config: &SerialConfig) -> Self {
+        Self::new(inner, config.chars_per_second())
+    }
+
+    /// Entries match the message type, e.g. `RMC`, or the start of the sentence id, e.g.
+    /// `GPRMC` or `AI`. The first entry has the highest priority, sentences without a
+    /// matching entry the lowest.
+    pub fn with_priorities(mut self, priorities: &[&str]) -> Self {
+        self.priorities = priorities.iter().map(|entry| entry.to_string()).collect();
+        self.offered = vec![0.0; priorities.len() + 1];
+        self.demand = self.offered.clone();
+        self
+    }
+
+    /// Longest time the budget may be saved up for a burst.
+    pub fn with_burst(mut self, burst: Duration) -> Self {
+        self.burst = burst;
+        self.budget = self.budget.min(self.max_budget());
+        self
+    }
+
+    fn max_budget(&self) -> f64 {
+        self.chars_per_second * self.burst.as_secs_f64()
+    }
+
+    pub fn get_ref(&self) -> &K {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut K {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> K {
+        self.inner
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.values().sum()
+    }
+
+    pub fn dropped_by_sentence(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.dropped.iter().map(|(id, count)| (id.as_str(), *count))
+    }
+
+    /// Index into the priorities, `priorities.len()` if none matches.
+    fn priority(&self, msg: &Nmea0183Msg) -> usize {
+        let id = format!("{}{}", msg.talker(), msg.msgtype());
+        self.priorities
+            .iter()
+            .position(|entry| entry == msg.msgtype() || id.starts_with(entry.as_str()))
+            .unwrap_or(self.priorities.len())
+    }
+
+    /// Takes the characters of `msg` from the budget if its priority may use them.
+    fn admit(&mut self, msg: &Nmea0183Msg) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.budget = (self.budget + elapsed * self.chars_per_second).min(self.max_budget());
+        self.updated = now;
+        let window = now.duration_since(self.window_start);
+        if window >= self.burst {
+            // nothing is known about the traffic after a longer pause
+            let offered = std::mem::replace(&mut self.offered, vec![0.0; self.demand.len()]);
+            self.demand_known = window < self.burst * 2;
+            self.demand = if self.demand_known {
+                offered
+            } else {
+                vec![0.0; self.demand.len()]
+            };
+            self.window_start = now;
+        }
+
+        // including CR LF
+        let chars = (msg.to_string().len() + 2) as f64;
+        let priority = self.priority(msg);
+        self.offered[priority] += chars;
+        let id = format!("{}{}", msg.talker(), msg.msgtype());
+        let (admitted, cost) = match fragment(msg) {
+            // later fragments follow the first one, which paid for them
+            Some((key, number, total)) if number > 1 => {
+                let key = (id.clone(), key.0, key.1);
+                let admitted = self.groups.get(&key).copied().unwrap_or(false);
+                if number == total {
+                    self.groups.remove(&key);
+                }
+                (admitted, 0.0)
+            }
+            // the group is estimated from its first fragment
+            Some((key, _, total)) => {
+                let cost = chars * total as f64;
+                let admitted = self.fits(cost, priority);
+                if self.groups.len() >= MAX_GROUPS {
+                    self.groups.clear();
+                }
+                self.groups.insert((id.clone(), key.0, key.1), admitted);
+                (admitted, cost)
+            }
+            None => (self.fits(chars, priority), chars),
+        };
+        if admitted {
+            self.budget -= cost;
+            self.sent += 1;
+        } else {
+            *self.dropped.entry(id).or_default() += 1;
+        }
+        admitted
+    }
+
+    /// The budget minus `chars` covers what the higher priorities than `priority` are
+    /// expected to still need in the current window.
+    fn fits(&self, chars: f64, priority: usize) -> bool {
+        let reserved = (0..priority)
+            .map(|higher| {
+                let demand = if self.demand_known {
+                    self.demand[higher]
+                } else {
+                    MAX_SENTENCE_CHARS
+                };
+                (demand - self.offered[higher]).max(0.0)
+            })
+            .sum::<f64>();
+        self.budget - chars >= reserved
+    }
+}
+
+/// Sequential id and channel, fragment number and count of a multi sentence `VDM` /
+/// `VDO`, `None` for other sentences.
+fn fragment(msg: &Nmea0183Msg) -> Option<((String, String), usize, usize)> {
+    if !matches!(msg.msgtype(), "VDM" | "VDO") {
+        return None;
+    }
+    let params = msg.params();
+    let total = params.first()?.parse::<usize>().ok()?;
+    let number = params.get(1)?.parse::<usize>().ok()?;
+    if total < 2 || number == 0 {
+        return None;
+    }
+    let key = (params.get(2)?.clone(), params.get(3)?.clone());
+    Some((key, number, total))
+}
+
+impl<K> fmt::Display for RateLimit<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sent {}, dropped {}", self.sent, self.dropped())?;
+        for (id, count) in self.dropped_by_sentence() {
+            write!(f, ", {} {}", id, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K> Sink<Nmea0183Msg> for RateLimit<K>
+where
+    K: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+{
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Nmea0183Msg) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if this.admit(&msg) {
+            Pin::new(&mut this.inner).start_send(msg)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+
+    #[test]
+    fn test_rate_limit() {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+            let (tx, rx) = futures::channel::mpsc::unbounded();
+            let mut sink =
+                RateLimit::for_serial(tx.sink_map_err(io::Error::other), &SerialConfig::new(4800))
+                    .with_priorities(&["RMC", "GGA"]);
+            let rmc: Nmea0183Msg =
+                "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E"
+                    .parse()
+                    .unwrap();
+            let gga: Nmea0183Msg =
+                "$GPGGA,184906.000,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*43"
+                    .parse()
+                    .unwrap();
+            let vdm: Nmea0183Msg = "!AIVDM,1,1,,B,15TPq@0Oj0rClEv53P9HWVn<283C,0*51"
+                .parse()
+                .unwrap();
+            // 10 s of a 38400 baud feed, 78 + 79 characters of GPS and 100 AIS sentences
+            // of 50 characters per second
+            for _ in 0..10 {
+                for _ in 0..50 {
+                    sink.send(vdm.clone()).await.unwrap();
+                }
+                sink.send(rmc.clone()).await.unwrap();
+                sink.send(gga.clone()).await.unwrap();
+                for _ in 0..50 {
+                    sink.send(vdm.clone()).await.unwrap();
+                }
+                tokio::time::advance(Duration::from_secs(1)).await;
+            }
+            sink.close().await.unwrap();
+            let sent = rx.collect::<Vec<_>>().await;
+
+            let count = |msgtype: &str| sent.iter().filter(|msg| msg.msgtype() == msgtype).count();
+            // the GPS traffic is reserved from the start, in the first second as one
+            // sentence of maximum length each
+            assert_eq!(count("RMC"), 10);
+            assert_eq!(count("GGA"), 10);
+            assert_eq!(count("VDM"), 10 * 6);
+            assert_eq!(sink.sent(), sent.len() as u64);
+            assert_eq!(sink.dropped(), 1020 - sink.sent());
+            assert_eq!(
+                sink.dropped_by_sentence().collect::<Vec<_>>(),
+                [("AIVDM", 1000 - 60)]
+            );
+            assert!(sink
+                .to_string()
+                .starts_with(&format!("sent {}, dropped ", sent.len())));
+        })
+    }
+
+    #[test]
+    fn test_fragments() {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+            let (tx, rx) = futures::channel::mpsc::unbounded();
+            let mut sink = RateLimit::new(tx.sink_map_err(io::Error::other), 100.0)
+                .with_burst(Duration::from_secs(2));
+            let vdm = |total: &str, number: &str, id: &str, payload: &str| {
+                let params = [total, number, id, "A", payload, "0"];
+                Nmea0183Msg::new_encapsulated(
+                    "AI",
+                    "VDM",
+                    params.iter().map(|param| param.to_string()).collect(),
+                )
+            };
+            let long = "5".repeat(58);
+            // 80 characters, the group of two does not fit into the budget of 100
+            assert_eq!(vdm("2", "1", "3", &long).to_string().len() + 2, 80);
+            sink.send(vdm("2", "1", "3", &long)).await.unwrap();
+            sink.send(vdm("2", "2", "3", "1@00")).await.unwrap();
+
+            tokio::time::advance(Duration::from_secs(2)).await;
+            sink.send(vdm("2", "1", "4", &long)).await.unwrap();
+            sink.send(vdm("1", "1", "", "15M6")).await.unwrap();
+            // the rest of the group is paid for
+            sink.send(vdm("1", "1", "", "15M6")).await.unwrap();
+            sink.send(vdm("2", "2", "4", "1@00")).await.unwrap();
+            // the first fragment is missing
+            sink.send(vdm("2", "2", "5", "1@00")).await.unwrap();
+            sink.close().await.unwrap();
+
+            let sent = rx
+                .map(|msg| format!("{},{}", msg.params()[1], msg.params()[2]))
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(sent, ["1,4", "1,", "2,4"]);
+            assert_eq!(sink.dropped(), 4);
+        })
+    }
+}
@@ -0,0 +1,370 @@
+use crate::tag_block::TagBlock;
+use crate::{Nmea0183Msg, ResumeOnError};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{Stream, StreamExt};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader, BufWriter};
+use tokio::task::JoinHandle;
+
+/// Marks recorded decode errors, such lines are decoded as errors again on replay.
+pub const ERROR_MARKER: &str = "#";
+
+/// How the receive time is recorded in front of each line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timestamp {
+    /// IEC 61162-450 tag block with the `c` parameter in seconds, readable by
+    /// [`crate::replay::Replay`].
+    TagBlock,
+    /// Unix time with milliseconds followed by the separator, e.g. `1712345678.123 `.
+    Prefix(String),
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecorderConfig {
+    pub dir: PathBuf,
+    /// Log files are named `<prefix>-<unix time>-<sequence>.log`.
+    pub prefix: String,
+    pub timestamp: Timestamp,
+    /// Start a new file once the current one exceeds this many bytes.
+    pub max_size: Option<u64>,
+    /// Start a new file once the current one is older.
+    pub max_age: Option<Duration>,
+    /// Compress closed files to `.log.gz`.
+    pub gzip: bool,
+}
+
+impl RecorderConfig {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: "nmea".to_string(),
+            timestamp: Timestamp::TagBlock,
+            max_size: None,
+            max_age: None,
+            gzip: false,
+        }
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    size: u64,
+    opened: Instant,
+}
+
+/// Writes received sentences and decode errors to rotating log files. Each line is
+/// flushed right away, so a crash loses at most the line being written.
+pub struct Recorder {
+    config: RecorderConfig,
+    file: Option<LogFile>,
+    sequence: u32,
+    compressing: Vec<JoinHandle<io::Result<PathBuf>>>,
+}
+
+impl Recorder {
+    /// The first file is created on the first write.
+    pub fn new(config: RecorderConfig) -> Self {
+        Self {
+            config,
+            file: None,
+            sequence: 0,
+            compressing: Vec::new(),
+        }
+    }
+
+    pub fn current_path(&self) -> Option<&Path> {
+        self.file.as_ref().map(|file| file.path.as_path())
+    }
+
+    /// Records the sentence with the checksum as received, see [`Nmea0183Msg`]'s `Display`.
+    pub async fn write_msg(&mut self, msg: &Nmea0183Msg) -> io::Result<()> {
+        self.write_line_at(&msg.to_string(), SystemTime::now())
+            .await
+    }
+
+    /// Records a decode error, e.g. `# Invalid event 2A-* @3 in state Talker`.
+    pub async fn write_error(&mut self, error: &str) -> io::Result<()> {
+        self.write_line_at(&format!("{} {}", ERROR_MARKER, error), SystemTime::now())
+            .await
+    }
+
+    /// Records a line as received, CR LF is appended.
+    pub async fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.write_line_at(line, SystemTime::now()).await
+    }
+
+    async fn write_line_at(&mut self, line: &str, received: SystemTime) -> io::Result<()> {
+        let received = received.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = match &self.config.timestamp {
+            Timestamp::TagBlock => format!("{}\r\n", with_timestamp(line, received.as_secs())),
+            Timestamp::Prefix(separator) => format!(
+                "{}.{:03}{}{}\r\n",
+                received.as_secs(),
+                received.subsec_millis(),
+                separator,
+                line
+            ),
+            Timestamp::None => format!("{}\r\n", line),
+        };
+
+        if self.needs_rotation() {
+            self.rotate().await?;
+        }
+        if self.file.is_none() {
+            self.file = Some(self.open().await?);
+        }
+        let file = self.file.as_mut().expect("log file is open");
+        file.writer.write_all(line.as_bytes()).await?;
+        file.writer.flush().await?;
+        file.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn needs_rotation(&self) -> bool {
+        match &self.file {
+            Some(file) => {
+                self.config
+                    .max_size
+                    .is_some_and(|max_size| file.size >= max_size)
+                    || self
+                        .config
+                        .max_age
+                        .is_some_and(|max_age| file.opened.elapsed() >= max_age)
+            }
+            None => false,
+        }
+    }
+
+    async fn open(&mut self) -> io::Result<LogFile> {
+        tokio::fs::create_dir_all(&self.config.dir).await?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.sequence += 1;
+        let path = self.config.dir.join(format!(
+            "{}-{}-{:04}.log",
+            self.config.prefix,
+            now.as_secs(),
+            self.sequence
+        ));
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        Ok(LogFile {
+            path,
+            writer: BufWriter::new(file),
+            size: 0,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Closes the current file, the next write starts a new one.
+    pub async fn rotate(&mut self) -> io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.writer.flush().await?;
+            file.writer.get_ref().sync_all().await?;
+            if self.config.gzip {
+                let path = file.path;
+                self.compressing
+                    .push(tokio::task::spawn_blocking(move || compress(&path)));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.writer.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Closes the current file and waits until all closed files are compressed.
+    /// Returns the paths of the compressed files.
+    pub async fn close(&mut self) -> io::Result<Vec<PathBuf>> {
+        self.rotate().await?;
+        let mut paths = Vec::new();
+        for handle in self.compressing.drain(..) {
+            paths.push(handle.await.map_err(io::Error::other)??);
+        }
+        Ok(paths)
+    }
+
+    /// Records a decoded stream, e.g. from [`crate::get_codec`], until it ends. Decode
+    /// errors are recorded as error lines.
+    pub async fn record<S>(&mut self, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(msg) => self.write_msg(&msg).await?,
+                Err(error) => self.write_error(&error.to_string()).await?,
+            }
+        }
+        self.flush().await
+    }
+
+    /// Records the lines read from `reader` without decoding them, invalid sentences are
+    /// kept byte for byte apart from invalid UTF-8.
+    pub async fn record_raw<R: AsyncRead + Unpin>(&mut self, reader: R) -> io::Result<()> {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\r', '\n']);
+            if !text.is_empty() {
+                self.write_line(text).await?;
+            }
+        }
+        self.flush().await
+    }
+}
+
+fn compress(path: &Path) -> io::Result<PathBuf> {
+    let mut gz_path = path.as_os_str().to_owned();
+    gz_path.push(".gz");
+    let gz_path = PathBuf::from(gz_path);
+    let mut input = std::fs::File::open(path)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&gz_path)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    std::fs::remove_file(path)?;
+    Ok(gz_path)
+}
+
+/// `line` with the receive time as `c` parameter of its tag block. An existing tag block
+/// is extended, its own `c` parameter is kept.
+fn with_timestamp(line: &str, seconds: u64) -> String {
+    let existing = line
+        .strip_prefix('\\')
+        .and_then(|rest| rest.split_once('\\'))
+        .and_then(|(tag_block, sentence)| Some((tag_block.parse::<TagBlock>().ok()?, sentence)));
+    let (mut tag_block, sentence) = existing.unwrap_or((TagBlock::new(), line));
+    if tag_block.get("c").is_none() {
+        tag_block.set_timestamp(seconds);
+    }
+    format!("{}{}", tag_block, sentence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_codec;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
+
+    fn read_dir(dir: &Path) -> Vec<PathBuf> {
+        let mut paths = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_record() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let mut recorder = Recorder::new(RecorderConfig::new(dir.path()));
+            let stream = futures::stream::iter(vec![
+                Ok("$GPZDA,160012.71,11,03,2004,-1,00*7D".parse().unwrap()),
+                Err(io::Error::other("Invalid event 0D-☺ @3 in state Talker")),
+                Ok("\\s:GP0001*5F\\$GPZDA,160012.71,11,03,2004,-1,00*00"
+                    .parse()
+                    .unwrap()),
+            ]);
+            recorder.record(stream).await.unwrap();
+            recorder.close().await.unwrap();
+
+            let paths = read_dir(dir.path());
+            assert_eq!(paths.len(), 1);
+            let content = std::fs::read_to_string(&paths[0]).unwrap();
+            let lines = content.lines().collect::<Vec<_>>();
+            let msg: Nmea0183Msg = lines[0].parse().unwrap();
+            assert!(msg.tag_block().unwrap().timestamp().unwrap() > 1.6e9);
+            assert_eq!(msg.msgtype(), "ZDA");
+            assert!(lines[1].ends_with("\\# Invalid event 0D-☺ @3 in state Talker"));
+            // merged into the existing tag block, the invalid checksum is kept
+            let msg: Nmea0183Msg = lines[2].parse().unwrap();
+            let tag_block = msg.tag_block().unwrap();
+            assert_eq!(tag_block.source(), Some("GP0001"));
+            assert!(tag_block.timestamp().unwrap() > 1.6e9);
+            assert!(lines[2].ends_with("$GPZDA,160012.71,11,03,2004,-1,00*00"));
+        })
+    }
+
+    #[test]
+    fn test_rotate_and_gzip() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let config = RecorderConfig {
+                timestamp: Timestamp::Prefix(" ".to_string()),
+                max_size: Some(16 * 1024),
+                gzip: true,
+                ..RecorderConfig::new(dir.path())
+            };
+            let mut recorder = Recorder::new(config);
+            let file = File::open(TEST_FILE).await.unwrap();
+            recorder.record(get_codec(file)).await.unwrap();
+            let compressed = recorder.close().await.unwrap();
+
+            let paths = read_dir(dir.path());
+            assert!(paths.len() > 1);
+            assert_eq!(paths, compressed);
+            let mut lines = 0;
+            for path in paths {
+                assert!(path.to_string_lossy().ends_with(".log.gz"));
+                let mut content = String::new();
+                GzDecoder::new(std::fs::File::open(path).unwrap())
+                    .read_to_string(&mut content)
+                    .unwrap();
+                for line in content.lines() {
+                    let (timestamp, sentence) = line.split_once(' ').unwrap();
+                    assert!(timestamp.parse::<f64>().is_ok());
+                    assert!(sentence.parse::<Nmea0183Msg>().is_ok());
+                    lines += 1;
+                }
+            }
+            assert_eq!(lines, 1389);
+        })
+    }
+
+    #[test]
+    fn test_record_raw() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let config = RecorderConfig {
+                timestamp: Timestamp::None,
+                ..RecorderConfig::new(dir.path())
+            };
+            let mut recorder = Recorder::new(config);
+            let raw: &[u8] = b"$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n$GPZDA,garbage\r\n";
+            recorder.record_raw(raw).await.unwrap();
+            let content = std::fs::read(&read_dir(dir.path())[0]).unwrap();
+            assert_eq!(content, raw);
+
+            // written through before the file is closed
+            recorder.write_line("$GPZDA,garbage").await.unwrap();
+            let content = std::fs::read(recorder.current_path().unwrap()).unwrap();
+            assert!(content.ends_with(b"\r\n$GPZDA,garbage\r\n"));
+            recorder.close().await.unwrap();
+        })
+    }
+}
@@ -0,0 +1,403 @@
+use crate::sentence::{Rmc, UtcTime, Zda};
+use crate::{get_codec, Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::time::{sleep_until, Instant, Sleep};
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+struct Entry {
+    /// Seconds since the first sentence of the log.
+    offset: f64,
+    result: Result<Nmea0183Msg, String>,
+}
+
+/// Replays a captured log paced like the original feed. The cadence is reconstructed from
+/// tag block timestamps and the time fields of GGA, RMC and ZDA sentences, sentences
+/// without time are sent right after their predecessor. Yields the same items as
+/// [`crate::get_codec`].
+pub struct Replay {
+    entries: Vec<Entry>,
+    pos: usize,
+    speed: f64,
+    looping: bool,
+    paused: bool,
+    max_gap: Option<f64>,
+    // wall clock instant at which the entry at `pos` is due
+    start: Option<(Instant, f64)>,
+    sleep: Option<Pin<Box<Sleep>>>,
+    // task waiting while paused or for the due time, woken when either changes
+    waker: Option<Waker>,
+}
+
+impl Replay {
+    /// Reads and decodes the whole log, decode errors are replayed as errors.
+    pub async fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path).await?;
+        let results = ResumeOnError::new(get_codec(file))
+            .map(|result| result.map_err(|error| error.to_string()))
+            .collect::<Vec<_>>()
+            .await;
+        Ok(Self::from_results(results))
+    }
+
+    pub fn from_msgs(msgs: Vec<Nmea0183Msg>) -> Self {
+        Self::from_results(msgs.into_iter().map(Ok).collect())
+    }
+
+    fn from_results(results: Vec<Result<Nmea0183Msg, String>>) -> Self {
+        let times = results
+            .iter()
+            .map(|result| result.as_ref().ok().and_then(log_time))
+            .collect::<Vec<_>>();
+        // sentences with a time of day only are placed on the day of the last sentence with
+        // a date, or of the first one if none came before
+        let mut day = times
+            .iter()
+            .find_map(|time| match time {
+                Some(LogTime::Absolute(time)) => Some(day_of(*time)),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let mut last: Option<f64> = None;
+        let entries = results
+            .into_iter()
+            .zip(times)
+            .map(|(result, time)| {
+                let time = match time {
+                    Some(LogTime::Absolute(time)) => {
+                        day = day_of(time);
+                        Some(time)
+                    }
+                    Some(LogTime::TimeOfDay(time)) => {
+                        // the time of day wraps at midnight
+                        if last.is_some_and(|last| day + time < last - SECONDS_PER_DAY / 2.0) {
+                            day += SECONDS_PER_DAY;
+                        }
+                        Some(day + time)
+                    }
+                    None => None,
+                };
+                // never go back in time, e.g. on sentences sent out of order
+                if let Some(time) = time {
+                    last = Some(last.map_or(time, |last| last.max(time)));
+                }
+                (last, result)
+            })
+            .collect::<Vec<_>>();
+        // sentences before the first timestamp are sent right away
+        let first = entries
+            .iter()
+            .find_map(|(time, _)| *time)
+            .unwrap_or_default();
+        let entries = entries
+            .into_iter()
+            .map(|(time, result)| Entry {
+                offset: time.map_or(0.0, |time| time - first),
+                result,
+            })
+            .collect();
+        Self {
+            entries,
+            pos: 0,
+            speed: 1.0,
+            looping: false,
+            paused: false,
+            max_gap: None,
+            start: None,
+            sleep: None,
+            waker: None,
+        }
+    }
+
+    /// Replay speed multiplier, e.g. `10.0` for ten times real time or `f64::INFINITY`
+    /// for as fast as possible.
+    pub fn set_speed(&mut self, speed: f64) -> Result<(), String> {
+        if speed > 0.0 {
+            self.speed = speed;
+            self.start = None;
+            self.wake();
+            Ok(())
+        } else {
+            Err(format!("Invalid replay speed {}", speed))
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f64) -> Result<Self, String> {
+        self.set_speed(speed)?;
+        Ok(self)
+    }
+
+    /// Restart from the beginning when the end of the log is reached.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.set_looping(looping);
+        self
+    }
+
+    /// Gaps between sentences longer than `max_gap`, e.g. between concatenated captures,
+    /// are skipped.
+    pub fn set_max_gap(&mut self, max_gap: Option<Duration>) {
+        self.max_gap = max_gap.map(|max_gap| max_gap.as_secs_f64());
+    }
+
+    pub fn with_max_gap(mut self, max_gap: Option<Duration>) -> Self {
+        self.set_max_gap(max_gap);
+        self
+    }
+
+    /// While paused the stream yields nothing, the replay continues where it stopped.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.start = None;
+        self.wake();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Continues with the first sentence at or after `offset` from the start of the log.
+    pub fn seek(&mut self, offset: Duration) {
+        let offset = offset.as_secs_f64();
+        self.pos = self.entries.partition_point(|entry| entry.offset < offset);
+        self.start = None;
+        self.wake();
+    }
+
+    /// Offset of the next sentence from the start of the log.
+    pub fn position(&self) -> Duration {
+        self.entries
+            .get(self.pos)
+            .or(self.entries.last())
+            .map_or(Duration::ZERO, |entry| {
+                Duration::from_secs_f64(entry.offset)
+            })
+    }
+
+    /// Time span of the log.
+    pub fn duration(&self) -> Duration {
+        self.entries.last().map_or(Duration::ZERO, |entry| {
+            Duration::from_secs_f64(entry.offset)
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+enum LogTime {
+    /// Seconds since the unix epoch.
+    Absolute(f64),
+    /// Seconds since midnight.
+    TimeOfDay(f64),
+}
+
+fn day_of(time: f64) -> f64 {
+    time - time.rem_euclid(SECONDS_PER_DAY)
+}
+
+/// The tag block timestamp takes precedence over the time fields.
+fn log_time(msg: &Nmea0183Msg) -> Option<LogTime> {
+    if let Some(timestamp) = msg.tag_block().and_then(|tag_block| tag_block.timestamp()) {
+        return Some(LogTime::Absolute(timestamp));
+    }
+    let absolute = match msg.msgtype() {
+        "RMC" => Rmc::try_from(msg).ok().and_then(|rmc| rmc.unix_time()),
+        "ZDA" => Zda::try_from(msg).ok().and_then(|zda| zda.unix_time()),
+        "GGA" => None,
+        _ => return None,
+    };
+    match absolute {
+        Some(time) => Some(LogTime::Absolute(time)),
+        None => UtcTime::from_str(msg.params().first()?)
+            .ok()
+            .map(|time| LogTime::TimeOfDay(time.seconds_of_day())),
+    }
+}
+
+impl Stream for Replay {
+    type Item = Result<Nmea0183Msg, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.paused {
+            this.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        if this.pos >= this.entries.len() {
+            if this.looping && !this.entries.is_empty() {
+                this.pos = 0;
+                this.start = None;
+            } else {
+                return Poll::Ready(None);
+            }
+        }
+
+        let offset = this.entries[this.pos].offset;
+        if let (Some(max_gap), Some(previous)) = (this.max_gap, this.pos.checked_sub(1)) {
+            if offset - this.entries[previous].offset > max_gap {
+                this.start = None;
+            }
+        }
+        let (start, start_offset) = *this.start.get_or_insert((Instant::now(), offset));
+        let due = start + Duration::from_secs_f64((offset - start_offset) / this.speed);
+        if due > Instant::now() {
+            // created on first use, a timer needs a runtime
+            let sleep = this.sleep.get_or_insert_with(|| Box::pin(sleep_until(due)));
+            if sleep.deadline() != due {
+                sleep.as_mut().reset(due);
+            }
+            if sleep.as_mut().poll(cx).is_pending() {
+                this.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+
+        let entry = &this.entries[this.pos];
+        this.pos += 1;
+        Poll::Ready(Some(match &entry.result {
+            Ok(msg) => Ok(msg.clone()),
+            Err(error) => Err(io::Error::other(error.clone())),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::task::ArcWake;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
+
+    fn msgs(sentences: &[&str]) -> Vec<Nmea0183Msg> {
+        sentences
+            .iter()
+            .map(|sentence| sentence.parse().expect("failed to parse message"))
+            .collect()
+    }
+
+    #[test]
+    fn test_cadence() {
+        let replay = Replay::from_msgs(msgs(&[
+            "$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09",
+            "$GPZDA,235959.50,11,03,2004,00,00*67",
+            "$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09",
+            "\\c:1079049600*5D\\$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09",
+            "$GPZDA,000000.25,12,03,2004,00,00*67",
+        ]));
+        let offsets = replay
+            .entries
+            .iter()
+            .map(|entry| entry.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [0.0, 0.0, 0.0, 0.5, 0.75]);
+    }
+
+    #[test]
+    fn test_replay() {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+            let mut replay = Replay::open(TEST_FILE)
+                .await
+                .unwrap()
+                .with_speed(100.0)
+                .unwrap();
+            let count = replay.len();
+            let duration = replay.duration();
+            assert!(duration > Duration::from_secs(3600));
+
+            let start = Instant::now();
+            let mut received = 0;
+            while let Some(result) = replay.next().await {
+                result.unwrap();
+                received += 1;
+            }
+            assert_eq!(received, count);
+            let elapsed = start.elapsed().as_secs_f64();
+            assert!((elapsed - duration.as_secs_f64() / 100.0).abs() < 0.1);
+
+            // the test data consists of two captures about 20 hours apart
+            let mut replay = replay.with_max_gap(Some(Duration::from_secs(60)));
+            replay.seek(Duration::ZERO);
+            let start = Instant::now();
+            while replay.next().await.is_some() {}
+            assert!(start.elapsed() < Duration::from_secs(30));
+        })
+    }
+
+    #[test]
+    fn test_seek_and_loop() {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+            let mut replay = Replay::open(TEST_FILE).await.unwrap().with_looping(true);
+            let count = replay.len();
+            replay.seek(Duration::from_secs(600));
+            assert!(replay.position() >= Duration::from_secs(600));
+
+            replay.set_speed(f64::INFINITY).unwrap();
+            let start = Instant::now();
+            let skipped = replay.pos;
+            for _ in skipped..count + 1 {
+                replay.next().await.unwrap().unwrap();
+            }
+            // looped back to the start
+            assert_eq!(replay.pos, 1);
+            assert!(start.elapsed() < Duration::from_millis(1));
+
+            replay.pause();
+            assert!(futures::poll!(replay.next()).is_pending());
+            replay.resume();
+            assert!(futures::poll!(replay.next()).is_ready());
+        })
+    }
+
+    #[test]
+    fn test_resume_wakes() {
+        struct Flag(AtomicBool);
+
+        impl ArcWake for Flag {
+            fn wake_by_ref(flag: &Arc<Self>) {
+                flag.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        let mut replay = Replay::from_msgs(msgs(&["$GPZDA,160012.71,11,03,2004,-1,00*7D"]));
+        replay.pause();
+        let flag = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = futures::task::waker(Arc::clone(&flag));
+        let mut cx = Context::from_waker(&waker);
+        assert!(Pin::new(&mut replay).poll_next(&mut cx).is_pending());
+        assert!(!flag.0.load(Ordering::SeqCst));
+        replay.resume();
+        assert!(flag.0.load(Ordering::SeqCst));
+        assert!(Pin::new(&mut replay).poll_next(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,161 @@
+use crate::nmea0183_codec::context::StateMachine;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::fmt;
+use std::io;
+
+/// Repairs sentences of instruments that send invalid or no checksums, e.g. for plotters
+/// that reject them. Decode with a lenient codec, see
+/// [`crate::Nmea0183Codec::with_lenient`], which already skips illegal characters, and
+/// encode the repaired sentences with [`crate::Nmea0183Codec`] to terminate them with CR LF.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sanitizer {
+    sentences: u64,
+    fixed: u64,
+    added: u64,
+}
+
+impl Sanitizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `msg` with a valid checksum in uppercase hex.
+    pub fn sanitize(&mut self, mut msg: Nmea0183Msg) -> Nmea0183Msg {
+        self.sentences += 1;
+        match msg.chksum_valid {
+            Some(true) => {}
+            Some(false) => self.fixed += 1,
+            None => self.added += 1,
+        }
+        msg.chksum = format!("{:02X}", msg.calc_chksum());
+        msg.chksum_valid = Some(true);
+        msg
+    }
+
+    /// Forwards the repaired sentences of `stream` to `sink` until the stream ends. Decode
+    /// errors are skipped.
+    pub async fn run<S, K>(&mut self, stream: S, mut sink: K) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+        K: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        while let Some(result) = stream.next().await {
+            if let Ok(msg) = result {
+                sink.send(self.sanitize(msg)).await?;
+            }
+        }
+        sink.close().await
+    }
+
+    pub fn sentences(&self) -> u64 {
+        self.sentences
+    }
+
+    /// Sentences received with an invalid checksum.
+    pub fn fixed(&self) -> u64 {
+        self.fixed
+    }
+
+    /// Sentences received without checksum.
+    pub fn added(&self) -> u64 {
+        self.added
+    }
+}
+
+impl fmt::Display for Sanitizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sentences {}, checksums fixed {}, added {}",
+            self.sentences, self.fixed, self.added
+        )
+    }
+}
+
+/// Repairs a single line like the lenient codec and [`Sanitizer`], the result ends with
+/// CR LF.
+pub fn sanitize_line(line: &str) -> Result<String, String> {
+    let mut ctx = StateMachine::new().with_lenient(true);
+    let line = line.trim_end_matches(['\r', '\n']);
+    for byte in line.bytes().chain(*b"\r\n") {
+        if let Some(msg) = ctx.handle_event(&byte)? {
+            return Ok(format!("{}\r\n", Sanitizer::new().sanitize(msg)));
+        }
+    }
+    Err("Incomplete message".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Nmea0183Codec;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    #[test]
+    fn test_sanitize() {
+        tokio_test::block_on(async {
+            let input: &[u8] = concat!(
+                "$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n",
+                // wrong checksum, LF only
+                "$GPZDA,160013.71,11,03,2004,-1,00*00\n",
+                // missing checksum, empty line
+                "$GPZDA,160014.71,11,03,2004,-1,00\r\n\r\n",
+                // lowercase checksum, NUL and non-ASCII characters
+                "\0$IIHDT,274.\u{b0}08,T*1b\r\r\n",
+                // single digit
+                "$GPZDA,160015.71,11,03,2004,-1,00*7\n\n",
+            )
+            .as_bytes();
+            let stream = FramedRead::new(input, Nmea0183Codec::new().with_lenient(true));
+            let msgs = stream.collect::<Vec<_>>().await;
+            let valid = msgs
+                .iter()
+                .map(|result| result.as_ref().unwrap().chksum_valid())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                valid,
+                [Some(true), Some(false), None, Some(true), Some(false)]
+            );
+            assert_eq!(msgs[3].as_ref().unwrap().params(), ["274.08", "T"]);
+
+            let strict = FramedRead::new(input, Nmea0183Codec::new());
+            let results = strict.collect::<Vec<_>>().await;
+            assert!(results.iter().filter(|result| result.is_ok()).count() < msgs.len());
+
+            let mut output = Vec::new();
+            let mut sanitizer = Sanitizer::new();
+            sanitizer
+                .run(
+                    FramedRead::new(input, Nmea0183Codec::new().with_lenient(true)),
+                    FramedWrite::new(&mut output, Nmea0183Codec::new()),
+                )
+                .await
+                .unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                concat!(
+                    "$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n",
+                    "$GPZDA,160013.71,11,03,2004,-1,00*7C\r\n",
+                    "$GPZDA,160014.71,11,03,2004,-1,00*7B\r\n",
+                    "$IIHDT,274.08,T*1B\r\n",
+                    "$GPZDA,160015.71,11,03,2004,-1,00*7A\r\n",
+                )
+            );
+            assert_eq!(sanitizer.sentences(), 5);
+            assert_eq!(sanitizer.fixed(), 2);
+            assert_eq!(sanitizer.added(), 1);
+            assert_eq!(
+                sanitizer.to_string(),
+                "sentences 5, checksums fixed 2, added 1"
+            );
+        });
+
+        assert_eq!(
+            sanitize_line("$GPZDA,160013.71,11,03,2004,-1,00*00\n").unwrap(),
+            "$GPZDA,160013.71,11,03,2004,-1,00*7C\r\n"
+        );
+        assert!(sanitize_line("GPZDA,160013.71").is_err());
+    }
+}
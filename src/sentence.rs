@@ -0,0 +1,462 @@
+use crate::Nmea0183Msg;
+use std::fmt;
+use std::str::FromStr;
+
+mod dbt;
+mod dpt;
+mod gga;
+mod gll;
+mod gsa;
+mod gsv;
+mod hsc;
+mod osd;
+pub mod proprietary;
+mod rmc;
+mod rot;
+mod rpm;
+mod rsa;
+mod ttm;
+mod vbw;
+mod vhw;
+mod vlw;
+mod xdr;
+mod zda;
+
+pub use dbt::Dbt;
+pub use dpt::Dpt;
+pub use gga::Gga;
+pub use gll::Gll;
+pub use gsa::Gsa;
+pub use gsv::{Gsv, GsvSatellite};
+pub use hsc::Hsc;
+pub use osd::Osd;
+pub use rmc::Rmc;
+pub use rot::Rot;
+pub use rpm::Rpm;
+pub use rsa::Rsa;
+pub use ttm::Ttm;
+pub use vbw::Vbw;
+pub use vhw::Vhw;
+pub use vlw::Vlw;
+pub use xdr::{Measurement, TransducerType, Xdr};
+pub use zda::Zda;
+
+/// Typed sentences that can be encoded back into a [`Nmea0183Msg`].
+pub trait Sentence {
+    const MSGTYPE: &'static str;
+
+    fn encode_params(&self) -> Vec<String>;
+
+    fn to_msg(&self, talker: &str) -> Nmea0183Msg {
+        Nmea0183Msg::new(talker, Self::MSGTYPE, self.encode_params())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UtcTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: f64,
+}
+
+impl UtcTime {
+    pub fn seconds_of_day(&self) -> f64 {
+        f64::from(self.hour) * 3600.0 + f64::from(self.minute) * 60.0 + self.second
+    }
+}
+
+impl fmt::Display for UtcTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}{:02}{:05.2}", self.hour, self.minute, self.second)
+    }
+}
+
+/// Parses `hhmmss.ss`, the fractional part is optional.
+impl FromStr for UtcTime {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.len() < 6 || !value.bytes().take(4).all(|b| b.is_ascii_digit()) {
+            return Err(format!("Invalid time '{}'", value));
+        }
+        let invalid = || format!("Invalid time '{}'", value);
+        let time = Self {
+            hour: value[0..2].parse().map_err(|_| invalid())?,
+            minute: value[2..4].parse().map_err(|_| invalid())?,
+            second: value[4..].parse().map_err(|_| invalid())?,
+        };
+        if time.hour > 23 || time.minute > 59 || time.second >= 61.0 {
+            Err(format!("Time out of range '{}'", value))
+        } else {
+            Ok(time)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UtcDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl UtcDate {
+    pub fn new(year: u16, month: u8, day: u8) -> Result<Self, String> {
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            Err(format!("Invalid date {:04}-{:02}-{:02}", year, month, day))
+        } else {
+            Ok(Self { year, month, day })
+        }
+    }
+
+    /// Parses the `ddmmyy` date used by RMC, two digit years are mapped to 1980 - 2079.
+    pub fn from_ddmmyy(value: &str) -> Result<Self, String> {
+        if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("Invalid date '{}'", value));
+        }
+        let year: u16 = value[4..6].parse().unwrap_or_default();
+        Self::new(
+            if year < 80 { 2000 + year } else { 1900 + year },
+            value[2..4].parse().unwrap_or_default(),
+            value[0..2].parse().unwrap_or_default(),
+        )
+    }
+
+    pub fn to_ddmmyy(&self) -> String {
+        format!("{:02}{:02}{:02}", self.day, self.month, self.year % 100)
+    }
+
+    pub fn days_since_epoch(&self) -> i64 {
+        // days from civil, see http://howardhinnant.github.io/date_algorithms.html
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let doy =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    /// Inverse of [`UtcDate::days_since_epoch`].
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let doe = days - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        Self {
+            year: year as u16,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+}
+
+/// Seconds since the unix epoch for the given UTC date and time.
+pub fn unix_time(date: &UtcDate, time: &UtcTime) -> f64 {
+    date.days_since_epoch() as f64 * 86400.0 + time.seconds_of_day()
+}
+
+/// Formats seconds since the unix epoch as ISO 8601 with milliseconds, e.g.
+/// `2004-03-11T16:00:12.710Z`.
+pub fn iso8601(unix_time: f64) -> String {
+    let millis = (unix_time * 1000.0).round() as i64;
+    let date = UtcDate::from_days_since_epoch(millis.div_euclid(86_400_000));
+    let millis = millis.rem_euclid(86_400_000);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        date.year,
+        date.month,
+        date.day,
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+pub(crate) fn check_msgtype(msg: &Nmea0183Msg, msgtype: &str) -> Result<(), String> {
+    if msg.msgtype() == msgtype {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid message type {}, expected {}",
+            msg.msgtype(),
+            msgtype
+        ))
+    }
+}
+
+/// Returns the field at the given 0-based parameter index, empty fields are `None`.
+pub(crate) fn field(msg: &Nmea0183Msg, idx: usize) -> Option<&str> {
+    msg.params()
+        .get(idx)
+        .map(|param| param.as_str())
+        .filter(|param| !param.is_empty())
+}
+
+pub(crate) fn parse_field<T: FromStr>(msg: &Nmea0183Msg, idx: usize) -> Result<Option<T>, String> {
+    field(msg, idx)
+        .map(|value| {
+            value.parse::<T>().map_err(|_| {
+                format!(
+                    "Invalid value '{}' in field {} of {}",
+                    value,
+                    idx + 1,
+                    msg.msgtype()
+                )
+            })
+        })
+        .transpose()
+}
+
+pub(crate) fn parse_char(msg: &Nmea0183Msg, idx: usize) -> Result<Option<char>, String> {
+    parse_field(msg, idx)
+}
+
+/// Converts `(d)ddmm.mm` and the hemisphere in field `idx + 1` to signed decimal degrees.
+pub(crate) fn parse_coord(msg: &Nmea0183Msg, idx: usize) -> Result<Option<f64>, String> {
+    let (value, hemisphere) = match (field(msg, idx), field(msg, idx + 1)) {
+        (Some(value), Some(hemisphere)) => (value, hemisphere),
+        _ => return Ok(None),
+    };
+    let invalid = || {
+        format!(
+            "Invalid coordinate '{},{}' in field {} of {}",
+            value,
+            hemisphere,
+            idx + 1,
+            msg.msgtype()
+        )
+    };
+    let raw: f64 = value.parse().map_err(|_| invalid())?;
+    let degrees = (raw / 100.0).trunc();
+    let decimal = degrees + (raw - degrees * 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Ok(Some(decimal)),
+        "S" | "W" => Ok(Some(-decimal)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Formats an optional value, `None` becomes an empty field.
+pub(crate) fn fmt_opt<T: fmt::Display>(value: &Option<T>) -> String {
+    value
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_default()
+}
+
+/// Inverse of [`parse_coord`], returns the `(d)ddmm.mmmm` field and the hemisphere.
+pub(crate) fn fmt_coord(value: &Option<f64>, latitude: bool) -> [String; 2] {
+    match value {
+        Some(value) => {
+            // round to the 4 decimals of the minutes first to never end up with 60 minutes
+            let total_minutes = (value.abs() * 600_000.0).round() / 10_000.0;
+            let degrees = (total_minutes / 60.0).trunc();
+            let minutes = total_minutes - degrees * 60.0;
+            let hemisphere = match (latitude, *value < 0.0) {
+                (true, false) => "N",
+                (true, true) => "S",
+                (false, false) => "E",
+                (false, true) => "W",
+            };
+            let width = if latitude { 2 } else { 3 };
+            [
+                format!("{:0width$}{:07.4}", degrees as u16, minutes, width = width),
+                hemisphere.to_string(),
+            ]
+        }
+        None => [String::new(), String::new()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time() {
+        let time: UtcTime = "160012.71".parse().expect("failed to parse time");
+        assert_eq!(time.hour, 16);
+        assert_eq!(time.minute, 0);
+        assert!((time.second - 12.71).abs() < 1e-9);
+        assert!("1600".parse::<UtcTime>().is_err());
+        assert!("250000".parse::<UtcTime>().is_err());
+        assert!("1\u{e9}345.71".parse::<UtcTime>().is_err());
+        assert!("+10000".parse::<UtcTime>().is_err());
+    }
+
+    #[test]
+    fn test_date() {
+        let date = UtcDate::from_ddmmyy("110304").expect("failed to parse date");
+        assert_eq!(date, UtcDate::new(2004, 3, 11).unwrap());
+        assert_eq!(UtcDate::new(1970, 1, 1).unwrap().days_since_epoch(), 0);
+        assert_eq!(UtcDate::new(2000, 3, 1).unwrap().days_since_epoch(), 11017);
+        assert!(UtcDate::from_ddmmyy("321304").is_err());
+        for days in [0, 11017, 12488, -1, 47541] {
+            assert_eq!(
+                UtcDate::from_days_since_epoch(days).days_since_epoch(),
+                days
+            );
+        }
+        assert_eq!(iso8601(1079020812.71), "2004-03-11T16:00:12.710Z");
+    }
+
+    #[test]
+    fn test_coord() {
+        let msg: Nmea0183Msg = "$GPGLL,0856.1964,N,07933.3281,W,184906.000,A,A*4E"
+            .parse()
+            .expect("failed to parse message");
+        let lat = parse_coord(&msg, 0).unwrap().unwrap();
+        let lon = parse_coord(&msg, 2).unwrap().unwrap();
+        assert!((lat - 8.936606).abs() < 1e-6);
+        assert!((lon + 79.555468).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_zda() {
+        let msg: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*7D"
+            .parse()
+            .expect("failed to parse message");
+        let zda = Zda::try_from(&msg).expect("failed to decode ZDA");
+        assert_eq!(zda.date, Some(UtcDate::new(2004, 3, 11).unwrap()));
+        assert_eq!(zda.zone_hours, Some(-1));
+        assert_eq!(zda.zone_minutes, Some(0));
+        assert!((zda.unix_time().unwrap() - 1079020812.71).abs() < 1e-6);
+        assert_eq!(
+            zda.to_msg("GP").to_string(),
+            "$GPZDA,160012.71,11,03,2004,-01,00*4D"
+        );
+        let params = ["1\u{e9}345.71", "11", "03", "2004", "00", "00"];
+        let msg = Nmea0183Msg::new("GP", "ZDA", params.iter().map(|p| p.to_string()).collect());
+        assert!(Zda::try_from(&msg).is_err());
+    }
+
+    #[test]
+    fn test_rmc() {
+        let msg: Nmea0183Msg =
+            "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E"
+                .parse()
+                .expect("failed to parse message");
+        let rmc = Rmc::try_from(&msg).expect("failed to decode RMC");
+        assert!(rmc.is_valid());
+        assert_eq!(rmc.date, Some(UtcDate::new(2014, 3, 30).unwrap()));
+        assert_eq!(rmc.mode, Some('A'));
+        assert!(Zda::try_from(&msg).is_err());
+        assert_eq!(
+            rmc.to_msg("GP").to_string(),
+            "$GPRMC,184906.00,A,0856.1964,N,07933.3281,W,0.3,222.3,300314,,,A*4E"
+        );
+    }
+
+    fn round_trip<T>(sentence: &str) -> T
+    where
+        T: Sentence + for<'a> TryFrom<&'a Nmea0183Msg, Error = String>,
+    {
+        let msg: Nmea0183Msg = sentence.parse().expect("failed to parse message");
+        assert_eq!(msg.chksum_valid(), Some(true), "{}", sentence);
+        let decoded = T::try_from(&msg).expect("failed to decode message");
+        assert_eq!(decoded.to_msg(msg.talker()).to_string(), sentence);
+        decoded
+    }
+
+    #[test]
+    fn test_ship_data() {
+        let hsc: Hsc = round_trip("$APHSC,40.1,T,39.5,M*5A");
+        assert_eq!(hsc.heading_true, Some(40.1));
+        let rot: Rot = round_trip("$HEROT,-12.5,A*30");
+        assert!(rot.is_valid());
+        let rsa: Rsa = round_trip("$IIRSA,-3.5,A,,V*52");
+        assert_eq!(rsa.port, None);
+        let rpm: Rpm = round_trip("$IIRPM,E,1,2200,-10.5,A*61");
+        assert_eq!(rpm.source, Some('E'));
+        let vhw: Vhw = round_trip("$VWVHW,223.5,T,220.1,M,5.5,N,10.2,K*60");
+        assert_eq!(vhw.speed_knots, Some(5.5));
+        let vlw: Vlw = round_trip("$VWVLW,1234.5,N,12.3,N*4D");
+        assert_eq!(vlw.trip_distance, Some(12.3));
+        let vbw: Vbw = round_trip("$VWVBW,5.5,-0.2,A,5.9,0.1,A*60");
+        assert_eq!(vbw.water_transverse, Some(-0.2));
+        let osd: Osd = round_trip("$RAOSD,223.5,A,225.1,B,5.9,B,,,N*48");
+        assert!(osd.is_valid());
+        let ttm: Ttm = round_trip("$RATTM,01,2.3,45.6,T,7.8,90.1,T,0.5,-12.3,N,TGT01,T,*36");
+        assert_eq!(ttm.name.as_deref(), Some("TGT01"));
+        assert_eq!(ttm.time, None);
+        let dbt: Dbt = round_trip("$SDDBT,35.1,f,10.7,M,5.8,F*0A");
+        assert_eq!(dbt.depth(), Some(10.7));
+        let dpt: Dpt = round_trip("$SDDPT,10.7,-1.2*4F");
+        assert!((dpt.below_keel().unwrap() - 9.5).abs() < 1e-9);
+        assert_eq!(dpt.below_surface(), None);
+        let ttm: Ttm = round_trip("$RATTM,02,2.3,45.6,T,7.8,90.1,T,0.5,12.3,N,,Q,,120000.00,A*37");
+        assert_eq!(ttm.acquisition, Some('A'));
+    }
+
+    #[test]
+    fn test_gnss() {
+        let gga: Gga =
+            round_trip("$GPGGA,184906.00,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*73");
+        assert!(gga.is_valid());
+        assert!((gga.latitude.unwrap() - 8.936607).abs() < 1e-6);
+        assert_eq!(gga.satellites, Some(11));
+        assert_eq!(gga.altitude, Some(50.8));
+        assert_eq!(gga.dgps_station.as_deref(), Some("0000"));
+        let gll: Gll = round_trip("$GPGLL,0856.1964,N,07933.3281,W,184906.00,A,A*7E");
+        assert!(gll.is_valid());
+        assert!((gll.longitude.unwrap() + 79.555468).abs() < 1e-6);
+        let gll: Gll = round_trip("$GPGLL,,,,,184907.00,V*2B");
+        assert!(!gll.is_valid());
+        assert_eq!(gll.latitude, None);
+        let gsa: Gsa = round_trip("$GPGSA,A,3,29,31,01,27,16,32,22,20,14,18,03,,1.4,0.7,1.2*35");
+        assert_eq!(gsa.fix_mode, Some(3));
+        assert_eq!(gsa.satellites.len(), 11);
+        assert_eq!(gsa.vdop, Some(1.2));
+        let gsv: Gsv =
+            round_trip("$GPGSV,3,2,12,14,35,065,45,06,19,181,,27,13,188,38,20,09,319,30*72");
+        assert!(!gsv.is_last());
+        assert_eq!(gsv.satellites.len(), 4);
+        assert_eq!(gsv.satellites[1].snr, None);
+        assert_eq!(gsv.satellites[1].azimuth, Some(181));
+    }
+
+    #[test]
+    fn test_encode() {
+        use crate::Nmea0183Codec;
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let mut codec = Nmea0183Codec::default();
+        let mut buf = BytesMut::new();
+        let rot = Rot {
+            rate: Some(3.2),
+            status: Some('A'),
+        };
+        codec.encode(rot.to_msg("HE"), &mut buf).unwrap();
+        assert_eq!(&buf[..], b"$HEROT,3.2,A*2A\r\n");
+        let msg = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(msg.chksum_valid(), Some(true));
+        assert_eq!(Rot::try_from(&msg).unwrap(), rot);
+    }
+
+    #[test]
+    fn test_xdr() {
+        let msg: Nmea0183Msg = "$IIXDR,P,1.02481,B,Barometer,A,-3.5,D,HEEL,C,,C,ENGINE*23"
+            .parse()
+            .expect("failed to parse message");
+        let xdr = Xdr::try_from(&msg).expect("failed to decode XDR");
+        assert_eq!(xdr.measurements.len(), 3);
+        assert_eq!(xdr.measurements[0].transducer, TransducerType::Pressure);
+        assert_eq!(xdr.measurements[0].name, "Barometer");
+        assert!((xdr.measurements[0].si_value().unwrap() - 102481.0).abs() < 1e-6);
+        assert_eq!(xdr.measurements[0].si_unit(), "Pa");
+        assert!((xdr.measurements[1].si_value().unwrap() + 0.0610865).abs() < 1e-6);
+        assert_eq!(xdr.measurements[2].value, None);
+    }
+}
@@ -0,0 +1,48 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// DBT - Depth below transducer
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dbt {
+    pub depth_feet: Option<f64>,
+    pub depth_meters: Option<f64>,
+    pub depth_fathoms: Option<f64>,
+}
+
+impl Dbt {
+    /// Depth in meters, converted from feet or fathoms if the meters field is empty.
+    pub fn depth(&self) -> Option<f64> {
+        self.depth_meters
+            .or(self.depth_feet.map(|feet| feet * 0.3048))
+            .or(self.depth_fathoms.map(|fathoms| fathoms * 1.8288))
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Dbt {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            depth_feet: parse_field(msg, 0)?,
+            depth_meters: parse_field(msg, 2)?,
+            depth_fathoms: parse_field(msg, 4)?,
+        })
+    }
+}
+
+impl Sentence for Dbt {
+    const MSGTYPE: &'static str = "DBT";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.depth_feet),
+            "f".to_string(),
+            fmt_opt(&self.depth_meters),
+            "M".to_string(),
+            fmt_opt(&self.depth_fathoms),
+            "F".to_string(),
+        ]
+    }
+}
@@ -0,0 +1,56 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// DPT - Depth of Water
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Dpt {
+    /// Depth below the transducer in meters.
+    pub depth: Option<f64>,
+    /// Positive: distance from the transducer to the water line, negative: distance from
+    /// the transducer to the keel.
+    pub offset: Option<f64>,
+    /// Maximum range scale in use, NMEA 3.0 and later.
+    pub range: Option<f64>,
+}
+
+impl Dpt {
+    pub fn below_surface(&self) -> Option<f64> {
+        match (self.depth, self.offset) {
+            (Some(depth), Some(offset)) if offset >= 0.0 => Some(depth + offset),
+            _ => None,
+        }
+    }
+
+    pub fn below_keel(&self) -> Option<f64> {
+        match (self.depth, self.offset) {
+            (Some(depth), Some(offset)) if offset < 0.0 => Some(depth + offset),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Dpt {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            depth: parse_field(msg, 0)?,
+            offset: parse_field(msg, 1)?,
+            range: parse_field(msg, 2)?,
+        })
+    }
+}
+
+impl Sentence for Dpt {
+    const MSGTYPE: &'static str = "DPT";
+
+    fn encode_params(&self) -> Vec<String> {
+        let mut params = vec![fmt_opt(&self.depth), fmt_opt(&self.offset)];
+        if self.range.is_some() {
+            params.push(fmt_opt(&self.range));
+        }
+        params
+    }
+}
@@ -0,0 +1,79 @@
+use crate::sentence::{
+    check_msgtype, field, fmt_coord, fmt_opt, parse_coord, parse_field, Sentence, UtcTime,
+};
+use crate::Nmea0183Msg;
+
+/// GGA - Global Positioning System Fix Data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gga {
+    pub time: Option<UtcTime>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// 0 invalid, 1 GPS, 2 DGPS, 4 RTK fixed, 5 RTK float, 6 estimated
+    pub quality: Option<u8>,
+    pub satellites: Option<u8>,
+    pub hdop: Option<f64>,
+    /// Antenna altitude above mean sea level in meters.
+    pub altitude: Option<f64>,
+    /// Height of the geoid above the WGS84 ellipsoid in meters.
+    pub geoid_separation: Option<f64>,
+    /// Seconds since the last DGPS update.
+    pub dgps_age: Option<f64>,
+    pub dgps_station: Option<String>,
+}
+
+impl Gga {
+    /// Quality other than 0.
+    pub fn is_valid(&self) -> bool {
+        self.quality.is_some_and(|quality| quality != 0)
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Gga {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            time: field(msg, 0).map(str::parse).transpose()?,
+            latitude: parse_coord(msg, 1)?,
+            longitude: parse_coord(msg, 3)?,
+            quality: parse_field(msg, 5)?,
+            satellites: parse_field(msg, 6)?,
+            hdop: parse_field(msg, 7)?,
+            altitude: parse_field(msg, 8)?,
+            geoid_separation: parse_field(msg, 10)?,
+            dgps_age: parse_field(msg, 12)?,
+            dgps_station: field(msg, 13).map(str::to_string),
+        })
+    }
+}
+
+impl Sentence for Gga {
+    const MSGTYPE: &'static str = "GGA";
+
+    fn encode_params(&self) -> Vec<String> {
+        let [latitude, north_south] = fmt_coord(&self.latitude, true);
+        let [longitude, east_west] = fmt_coord(&self.longitude, false);
+        let meters = |value: &Option<f64>| if value.is_some() { "M" } else { "" }.to_string();
+        vec![
+            fmt_opt(&self.time),
+            latitude,
+            north_south,
+            longitude,
+            east_west,
+            fmt_opt(&self.quality),
+            self.satellites
+                .map(|satellites| format!("{:02}", satellites))
+                .unwrap_or_default(),
+            fmt_opt(&self.hdop),
+            fmt_opt(&self.altitude),
+            meters(&self.altitude),
+            fmt_opt(&self.geoid_separation),
+            meters(&self.geoid_separation),
+            fmt_opt(&self.dgps_age),
+            fmt_opt(&self.dgps_station),
+        ]
+    }
+}
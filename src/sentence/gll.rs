@@ -0,0 +1,59 @@
+use crate::sentence::{
+    check_msgtype, field, fmt_coord, fmt_opt, parse_char, parse_coord, Sentence, UtcTime,
+};
+use crate::Nmea0183Msg;
+
+/// GLL - Geographic Position - Latitude/Longitude
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gll {
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub time: Option<UtcTime>,
+    pub status: Option<char>,
+    pub mode: Option<char>,
+}
+
+impl Gll {
+    /// Status `A` and no mode or a mode other than `N`, data not valid.
+    pub fn is_valid(&self) -> bool {
+        self.status == Some('A') && self.mode != Some('N')
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Gll {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            latitude: parse_coord(msg, 0)?,
+            longitude: parse_coord(msg, 2)?,
+            time: field(msg, 4).map(str::parse).transpose()?,
+            status: parse_char(msg, 5)?,
+            mode: parse_char(msg, 6)?,
+        })
+    }
+}
+
+impl Sentence for Gll {
+    const MSGTYPE: &'static str = "GLL";
+
+    fn encode_params(&self) -> Vec<String> {
+        let [latitude, north_south] = fmt_coord(&self.latitude, true);
+        let [longitude, east_west] = fmt_coord(&self.longitude, false);
+        let mut params = vec![
+            latitude,
+            north_south,
+            longitude,
+            east_west,
+            fmt_opt(&self.time),
+            fmt_opt(&self.status),
+        ];
+        // the mode indicator was added in NMEA 2.3
+        if self.mode.is_some() {
+            params.push(fmt_opt(&self.mode));
+        }
+        params
+    }
+}
@@ -0,0 +1,65 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+const SATELLITE_SLOTS: usize = 12;
+
+/// GSA - GPS DOP and active satellites
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gsa {
+    /// `M` manual, `A` automatic 2D/3D
+    pub selection_mode: Option<char>,
+    /// 1 no fix, 2 2D, 3 3D
+    pub fix_mode: Option<u8>,
+    /// PRNs of the satellites used for the fix, empty slots are skipped.
+    pub satellites: Vec<u16>,
+    pub pdop: Option<f64>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+    /// GNSS system id, NMEA 4.1 and later.
+    pub system_id: Option<u8>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Gsa {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        let mut satellites = Vec::new();
+        for idx in 2..2 + SATELLITE_SLOTS {
+            if let Some(prn) = parse_field(msg, idx)? {
+                satellites.push(prn);
+            }
+        }
+        Ok(Self {
+            selection_mode: parse_char(msg, 0)?,
+            fix_mode: parse_field(msg, 1)?,
+            satellites,
+            pdop: parse_field(msg, 14)?,
+            hdop: parse_field(msg, 15)?,
+            vdop: parse_field(msg, 16)?,
+            system_id: parse_field(msg, 17)?,
+        })
+    }
+}
+
+impl Sentence for Gsa {
+    const MSGTYPE: &'static str = "GSA";
+
+    fn encode_params(&self) -> Vec<String> {
+        let mut params = vec![fmt_opt(&self.selection_mode), fmt_opt(&self.fix_mode)];
+        params.extend((0..SATELLITE_SLOTS).map(|idx| {
+            self.satellites
+                .get(idx)
+                .map(|prn| format!("{:02}", prn))
+                .unwrap_or_default()
+        }));
+        params.push(fmt_opt(&self.pdop));
+        params.push(fmt_opt(&self.hdop));
+        params.push(fmt_opt(&self.vdop));
+        if let Some(system_id) = self.system_id {
+            params.push(system_id.to_string());
+        }
+        params
+    }
+}
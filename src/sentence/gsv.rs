@@ -0,0 +1,103 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+const FIELDS_PER_SATELLITE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GsvSatellite {
+    pub prn: Option<u16>,
+    /// Elevation in degrees, 90 maximum.
+    pub elevation: Option<i16>,
+    /// Azimuth in degrees from true north.
+    pub azimuth: Option<u16>,
+    /// Signal to noise ratio in dB-Hz, `None` if not tracking.
+    pub snr: Option<u8>,
+}
+
+/// GSV - Satellites in view, one of `total_messages` sentences with up to four
+/// satellites each.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gsv {
+    pub total_messages: Option<u8>,
+    pub message_number: Option<u8>,
+    pub satellites_in_view: Option<u8>,
+    pub satellites: Vec<GsvSatellite>,
+}
+
+impl Gsv {
+    pub fn is_last(&self) -> bool {
+        self.total_messages.is_some() && self.total_messages == self.message_number
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Gsv {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        let mut satellites = Vec::new();
+        // NMEA 4.1 appends a signal id, it leaves an incomplete group of fields
+        let mut idx = 3;
+        while idx + FIELDS_PER_SATELLITE <= msg.params().len() {
+            let satellite = GsvSatellite {
+                prn: parse_field(msg, idx)?,
+                elevation: parse_field(msg, idx + 1)?,
+                azimuth: parse_field(msg, idx + 2)?,
+                snr: parse_field(msg, idx + 3)?,
+            };
+            if satellite.prn.is_some() {
+                satellites.push(satellite);
+            }
+            idx += FIELDS_PER_SATELLITE;
+        }
+        Ok(Self {
+            total_messages: parse_field(msg, 0)?,
+            message_number: parse_field(msg, 1)?,
+            satellites_in_view: parse_field(msg, 2)?,
+            satellites,
+        })
+    }
+}
+
+impl Sentence for Gsv {
+    const MSGTYPE: &'static str = "GSV";
+
+    fn encode_params(&self) -> Vec<String> {
+        let mut params = vec![
+            fmt_opt(&self.total_messages),
+            fmt_opt(&self.message_number),
+            self.satellites_in_view
+                .map(|count| format!("{:02}", count))
+                .unwrap_or_default(),
+        ];
+        for satellite in &self.satellites {
+            params.push(
+                satellite
+                    .prn
+                    .map(|prn| format!("{:02}", prn))
+                    .unwrap_or_default(),
+            );
+            params.push(
+                satellite
+                    .elevation
+                    .map(|elevation| format!("{:02}", elevation))
+                    .unwrap_or_default(),
+            );
+            params.push(
+                satellite
+                    .azimuth
+                    .map(|azimuth| format!("{:03}", azimuth))
+                    .unwrap_or_default(),
+            );
+            params.push(
+                satellite
+                    .snr
+                    .map(|snr| format!("{:02}", snr))
+                    .unwrap_or_default(),
+            );
+        }
+        params
+    }
+}
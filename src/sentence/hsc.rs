@@ -0,0 +1,35 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// HSC - Heading Steering Command
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hsc {
+    pub heading_true: Option<f64>,
+    pub heading_magnetic: Option<f64>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Hsc {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            heading_true: parse_field(msg, 0)?,
+            heading_magnetic: parse_field(msg, 2)?,
+        })
+    }
+}
+
+impl Sentence for Hsc {
+    const MSGTYPE: &'static str = "HSC";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.heading_true),
+            "T".to_string(),
+            fmt_opt(&self.heading_magnetic),
+            "M".to_string(),
+        ]
+    }
+}
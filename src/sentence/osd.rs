@@ -0,0 +1,61 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// OSD - Own Ship Data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Osd {
+    pub heading: Option<f64>,
+    pub status: Option<char>,
+    pub course: Option<f64>,
+    pub course_reference: Option<char>,
+    pub speed: Option<f64>,
+    pub speed_reference: Option<char>,
+    pub set: Option<f64>,
+    pub drift: Option<f64>,
+    /// `K` km/h, `N` knots, `S` statute miles/h
+    pub speed_units: Option<char>,
+}
+
+impl Osd {
+    pub fn is_valid(&self) -> bool {
+        self.status == Some('A')
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Osd {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            heading: parse_field(msg, 0)?,
+            status: parse_char(msg, 1)?,
+            course: parse_field(msg, 2)?,
+            course_reference: parse_char(msg, 3)?,
+            speed: parse_field(msg, 4)?,
+            speed_reference: parse_char(msg, 5)?,
+            set: parse_field(msg, 6)?,
+            drift: parse_field(msg, 7)?,
+            speed_units: parse_char(msg, 8)?,
+        })
+    }
+}
+
+impl Sentence for Osd {
+    const MSGTYPE: &'static str = "OSD";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.heading),
+            fmt_opt(&self.status),
+            fmt_opt(&self.course),
+            fmt_opt(&self.course_reference),
+            fmt_opt(&self.speed),
+            fmt_opt(&self.speed_reference),
+            fmt_opt(&self.set),
+            fmt_opt(&self.drift),
+            fmt_opt(&self.speed_units),
+        ]
+    }
+}
@@ -0,0 +1,222 @@
+use crate::Nmea0183Msg;
+use std::any::Any;
+use std::collections::HashMap;
+
+mod ashtech;
+mod garmin;
+mod magellan;
+mod rockwell;
+mod ublox;
+
+pub use ashtech::{AshtechDecoder, Pashr};
+pub use garmin::{GarminDecoder, Pgrme};
+pub use magellan::{MagellanDecoder, Pmgnst};
+pub use rockwell::{Prwizch, RockwellDecoder};
+pub use ublox::{
+    Pubx, PubxFixStatus, PubxPosition, PubxSatellite, PubxSatellites, PubxTime, PubxUtm,
+    UbloxDecoder,
+};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Proprietary {
+    Pashr(Pashr),
+    Pgrme(Pgrme),
+    Pmgnst(Pmgnst),
+    Prwizch(Prwizch),
+    Pubx(Pubx),
+    /// Output of decoders registered by the user.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(Box<dyn Any + Send + Sync>),
+}
+
+/// Decoder for the `$P` sentences of one manufacturer.
+pub trait ProprietaryDecoder: Send + Sync {
+    /// The three letter manufacturer mnemonic following the `P`, e.g. `UBX`.
+    fn manufacturer(&self) -> &str;
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String>;
+}
+
+/// Dispatches proprietary sentences to the decoder registered for their manufacturer.
+pub struct ProprietaryDecoders {
+    decoders: HashMap<String, Box<dyn ProprietaryDecoder>>,
+}
+
+impl Default for ProprietaryDecoders {
+    /// All built-in decoders.
+    fn default() -> Self {
+        Self::new()
+            .with(AshtechDecoder)
+            .with(GarminDecoder)
+            .with(MagellanDecoder)
+            .with(RockwellDecoder)
+            .with(UbloxDecoder)
+    }
+}
+
+impl ProprietaryDecoders {
+    /// An empty set of decoders.
+    pub fn new() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+
+    pub fn with<D: ProprietaryDecoder + 'static>(mut self, decoder: D) -> Self {
+        self.register(decoder);
+        self
+    }
+
+    /// Registers a decoder, replacing any decoder for the same manufacturer.
+    pub fn register<D: ProprietaryDecoder + 'static>(&mut self, decoder: D) {
+        self.decoders
+            .insert(decoder.manufacturer().to_string(), Box::new(decoder));
+    }
+
+    pub fn remove(&mut self, manufacturer: &str) -> bool {
+        self.decoders.remove(manufacturer).is_some()
+    }
+
+    pub fn manufacturers(&self) -> impl Iterator<Item = &str> {
+        self.decoders
+            .keys()
+            .map(|manufacturer| manufacturer.as_str())
+    }
+
+    /// `None` if the sentence is not proprietary or no decoder is registered for the
+    /// manufacturer.
+    pub fn decode(&self, msg: &Nmea0183Msg) -> Option<Result<Proprietary, String>> {
+        let decoder = self.decoders.get(msg.manufacturer()?)?;
+        Some(decoder.decode(msg))
+    }
+}
+
+pub(crate) fn check_proprietary(msg: &Nmea0183Msg, msgtype: &str) -> Result<(), String> {
+    if msg.is_proprietary() && msg.msgtype() == msgtype {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid message type {}{}, expected P{}",
+            msg.talker(),
+            msg.msgtype(),
+            msgtype
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_pashr() {
+        let msg = msg("$PASHR,085335.000,224.19,T,-01.26,+00.83,+00.00,0.101,0.113,0.267,1,0*06");
+        assert_eq!(msg.manufacturer(), Some("ASH"));
+        match ProprietaryDecoders::default().decode(&msg) {
+            Some(Ok(Proprietary::Pashr(pashr))) => {
+                assert_eq!(pashr.heading, Some(224.19));
+                assert!(pashr.true_heading);
+                assert_eq!(pashr.roll, Some(-1.26));
+                assert_eq!(pashr.pitch, Some(0.83));
+                assert_eq!(pashr.aiding_status, Some(1));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pgrme() {
+        let pgrme = Pgrme::try_from(&msg("$PGRME,15.0,M,45.0,M,25.0,M*1C")).unwrap();
+        assert_eq!(pgrme.horizontal_error, Some(15.0));
+        assert_eq!(pgrme.vertical_error, Some(45.0));
+        assert_eq!(pgrme.spherical_error, Some(25.0));
+    }
+
+    #[test]
+    fn test_pmgnst() {
+        let pmgnst = Pmgnst::try_from(&msg("$PMGNST,02.12,3,T,534,05.0,+03327,00*40")).unwrap();
+        assert_eq!(pmgnst.version.as_deref(), Some("02.12"));
+        assert_eq!(pmgnst.mode, Some(3));
+        assert!(pmgnst.fix);
+        assert_eq!(pmgnst.battery_hours, Some(5.0));
+    }
+
+    #[test]
+    fn test_prwizch() {
+        let prwizch = Prwizch::try_from(&msg(
+            "$PRWIZCH,00,0,03,7,19,7,22,7,01,5,17,7,31,7,06,7,15,2,09,7,14,0,00,0*4D",
+        ))
+        .unwrap();
+        assert_eq!(prwizch.channels.len(), 12);
+        assert_eq!(prwizch.channels[1], (3, 7));
+    }
+
+    #[test]
+    fn test_pubx() {
+        let decoders = ProprietaryDecoders::new().with(UbloxDecoder);
+        let position = msg("$PUBX,00,081350.00,4717.113210,N,00833.915187,E,546.589,G3,2.1,2.0,0.007,77.52,0.007,,0.92,1.19,0.77,9,0,0*5F");
+        match decoders.decode(&position) {
+            Some(Ok(Proprietary::Pubx(Pubx::Position(position)))) => {
+                assert!((position.latitude.unwrap() - 47.285220).abs() < 1e-6);
+                assert_eq!(position.status.nav_status.as_deref(), Some("G3"));
+                assert_eq!(position.status.gps_satellites, Some(9));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let time =
+            msg("$PUBX,04,073731.00,091202,113851.00,1196,113851.00,1930035,-2660.664,43,*3C");
+        match decoders.decode(&time) {
+            Some(Ok(Proprietary::Pubx(Pubx::Time(time)))) => {
+                assert_eq!(time.week, Some(1196));
+                assert_eq!(time.clock_bias, Some(1930035));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let satellites = msg("$PUBX,03,03,23,-,,,45,010,29,-,,,46,013,07,U,067,31,42,025*45");
+        match decoders.decode(&satellites) {
+            Some(Ok(Proprietary::Pubx(Pubx::Satellites(satellites)))) => {
+                assert_eq!(satellites.satellites.len(), 3);
+                assert_eq!(satellites.satellites[2].status, Some('U'));
+                assert_eq!(satellites.satellites[2].elevation, Some(31));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        // Garmin decoder not registered
+        assert!(decoders
+            .decode(&msg("$PGRME,15.0,M,45.0,M,25.0,M*1C"))
+            .is_none());
+        assert!(decoders
+            .decode(&msg("$GPZDA,160012.71,11,03,2004,-1,00*7D"))
+            .is_none());
+    }
+
+    struct Custom;
+
+    impl ProprietaryDecoder for Custom {
+        fn manufacturer(&self) -> &str {
+            "XYZ"
+        }
+
+        fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+            Ok(Proprietary::Custom(Box::new(msg.params().len())))
+        }
+    }
+
+    #[test]
+    fn test_custom() {
+        let decoders = ProprietaryDecoders::default().with(Custom);
+        match decoders.decode(&msg("$PXYZ1,a,b,c*76")) {
+            Some(Ok(Proprietary::Custom(value))) => {
+                assert_eq!(value.downcast_ref::<usize>(), Some(&3))
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
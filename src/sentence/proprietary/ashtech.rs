@@ -0,0 +1,53 @@
+use crate::sentence::proprietary::{check_proprietary, Proprietary, ProprietaryDecoder};
+use crate::sentence::{field, parse_field, UtcTime};
+use crate::Nmea0183Msg;
+
+/// PASHR - RT300 proprietary roll and pitch sentence
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pashr {
+    pub time: Option<UtcTime>,
+    pub heading: Option<f64>,
+    pub true_heading: bool,
+    pub roll: Option<f64>,
+    pub pitch: Option<f64>,
+    pub heave: Option<f64>,
+    pub roll_accuracy: Option<f64>,
+    pub pitch_accuracy: Option<f64>,
+    pub heading_accuracy: Option<f64>,
+    pub aiding_status: Option<u8>,
+    pub imu_status: Option<u8>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Pashr {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_proprietary(msg, "ASHR")?;
+        Ok(Self {
+            time: field(msg, 0).map(str::parse).transpose()?,
+            heading: parse_field(msg, 1)?,
+            true_heading: field(msg, 2) == Some("T"),
+            roll: parse_field(msg, 3)?,
+            pitch: parse_field(msg, 4)?,
+            heave: parse_field(msg, 5)?,
+            roll_accuracy: parse_field(msg, 6)?,
+            pitch_accuracy: parse_field(msg, 7)?,
+            heading_accuracy: parse_field(msg, 8)?,
+            aiding_status: parse_field(msg, 9)?,
+            imu_status: parse_field(msg, 10)?,
+        })
+    }
+}
+
+pub struct AshtechDecoder;
+
+impl ProprietaryDecoder for AshtechDecoder {
+    fn manufacturer(&self) -> &str {
+        "ASH"
+    }
+
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+        Pashr::try_from(msg).map(Proprietary::Pashr)
+    }
+}
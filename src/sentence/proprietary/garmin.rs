@@ -0,0 +1,40 @@
+use crate::sentence::parse_field;
+use crate::sentence::proprietary::{check_proprietary, Proprietary, ProprietaryDecoder};
+use crate::Nmea0183Msg;
+
+/// PGRME - Garmin Estimated Error, all values in meters
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pgrme {
+    pub horizontal_error: Option<f64>,
+    pub vertical_error: Option<f64>,
+    pub spherical_error: Option<f64>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Pgrme {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_proprietary(msg, "GRME")?;
+        Ok(Self {
+            horizontal_error: parse_field(msg, 0)?,
+            vertical_error: parse_field(msg, 2)?,
+            spherical_error: parse_field(msg, 4)?,
+        })
+    }
+}
+
+pub struct GarminDecoder;
+
+impl ProprietaryDecoder for GarminDecoder {
+    fn manufacturer(&self) -> &str {
+        "GRM"
+    }
+
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+        match msg.msgtype() {
+            "GRME" => Pgrme::try_from(msg).map(Proprietary::Pgrme),
+            other => Err(format!("Unsupported Garmin sentence P{}", other)),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::sentence::proprietary::{check_proprietary, Proprietary, ProprietaryDecoder};
+use crate::sentence::{field, parse_field};
+use crate::Nmea0183Msg;
+
+/// PMGNST - Magellan Status
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pmgnst {
+    pub version: Option<String>,
+    /// 1 = no fix, 2 = 2D fix, 3 = 3D fix
+    pub mode: Option<u8>,
+    pub fix: bool,
+    pub battery_hours: Option<f64>,
+    pub focus_prn: Option<u8>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Pmgnst {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_proprietary(msg, "MGNST")?;
+        Ok(Self {
+            version: field(msg, 0).map(str::to_string),
+            mode: parse_field(msg, 1)?,
+            fix: field(msg, 2) == Some("T"),
+            battery_hours: parse_field(msg, 4)?,
+            focus_prn: parse_field(msg, 6)?,
+        })
+    }
+}
+
+pub struct MagellanDecoder;
+
+impl ProprietaryDecoder for MagellanDecoder {
+    fn manufacturer(&self) -> &str {
+        "MGN"
+    }
+
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+        match msg.msgtype() {
+            "MGNST" => Pmgnst::try_from(msg).map(Proprietary::Pmgnst),
+            other => Err(format!("Unsupported Magellan sentence P{}", other)),
+        }
+    }
+}
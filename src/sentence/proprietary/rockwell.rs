@@ -0,0 +1,42 @@
+use crate::sentence::parse_field;
+use crate::sentence::proprietary::{check_proprietary, Proprietary, ProprietaryDecoder};
+use crate::Nmea0183Msg;
+
+/// PRWIZCH - Rockwell Channel Status
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Prwizch {
+    /// Satellite PRN and signal quality 0 (worst) - 7 (best) of the used channels.
+    pub channels: Vec<(u8, u8)>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Prwizch {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_proprietary(msg, "RWIZCH")?;
+        let mut channels = Vec::new();
+        for idx in (0..msg.params().len()).step_by(2) {
+            if let (Some(prn), Some(quality)) = (parse_field(msg, idx)?, parse_field(msg, idx + 1)?)
+            {
+                channels.push((prn, quality));
+            }
+        }
+        Ok(Self { channels })
+    }
+}
+
+pub struct RockwellDecoder;
+
+impl ProprietaryDecoder for RockwellDecoder {
+    fn manufacturer(&self) -> &str {
+        "RWI"
+    }
+
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+        match msg.msgtype() {
+            "RWIZCH" => Prwizch::try_from(msg).map(Proprietary::Prwizch),
+            other => Err(format!("Unsupported Rockwell sentence P{}", other)),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use crate::sentence::proprietary::{check_proprietary, Proprietary, ProprietaryDecoder};
+use crate::sentence::{field, parse_char, parse_coord, parse_field, UtcDate, UtcTime};
+use crate::Nmea0183Msg;
+
+/// Navigation status fields shared by PUBX 00 and PUBX 01.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxFixStatus {
+    /// e.g. `NF` no fix, `G2`/`G3` 2D/3D fix, `D2`/`D3` differential fix, `DR` dead reckoning
+    pub nav_status: Option<String>,
+    pub horizontal_accuracy: Option<f64>,
+    pub vertical_accuracy: Option<f64>,
+    pub speed_kmh: Option<f64>,
+    pub course: Option<f64>,
+    /// m/s, positive downwards
+    pub vertical_velocity: Option<f64>,
+    pub age_corrections: Option<f64>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+    pub tdop: Option<f64>,
+    pub gps_satellites: Option<u8>,
+    pub glonass_satellites: Option<u8>,
+    pub dead_reckoning: Option<u8>,
+}
+
+impl PubxFixStatus {
+    fn parse(msg: &Nmea0183Msg) -> Result<Self, String> {
+        Ok(Self {
+            nav_status: field(msg, 7).map(str::to_string),
+            horizontal_accuracy: parse_field(msg, 8)?,
+            vertical_accuracy: parse_field(msg, 9)?,
+            speed_kmh: parse_field(msg, 10)?,
+            course: parse_field(msg, 11)?,
+            vertical_velocity: parse_field(msg, 12)?,
+            age_corrections: parse_field(msg, 13)?,
+            hdop: parse_field(msg, 14)?,
+            vdop: parse_field(msg, 15)?,
+            tdop: parse_field(msg, 16)?,
+            gps_satellites: parse_field(msg, 17)?,
+            glonass_satellites: parse_field(msg, 18)?,
+            dead_reckoning: parse_field(msg, 19)?,
+        })
+    }
+}
+
+/// PUBX 00 - uBlox Lat/Long Position Data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxPosition {
+    pub time: Option<UtcTime>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// Altitude above the user datum ellipsoid in meters.
+    pub altitude: Option<f64>,
+    pub status: PubxFixStatus,
+}
+
+/// PUBX 01 - uBlox UTM Position Data
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxUtm {
+    pub time: Option<UtcTime>,
+    pub easting: Option<f64>,
+    pub northing: Option<f64>,
+    /// Altitude above mean sea level in meters.
+    pub altitude: Option<f64>,
+    pub status: PubxFixStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxSatellite {
+    pub id: Option<u8>,
+    /// `-` not used, `U` used in solution, `e` ephemeris available but not used
+    pub status: Option<char>,
+    pub azimuth: Option<u16>,
+    pub elevation: Option<u8>,
+    pub snr: Option<u8>,
+    /// Carrier lock time in seconds.
+    pub lock_time: Option<u16>,
+}
+
+/// PUBX 03 - uBlox Satellite Status
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxSatellites {
+    pub satellites: Vec<PubxSatellite>,
+}
+
+/// PUBX 04 - uBlox Time of Day and Clock Information
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PubxTime {
+    pub time: Option<UtcTime>,
+    pub date: Option<UtcDate>,
+    /// UTC time of week in seconds.
+    pub utc_tow: Option<f64>,
+    pub week: Option<u16>,
+    /// Receiver clock bias in ns.
+    pub clock_bias: Option<i64>,
+    /// Receiver clock drift in ns/s.
+    pub clock_drift: Option<f64>,
+    /// Time pulse granularity in ns.
+    pub pulse_granularity: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Pubx {
+    Position(PubxPosition),
+    Utm(PubxUtm),
+    Satellites(PubxSatellites),
+    Time(PubxTime),
+}
+
+impl TryFrom<&Nmea0183Msg> for Pubx {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_proprietary(msg, "UBX")?;
+        match field(msg, 0) {
+            Some("00") => Ok(Self::Position(PubxPosition {
+                time: field(msg, 1).map(str::parse).transpose()?,
+                latitude: parse_coord(msg, 2)?,
+                longitude: parse_coord(msg, 4)?,
+                altitude: parse_field(msg, 6)?,
+                status: PubxFixStatus::parse(msg)?,
+            })),
+            Some("01") => Ok(Self::Utm(PubxUtm {
+                time: field(msg, 1).map(str::parse).transpose()?,
+                easting: parse_field(msg, 2)?,
+                northing: parse_field(msg, 4)?,
+                altitude: parse_field(msg, 6)?,
+                status: PubxFixStatus::parse(msg)?,
+            })),
+            Some("03") => {
+                let mut satellites = Vec::new();
+                for idx in (2..msg.params().len()).step_by(6) {
+                    satellites.push(PubxSatellite {
+                        id: parse_field(msg, idx)?,
+                        status: parse_char(msg, idx + 1)?,
+                        azimuth: parse_field(msg, idx + 2)?,
+                        elevation: parse_field(msg, idx + 3)?,
+                        snr: parse_field(msg, idx + 4)?,
+                        lock_time: parse_field(msg, idx + 5)?,
+                    });
+                }
+                Ok(Self::Satellites(PubxSatellites { satellites }))
+            }
+            Some("04") => Ok(Self::Time(PubxTime {
+                time: field(msg, 1).map(str::parse).transpose()?,
+                date: field(msg, 2).map(UtcDate::from_ddmmyy).transpose()?,
+                utc_tow: parse_field(msg, 3)?,
+                week: parse_field(msg, 4)?,
+                clock_bias: parse_field(msg, 6)?,
+                clock_drift: parse_field(msg, 7)?,
+                pulse_granularity: parse_field(msg, 8)?,
+            })),
+            other => Err(format!("Unsupported PUBX message id {:?}", other)),
+        }
+    }
+}
+
+pub struct UbloxDecoder;
+
+impl ProprietaryDecoder for UbloxDecoder {
+    fn manufacturer(&self) -> &str {
+        "UBX"
+    }
+
+    fn decode(&self, msg: &Nmea0183Msg) -> Result<Proprietary, String> {
+        Pubx::try_from(msg).map(Proprietary::Pubx)
+    }
+}
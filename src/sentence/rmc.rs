@@ -0,0 +1,85 @@
+use crate::sentence::{
+    check_msgtype, field, fmt_coord, fmt_opt, parse_char, parse_coord, parse_field, unix_time,
+    Sentence, UtcDate, UtcTime,
+};
+use crate::Nmea0183Msg;
+
+/// RMC - Recommended Minimum Navigation Information
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rmc {
+    pub time: Option<UtcTime>,
+    pub status: Option<char>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub speed_knots: Option<f64>,
+    pub course: Option<f64>,
+    pub date: Option<UtcDate>,
+    pub magnetic_variation: Option<f64>,
+    pub mode: Option<char>,
+}
+
+impl Rmc {
+    /// Status `A`, the receiver reports a valid fix.
+    pub fn is_valid(&self) -> bool {
+        self.status == Some('A')
+    }
+
+    pub fn unix_time(&self) -> Option<f64> {
+        match (&self.date, &self.time) {
+            (Some(date), Some(time)) => Some(unix_time(date, time)),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Rmc {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        let magnetic_variation = match (parse_field::<f64>(msg, 9)?, field(msg, 10)) {
+            (Some(variation), Some("W")) => Some(-variation),
+            (variation, _) => variation,
+        };
+        Ok(Self {
+            time: field(msg, 0).map(str::parse).transpose()?,
+            status: parse_char(msg, 1)?,
+            latitude: parse_coord(msg, 2)?,
+            longitude: parse_coord(msg, 4)?,
+            speed_knots: parse_field(msg, 6)?,
+            course: parse_field(msg, 7)?,
+            date: field(msg, 8).map(UtcDate::from_ddmmyy).transpose()?,
+            magnetic_variation,
+            mode: parse_char(msg, 11)?,
+        })
+    }
+}
+
+impl Sentence for Rmc {
+    const MSGTYPE: &'static str = "RMC";
+
+    fn encode_params(&self) -> Vec<String> {
+        let [latitude, north_south] = fmt_coord(&self.latitude, true);
+        let [longitude, east_west] = fmt_coord(&self.longitude, false);
+        let (variation, variation_direction) = match self.magnetic_variation {
+            Some(variation) if variation < 0.0 => ((-variation).to_string(), "W".to_string()),
+            Some(variation) => (variation.to_string(), "E".to_string()),
+            None => Default::default(),
+        };
+        vec![
+            fmt_opt(&self.time),
+            fmt_opt(&self.status),
+            latitude,
+            north_south,
+            longitude,
+            east_west,
+            fmt_opt(&self.speed_knots),
+            fmt_opt(&self.course),
+            self.date.map(|date| date.to_ddmmyy()).unwrap_or_default(),
+            variation,
+            variation_direction,
+            fmt_opt(&self.mode),
+        ]
+    }
+}
@@ -0,0 +1,37 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// ROT - Rate Of Turn
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rot {
+    /// Degrees per minute, negative when the bow turns to port.
+    pub rate: Option<f64>,
+    pub status: Option<char>,
+}
+
+impl Rot {
+    pub fn is_valid(&self) -> bool {
+        self.status == Some('A')
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Rot {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            rate: parse_field(msg, 0)?,
+            status: parse_char(msg, 1)?,
+        })
+    }
+}
+
+impl Sentence for Rot {
+    const MSGTYPE: &'static str = "ROT";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![fmt_opt(&self.rate), fmt_opt(&self.status)]
+    }
+}
@@ -0,0 +1,51 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// RPM - Revolutions
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rpm {
+    /// `S` shaft, `E` engine
+    pub source: Option<char>,
+    pub number: Option<u8>,
+    /// Revolutions per minute.
+    pub speed: Option<f64>,
+    /// Propeller pitch in % of maximum, negative means astern.
+    pub pitch: Option<f64>,
+    pub status: Option<char>,
+}
+
+impl Rpm {
+    pub fn is_valid(&self) -> bool {
+        self.status == Some('A')
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Rpm {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            source: parse_char(msg, 0)?,
+            number: parse_field(msg, 1)?,
+            speed: parse_field(msg, 2)?,
+            pitch: parse_field(msg, 3)?,
+            status: parse_char(msg, 4)?,
+        })
+    }
+}
+
+impl Sentence for Rpm {
+    const MSGTYPE: &'static str = "RPM";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.source),
+            fmt_opt(&self.number),
+            fmt_opt(&self.speed),
+            fmt_opt(&self.pitch),
+            fmt_opt(&self.status),
+        ]
+    }
+}
@@ -0,0 +1,40 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// RSA - Rudder Sensor Angle, negative angles turn to port
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rsa {
+    /// Starboard or single rudder sensor.
+    pub starboard: Option<f64>,
+    pub starboard_status: Option<char>,
+    pub port: Option<f64>,
+    pub port_status: Option<char>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Rsa {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            starboard: parse_field(msg, 0)?,
+            starboard_status: parse_char(msg, 1)?,
+            port: parse_field(msg, 2)?,
+            port_status: parse_char(msg, 3)?,
+        })
+    }
+}
+
+impl Sentence for Rsa {
+    const MSGTYPE: &'static str = "RSA";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.starboard),
+            fmt_opt(&self.starboard_status),
+            fmt_opt(&self.port),
+            fmt_opt(&self.port_status),
+        ]
+    }
+}
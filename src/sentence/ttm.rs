@@ -0,0 +1,84 @@
+use crate::sentence::{check_msgtype, field, fmt_opt, parse_char, parse_field, Sentence, UtcTime};
+use crate::Nmea0183Msg;
+
+/// TTM - Tracked Target Message
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ttm {
+    pub target_number: Option<u8>,
+    pub distance: Option<f64>,
+    pub bearing: Option<f64>,
+    /// `T` true, `R` relative
+    pub bearing_units: Option<char>,
+    pub speed: Option<f64>,
+    pub course: Option<f64>,
+    pub course_units: Option<char>,
+    /// Distance of the closest point of approach.
+    pub cpa_distance: Option<f64>,
+    /// Minutes until the closest point of approach, negative means increasing.
+    pub cpa_time: Option<f64>,
+    /// Speed and distance units, `K`, `N` or `S`
+    pub units: Option<char>,
+    pub name: Option<String>,
+    /// `L` lost, `Q` query, `T` tracking
+    pub status: Option<char>,
+    pub reference: Option<char>,
+    /// Time of observation, not sent by older devices.
+    pub time: Option<UtcTime>,
+    /// `A` automatic, `M` manual, not sent by older devices.
+    pub acquisition: Option<char>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Ttm {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            target_number: parse_field(msg, 0)?,
+            distance: parse_field(msg, 1)?,
+            bearing: parse_field(msg, 2)?,
+            bearing_units: parse_char(msg, 3)?,
+            speed: parse_field(msg, 4)?,
+            course: parse_field(msg, 5)?,
+            course_units: parse_char(msg, 6)?,
+            cpa_distance: parse_field(msg, 7)?,
+            cpa_time: parse_field(msg, 8)?,
+            units: parse_char(msg, 9)?,
+            name: field(msg, 10).map(str::to_string),
+            status: parse_char(msg, 11)?,
+            reference: parse_char(msg, 12)?,
+            time: field(msg, 13).map(str::parse).transpose()?,
+            acquisition: parse_char(msg, 14)?,
+        })
+    }
+}
+
+impl Sentence for Ttm {
+    const MSGTYPE: &'static str = "TTM";
+
+    fn encode_params(&self) -> Vec<String> {
+        let mut params = vec![
+            self.target_number
+                .map(|number| format!("{:02}", number))
+                .unwrap_or_default(),
+            fmt_opt(&self.distance),
+            fmt_opt(&self.bearing),
+            fmt_opt(&self.bearing_units),
+            fmt_opt(&self.speed),
+            fmt_opt(&self.course),
+            fmt_opt(&self.course_units),
+            fmt_opt(&self.cpa_distance),
+            fmt_opt(&self.cpa_time),
+            fmt_opt(&self.units),
+            fmt_opt(&self.name),
+            fmt_opt(&self.status),
+            fmt_opt(&self.reference),
+        ];
+        if self.time.is_some() || self.acquisition.is_some() {
+            params.push(fmt_opt(&self.time));
+            params.push(fmt_opt(&self.acquisition));
+        }
+        params
+    }
+}
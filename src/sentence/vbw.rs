@@ -0,0 +1,45 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_char, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// VBW - Dual Ground/Water Speed in knots, negative speeds mean astern or port
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vbw {
+    pub water_longitudinal: Option<f64>,
+    pub water_transverse: Option<f64>,
+    pub water_status: Option<char>,
+    pub ground_longitudinal: Option<f64>,
+    pub ground_transverse: Option<f64>,
+    pub ground_status: Option<char>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Vbw {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            water_longitudinal: parse_field(msg, 0)?,
+            water_transverse: parse_field(msg, 1)?,
+            water_status: parse_char(msg, 2)?,
+            ground_longitudinal: parse_field(msg, 3)?,
+            ground_transverse: parse_field(msg, 4)?,
+            ground_status: parse_char(msg, 5)?,
+        })
+    }
+}
+
+impl Sentence for Vbw {
+    const MSGTYPE: &'static str = "VBW";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.water_longitudinal),
+            fmt_opt(&self.water_transverse),
+            fmt_opt(&self.water_status),
+            fmt_opt(&self.ground_longitudinal),
+            fmt_opt(&self.ground_transverse),
+            fmt_opt(&self.ground_status),
+        ]
+    }
+}
@@ -0,0 +1,43 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// VHW - Water speed and heading
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vhw {
+    pub heading_true: Option<f64>,
+    pub heading_magnetic: Option<f64>,
+    pub speed_knots: Option<f64>,
+    pub speed_kmh: Option<f64>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Vhw {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            heading_true: parse_field(msg, 0)?,
+            heading_magnetic: parse_field(msg, 2)?,
+            speed_knots: parse_field(msg, 4)?,
+            speed_kmh: parse_field(msg, 6)?,
+        })
+    }
+}
+
+impl Sentence for Vhw {
+    const MSGTYPE: &'static str = "VHW";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.heading_true),
+            "T".to_string(),
+            fmt_opt(&self.heading_magnetic),
+            "M".to_string(),
+            fmt_opt(&self.speed_knots),
+            "N".to_string(),
+            fmt_opt(&self.speed_kmh),
+            "K".to_string(),
+        ]
+    }
+}
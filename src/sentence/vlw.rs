@@ -0,0 +1,36 @@
+use crate::sentence::{check_msgtype, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+/// VLW - Distance Traveled through Water, in nautical miles
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vlw {
+    pub total_distance: Option<f64>,
+    /// Distance since reset.
+    pub trip_distance: Option<f64>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Vlw {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        Ok(Self {
+            total_distance: parse_field(msg, 0)?,
+            trip_distance: parse_field(msg, 2)?,
+        })
+    }
+}
+
+impl Sentence for Vlw {
+    const MSGTYPE: &'static str = "VLW";
+
+    fn encode_params(&self) -> Vec<String> {
+        vec![
+            fmt_opt(&self.total_distance),
+            "N".to_string(),
+            fmt_opt(&self.trip_distance),
+            "N".to_string(),
+        ]
+    }
+}
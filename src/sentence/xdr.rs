@@ -0,0 +1,180 @@
+use crate::sentence::{check_msgtype, field, fmt_opt, parse_field, Sentence};
+use crate::Nmea0183Msg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransducerType {
+    Angular,
+    Temperature,
+    Displacement,
+    Frequency,
+    Generic,
+    Humidity,
+    Current,
+    Force,
+    Pressure,
+    FlowRate,
+    Switch,
+    Tachometer,
+    Voltage,
+    Volume,
+    Other(char),
+}
+
+impl From<char> for TransducerType {
+    fn from(value: char) -> Self {
+        match value {
+            'A' => Self::Angular,
+            'C' => Self::Temperature,
+            'D' => Self::Displacement,
+            'F' => Self::Frequency,
+            'G' => Self::Generic,
+            'H' => Self::Humidity,
+            'I' => Self::Current,
+            'N' => Self::Force,
+            'P' => Self::Pressure,
+            'R' => Self::FlowRate,
+            'S' => Self::Switch,
+            'T' => Self::Tachometer,
+            'U' => Self::Voltage,
+            'V' => Self::Volume,
+            other => Self::Other(other),
+        }
+    }
+}
+
+impl From<TransducerType> for char {
+    fn from(value: TransducerType) -> Self {
+        match value {
+            TransducerType::Angular => 'A',
+            TransducerType::Temperature => 'C',
+            TransducerType::Displacement => 'D',
+            TransducerType::Frequency => 'F',
+            TransducerType::Generic => 'G',
+            TransducerType::Humidity => 'H',
+            TransducerType::Current => 'I',
+            TransducerType::Force => 'N',
+            TransducerType::Pressure => 'P',
+            TransducerType::FlowRate => 'R',
+            TransducerType::Switch => 'S',
+            TransducerType::Tachometer => 'T',
+            TransducerType::Voltage => 'U',
+            TransducerType::Volume => 'V',
+            TransducerType::Other(other) => other,
+        }
+    }
+}
+
+/// One quadruplet of an XDR sentence.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Measurement {
+    pub transducer: TransducerType,
+    pub value: Option<f64>,
+    pub unit: String,
+    pub name: String,
+}
+
+impl Measurement {
+    /// The value converted to SI units, see [`Measurement::si_unit`].
+    /// `None` if the value is missing or the unit is unknown.
+    pub fn si_value(&self) -> Option<f64> {
+        let value = self.value?;
+        match (self.transducer, self.unit.as_str()) {
+            (TransducerType::Angular, "D") => Some(value.to_radians()),
+            (TransducerType::Angular, "R") => Some(value),
+            (TransducerType::Temperature, "C") => Some(value + 273.15),
+            (TransducerType::Temperature, "F") => Some((value - 32.0) / 1.8 + 273.15),
+            (TransducerType::Temperature, "K") => Some(value),
+            (TransducerType::Pressure, "B") => Some(value * 100_000.0),
+            (TransducerType::Pressure, "P") => Some(value),
+            (TransducerType::Humidity, "P") => Some(value / 100.0),
+            (TransducerType::FlowRate, "L") => Some(value / 1000.0),
+            (TransducerType::Tachometer, "R") => Some(value / 60.0),
+            (TransducerType::Displacement, "M")
+            | (TransducerType::Frequency, "H")
+            | (TransducerType::Current, "A")
+            | (TransducerType::Force, "N")
+            | (TransducerType::Voltage, "V")
+            | (TransducerType::Volume, "M") => Some(value),
+            (TransducerType::Generic, _) | (TransducerType::Switch, _) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn si_unit(&self) -> &'static str {
+        match self.transducer {
+            TransducerType::Angular => "rad",
+            TransducerType::Temperature => "K",
+            TransducerType::Displacement => "m",
+            TransducerType::Frequency | TransducerType::Tachometer => "Hz",
+            TransducerType::Humidity => "ratio",
+            TransducerType::Current => "A",
+            TransducerType::Force => "N",
+            TransducerType::Pressure => "Pa",
+            TransducerType::FlowRate => "m3/s",
+            TransducerType::Voltage => "V",
+            TransducerType::Volume => "m3",
+            TransducerType::Generic | TransducerType::Switch | TransducerType::Other(_) => "",
+        }
+    }
+}
+
+/// XDR - Transducer Measurement
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Xdr {
+    pub measurements: Vec<Measurement>,
+}
+
+impl TryFrom<&Nmea0183Msg> for Xdr {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        let mut measurements = Vec::new();
+        for idx in (0..msg.params().len()).step_by(4) {
+            let transducer = match field(msg, idx) {
+                Some(transducer) => transducer,
+                // trailing empty fields
+                None => continue,
+            };
+            let mut chars = transducer.chars();
+            let transducer = match (chars.next(), chars.next()) {
+                (Some(transducer), None) => TransducerType::from(transducer),
+                _ => {
+                    return Err(format!(
+                        "Invalid transducer type '{}' in field {} of XDR",
+                        transducer,
+                        idx + 1
+                    ))
+                }
+            };
+            measurements.push(Measurement {
+                transducer,
+                value: parse_field(msg, idx + 1)?,
+                unit: field(msg, idx + 2).unwrap_or_default().to_string(),
+                name: field(msg, idx + 3).unwrap_or_default().to_string(),
+            });
+        }
+        Ok(Self { measurements })
+    }
+}
+
+impl Sentence for Xdr {
+    const MSGTYPE: &'static str = "XDR";
+
+    fn encode_params(&self) -> Vec<String> {
+        self.measurements
+            .iter()
+            .flat_map(|measurement| {
+                [
+                    char::from(measurement.transducer).to_string(),
+                    fmt_opt(&measurement.value),
+                    measurement.unit.clone(),
+                    measurement.name.clone(),
+                ]
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,78 @@
+use crate::sentence::{
+    check_msgtype, field, fmt_opt, parse_field, unix_time, Sentence, UtcDate, UtcTime,
+};
+use crate::Nmea0183Msg;
+
+/// ZDA - Time & Date - UTC, day, month, year and local time zone
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Zda {
+    pub time: Option<UtcTime>,
+    pub date: Option<UtcDate>,
+    pub zone_hours: Option<i8>,
+    pub zone_minutes: Option<i8>,
+}
+
+impl Zda {
+    pub fn unix_time(&self) -> Option<f64> {
+        match (&self.date, &self.time) {
+            (Some(date), Some(time)) => Some(unix_time(date, time)),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Nmea0183Msg> for Zda {
+    type Error = String;
+
+    fn try_from(msg: &Nmea0183Msg) -> Result<Self, Self::Error> {
+        check_msgtype(msg, Self::MSGTYPE)?;
+        let date = match (
+            parse_field::<u16>(msg, 3)?,
+            parse_field::<u8>(msg, 2)?,
+            parse_field::<u8>(msg, 1)?,
+        ) {
+            (Some(year), Some(month), Some(day)) => Some(UtcDate::new(year, month, day)?),
+            _ => None,
+        };
+        Ok(Self {
+            time: field(msg, 0).map(str::parse).transpose()?,
+            date,
+            zone_hours: parse_field(msg, 4)?,
+            zone_minutes: parse_field(msg, 5)?,
+        })
+    }
+}
+
+impl Sentence for Zda {
+    const MSGTYPE: &'static str = "ZDA";
+
+    fn encode_params(&self) -> Vec<String> {
+        let (day, month, year) = match &self.date {
+            Some(date) => (
+                format!("{:02}", date.day),
+                format!("{:02}", date.month),
+                format!("{:04}", date.year),
+            ),
+            None => Default::default(),
+        };
+        vec![
+            fmt_opt(&self.time),
+            day,
+            month,
+            year,
+            self.zone_hours
+                .map(|hours| {
+                    if hours < 0 {
+                        format!("-{:02}", hours.unsigned_abs())
+                    } else {
+                        format!("{:02}", hours)
+                    }
+                })
+                .unwrap_or_default(),
+            self.zone_minutes
+                .map(|minutes| format!("{:02}", minutes))
+                .unwrap_or_default(),
+        ]
+    }
+}
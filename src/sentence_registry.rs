@@ -0,0 +1,233 @@
+use crate::Nmea0183Msg;
+use futures::Stream;
+use std::any::Any;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Matches any talker or sentence id.
+pub const WILDCARD: &str = "*";
+
+type DecodeFn<T, E> = Box<dyn Fn(&Nmea0183Msg) -> Result<T, E> + Send + Sync>;
+
+/// Decoders keyed on talker and sentence id, e.g. (`GP`, `GGA`), (`*`, `ZDA`) or (`P`, `UBX`).
+pub struct SentenceRegistry<T, E = String> {
+    decoders: HashMap<(String, String), DecodeFn<T, E>>,
+}
+
+impl<T, E> Default for SentenceRegistry<T, E> {
+    fn default() -> Self {
+        Self {
+            decoders: HashMap::new(),
+        }
+    }
+}
+
+impl<T, E> SentenceRegistry<T, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a decoder, replacing any decoder for the same talker and sentence id.
+    /// Both may be [`WILDCARD`].
+    pub fn register<F>(&mut self, talker: &str, msgtype: &str, decoder: F)
+    where
+        F: Fn(&Nmea0183Msg) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.decoders
+            .insert((talker.to_string(), msgtype.to_string()), Box::new(decoder));
+    }
+
+    pub fn with<F>(mut self, talker: &str, msgtype: &str, decoder: F) -> Self
+    where
+        F: Fn(&Nmea0183Msg) -> Result<T, E> + Send + Sync + 'static,
+    {
+        self.register(talker, msgtype, decoder);
+        self
+    }
+
+    pub fn remove(&mut self, talker: &str, msgtype: &str) -> bool {
+        self.decoders
+            .remove(&(talker.to_string(), msgtype.to_string()))
+            .is_some()
+    }
+
+    /// Finds the most specific decoder: exact match first, then any talker, then any
+    /// sentence id of the talker, then the catch all.
+    fn lookup(&self, talker: &str, msgtype: &str) -> Option<&DecodeFn<T, E>> {
+        [
+            (talker, msgtype),
+            (WILDCARD, msgtype),
+            (talker, WILDCARD),
+            (WILDCARD, WILDCARD),
+        ]
+        .iter()
+        .find_map(|(talker, msgtype)| {
+            self.decoders
+                .get(&(talker.to_string(), msgtype.to_string()))
+        })
+    }
+
+    /// `None` if no decoder matches the sentence.
+    pub fn decode(&self, msg: &Nmea0183Msg) -> Option<Result<T, E>> {
+        self.lookup(msg.talker(), msg.msgtype())
+            .map(|decoder| decoder(msg))
+    }
+
+    /// Wraps a stream of decoded sentences, e.g. from [`crate::get_codec`], and yields the
+    /// output of the matching decoders. Sentences without decoder are skipped.
+    pub fn dispatch<S>(self, stream: S) -> Dispatch<S, T, E>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, std::io::Error>> + Unpin,
+    {
+        Dispatch {
+            stream,
+            registry: self,
+        }
+    }
+}
+
+impl<E> SentenceRegistry<Box<dyn Any + Send>, E> {
+    /// Registers a decoder with its own output type for a registry yielding `Box<dyn Any>`.
+    pub fn register_any<U, F>(&mut self, talker: &str, msgtype: &str, decoder: F)
+    where
+        U: Any + Send,
+        F: Fn(&Nmea0183Msg) -> Result<U, E> + Send + Sync + 'static,
+    {
+        self.register(talker, msgtype, move |msg| {
+            decoder(msg).map(|value| Box::new(value) as Box<dyn Any + Send>)
+        });
+    }
+}
+
+#[derive(Debug)]
+pub enum DispatchError<E> {
+    /// The underlying stream failed to decode a sentence.
+    Io(std::io::Error),
+    /// The registered decoder rejected the sentence.
+    Decode(Nmea0183Msg, E),
+}
+
+pub struct Dispatch<S, T, E> {
+    stream: S,
+    registry: SentenceRegistry<T, E>,
+}
+
+impl<S, T, E> Dispatch<S, T, E> {
+    pub fn registry_mut(&mut self) -> &mut SentenceRegistry<T, E> {
+        &mut self.registry
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S, T, E> Stream for Dispatch<S, T, E>
+where
+    S: Stream<Item = Result<Nmea0183Msg, std::io::Error>> + Unpin,
+{
+    type Item = Result<T, DispatchError<E>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // all fields are Unpin
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.stream).poll_next(cx) {
+                Poll::Ready(Some(Ok(msg))) => match this.registry.decode(&msg) {
+                    Some(Ok(value)) => return Poll::Ready(Some(Ok(value))),
+                    Some(Err(error)) => {
+                        return Poll::Ready(Some(Err(DispatchError::Decode(msg, error))))
+                    }
+                    None => continue,
+                },
+                Poll::Ready(Some(Err(error))) => {
+                    return Poll::Ready(Some(Err(DispatchError::Io(error))))
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_codec;
+    use crate::sentence::{Rmc, Zda};
+    use futures::stream::StreamExt;
+    use tokio::fs::File;
+
+    const TEST_FILE: &str = "./test_data/nmea0183_1000.log";
+
+    #[derive(Debug)]
+    enum Sentence {
+        Rmc(Rmc),
+        Zda(Zda),
+        Position(String),
+        Other(String),
+    }
+
+    fn registry() -> SentenceRegistry<Sentence> {
+        SentenceRegistry::new()
+            .with(WILDCARD, "RMC", |msg| Rmc::try_from(msg).map(Sentence::Rmc))
+            .with(WILDCARD, "ZDA", |msg| Zda::try_from(msg).map(Sentence::Zda))
+            .with("GP", "GLL", |msg| {
+                Ok(Sentence::Position(msg.params()[0].clone()))
+            })
+            .with("GP", WILDCARD, |msg| {
+                Ok(Sentence::Other(msg.msgtype().to_string()))
+            })
+    }
+
+    #[test]
+    fn test_lookup() {
+        let registry = registry();
+        let msg: Nmea0183Msg = "$GNZDA,160012.71,11,03,2004,-1,00*63".parse().unwrap();
+        assert!(matches!(registry.decode(&msg), Some(Ok(Sentence::Zda(_)))));
+        let msg: Nmea0183Msg = "$GPGLL,0856.1964,N,07933.3281,W,184906.000,A,A*4E"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            registry.decode(&msg),
+            Some(Ok(Sentence::Position(_)))
+        ));
+        let msg: Nmea0183Msg = "$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09".parse().unwrap();
+        assert!(matches!(
+            registry.decode(&msg),
+            Some(Ok(Sentence::Other(_)))
+        ));
+        let msg: Nmea0183Msg = "$GNVTG,222.30,T,,M,0.30,N,0.6,K,A*17".parse().unwrap();
+        assert!(registry.decode(&msg).is_none());
+    }
+
+    #[test]
+    fn test_any() {
+        let mut registry = SentenceRegistry::<Box<dyn Any + Send>>::new();
+        registry.register_any(WILDCARD, "ZDA", |msg| Zda::try_from(msg));
+        let msg: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*7D".parse().unwrap();
+        let value = registry.decode(&msg).unwrap().unwrap();
+        assert!(value.downcast_ref::<Zda>().is_some());
+    }
+
+    #[test]
+    fn test_dispatch() {
+        tokio_test::block_on(async {
+            let file = File::open(TEST_FILE)
+                .await
+                .unwrap_or_else(|_| panic!("failed to open file {}", TEST_FILE));
+            let registry = SentenceRegistry::new()
+                .with(WILDCARD, "RMC", |msg| Rmc::try_from(msg).map(Sentence::Rmc));
+            let mut stream = registry.dispatch(get_codec(file));
+            let mut count = 0;
+            while let Some(result) = stream.next().await {
+                match result {
+                    Ok(Sentence::Rmc(_)) => count += 1,
+                    other => panic!("unexpected result {:?}", other),
+                }
+            }
+            assert_eq!(count, 98);
+        })
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{Nmea0183Codec, ResumeOnError};
+use futures::StreamExt;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::time::Instant;
+use tokio_serial::{
+    ClearBuffer, DataBits, Parity, SerialPort, SerialPortBuilderExt, SerialStream, StopBits,
+};
+use tokio_util::codec::FramedRead;
+
+/// Baud rates tried by [`Autodetect`], most common first.
+pub const BAUD_RATES: [u32; 6] = [4800, 9600, 38400, 19200, 57600, 115200];
+
+/// Character formats tried by [`Autodetect`] at each baud rate.
+pub const FORMATS: [&str; 3] = ["8N1", "7N2", "8O1"];
+
+/// Serial line settings, written as `<baud>[,<data bits><parity><stop bits>]`, e.g.
+/// `4800`, `38400,8N1` or `9600,7E2`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl SerialConfig {
+    /// 8N1 at `baud_rate`, 4800 is the NMEA 0183 default, 38400 is used for AIS.
+    pub fn new(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        }
+    }
+
+    /// Sets the character format, e.g. `7N2`.
+    pub fn with_format(mut self, format: &str) -> Result<Self, String> {
+        let mut chars = format.chars();
+        self.data_bits = match chars.next() {
+            Some('5') => DataBits::Five,
+            Some('6') => DataBits::Six,
+            Some('7') => DataBits::Seven,
+            Some('8') => DataBits::Eight,
+            _ => return Err(format!("Invalid data bits in format '{}'", format)),
+        };
+        self.parity = match chars.next().map(|c| c.to_ascii_uppercase()) {
+            Some('N') => Parity::None,
+            Some('O') => Parity::Odd,
+            Some('E') => Parity::Even,
+            _ => return Err(format!("Invalid parity in format '{}'", format)),
+        };
+        self.stop_bits = match chars.next() {
+            Some('1') => StopBits::One,
+            Some('2') => StopBits::Two,
+            _ => return Err(format!("Invalid stop bits in format '{}'", format)),
+        };
+        if chars.next().is_some() {
+            return Err(format!("Invalid format '{}'", format));
+        }
+        Ok(self)
+    }
+
+    pub fn open(&self, path: &str) -> tokio_serial::Result<SerialStream> {
+        tokio_serial::new(path, self.baud_rate)
+            .data_bits(self.data_bits)
+            .parity(self.parity)
+            .stop_bits(self.stop_bits)
+            .open_native_async()
+    }
+
+    fn data_bit_count(&self) -> u32 {
+        match self.data_bits {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        }
+    }
+
+    /// Characters per second including start, parity and stop bits, 480 at 4800 8N1.
+    pub fn chars_per_second(&self) -> f64 {
+        let parity = u32::from(self.parity != Parity::None);
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        f64::from(self.baud_rate) / f64::from(1 + self.data_bit_count() + parity + stop_bits)
+    }
+
+    /// Reconfigures an open port.
+    pub fn apply(&self, port: &mut SerialStream) -> tokio_serial::Result<()> {
+        port.set_baud_rate(self.baud_rate)?;
+        port.set_data_bits(self.data_bits)?;
+        port.set_parity(self.parity)?;
+        port.set_stop_bits(self.stop_bits)
+    }
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self::new(4800)
+    }
+}
+
+impl fmt::Display for SerialConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data_bits = self.data_bit_count();
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        let stop_bits = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        write!(f, "{},{}{}{}", self.baud_rate, data_bits, parity, stop_bits)
+    }
+}
+
+impl FromStr for SerialConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (baud_rate, format) = match s.split_once(',') {
+            Some((baud_rate, format)) => (baud_rate, Some(format)),
+            None => (s, None),
+        };
+        let baud_rate = baud_rate
+            .trim()
+            .parse::<u32>()
+            .map_err(|_| format!("Invalid baud rate '{}'", baud_rate))?;
+        let config = Self::new(baud_rate);
+        match format {
+            Some(format) => config.with_format(format.trim()),
+            None => Ok(config),
+        }
+    }
+}
+
+/// Finds the line settings of a device by trying candidate settings in turn and scoring
+/// each by the ratio of checksum-valid sentences among everything decoded.
+pub struct Autodetect {
+    candidates: Vec<SerialConfig>,
+    sample_time: Duration,
+    sample_size: usize,
+    min_score: f64,
+}
+
+impl Default for Autodetect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Autodetect {
+    /// All [`BAUD_RATES`] with all [`FORMATS`], each sampled for up to 2 seconds.
+    pub fn new() -> Self {
+        let candidates = BAUD_RATES
+            .iter()
+            .flat_map(|baud_rate| {
+                FORMATS.iter().map(|format| {
+                    SerialConfig::new(*baud_rate)
+                        .with_format(format)
+                        .expect("valid format")
+                })
+            })
+            .collect();
+        Self {
+            candidates,
+            sample_time: Duration::from_secs(2),
+            sample_size: 10,
+            min_score: 0.8,
+        }
+    }
+
+    pub fn with_candidates(mut self, candidates: Vec<SerialConfig>) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Maximum time spent on each candidate.
+    pub fn with_sample_time(mut self, sample_time: Duration) -> Self {
+        self.sample_time = sample_time;
+        self
+    }
+
+    /// A candidate reaching the minimum score with this many decoded sentences or
+    /// errors is accepted without trying the others.
+    pub fn with_sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Leaves `port` configured with the best candidate and returns it with its score.
+    pub async fn detect(&self, port: &mut SerialStream) -> io::Result<(SerialConfig, f64)> {
+        let mut best: Option<(SerialConfig, f64)> = None;
+        for candidate in &self.candidates {
+            candidate.apply(port)?;
+            port.clear(ClearBuffer::Input)?;
+            let (score, count) = self.score(port).await;
+            if score >= self.min_score && count >= self.sample_size {
+                return Ok((*candidate, score));
+            }
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((*candidate, score));
+            }
+        }
+        match best {
+            Some((config, score)) if score >= self.min_score => {
+                config.apply(port)?;
+                Ok((config, score))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "No candidate settings received valid sentences",
+            )),
+        }
+    }
+
+    /// Returns the ratio of valid sentences and the number of decoded sentences and errors.
+    async fn score(&self, port: &mut SerialStream) -> (f64, usize) {
+        let deadline = Instant::now() + self.sample_time;
+        let mut stream = ResumeOnError::new(FramedRead::new(port, Nmea0183Codec::default()));
+        let mut valid = 0;
+        let mut count = 0;
+        while count < self.sample_size {
+            match tokio::time::timeout_at(deadline, stream.next()).await {
+                Ok(Some(Ok(msg))) => {
+                    if msg.chksum_valid() == Some(true) {
+                        valid += 1;
+                    }
+                    count += 1;
+                }
+                Ok(Some(Err(_))) => count += 1,
+                Ok(None) | Err(_) => break,
+            }
+        }
+        if count == 0 {
+            (0.0, 0)
+        } else {
+            (valid as f64 / count as f64, count)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    #[test]
+    fn test_serial_config() {
+        let config: SerialConfig = "9600,7N2".parse().unwrap();
+        assert_eq!(config.baud_rate, 9600);
+        assert_eq!(config.data_bits, DataBits::Seven);
+        assert_eq!(config.parity, Parity::None);
+        assert_eq!(config.stop_bits, StopBits::Two);
+        assert_eq!(config.to_string(), "9600,7N2");
+        assert_eq!(config.chars_per_second(), 960.0);
+        assert_eq!(SerialConfig::new(4800).chars_per_second(), 480.0);
+        assert_eq!(
+            "38400".parse::<SerialConfig>().unwrap().to_string(),
+            "38400,8N1"
+        );
+        assert!("9600,8X1".parse::<SerialConfig>().is_err());
+        assert!("fast".parse::<SerialConfig>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_autodetect() {
+        let (mut device, mut port) = SerialStream::pair().unwrap();
+        // a pseudo terminal shares its settings between both ends, the device only talks
+        // sense at 9600 baud
+        let device_task = tokio::spawn(async move {
+            loop {
+                let line: &[u8] = if device.baud_rate().unwrap() == 9600 {
+                    b"$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n"
+                } else {
+                    b"$G\xe3\x1c\x9a,\xfe1*0\r\n"
+                };
+                if device.write_all(line).await.is_err() {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+
+        let autodetect = Autodetect::new()
+            .with_candidates(vec![
+                SerialConfig::new(4800),
+                SerialConfig::new(9600),
+                SerialConfig::new(38400),
+            ])
+            .with_sample_time(Duration::from_millis(500));
+        let (config, score) = autodetect.detect(&mut port).await.unwrap();
+        assert_eq!(config, SerialConfig::new(9600));
+        assert!(score >= 0.8);
+        assert_eq!(port.baud_rate().unwrap(), 9600);
+        device_task.abort();
+    }
+}
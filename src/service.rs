@@ -0,0 +1,331 @@
+use crate::config::{Config, Route, ServerKind};
+use crate::endpoint::{with_host, MsgStream};
+use crate::gpsd::GpsdServer;
+use crate::health::Health;
+use crate::mux::{MuxError, MuxMsg, NmeaMux};
+use crate::rate_limit::RateLimit;
+use crate::recorder::Recorder;
+use crate::signalk::SignalKServer;
+use crate::tcp::TcpServer;
+use crate::websocket::WebSocketServer;
+use crate::Nmea0183Msg;
+use futures::{Sink, SinkExt, StreamExt};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::{JoinHandle, JoinSet};
+
+const QUEUE_SIZE: usize = 1024;
+// sinks may be blocked by a peer that does not read
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs the sources, sinks, recorder and servers of a [`Config`]. The sentences of all
+/// sources are merged, each sink, the recorder and each server receives those of its
+/// route. Consumers that cannot keep up skip sentences.
+pub struct Service {
+    mux: JoinHandle<()>,
+    tasks: JoinSet<io::Result<()>>,
+}
+
+impl Service {
+    /// Opens all sources, sinks and servers, errors name the failing one.
+    pub async fn start(config: &Config) -> io::Result<Self> {
+        Self::start_with_health(config, Arc::new(Mutex::new(Health::new()))).await
+    }
+
+    /// Like [`Service::start`], counting the sentences and errors of each source in
+    /// `health`. Sources that are no longer configured are removed from it, the counters
+    /// of the others continue, e.g. when a reloaded configuration replaces a service.
+    pub async fn start_with_health(
+        config: &Config,
+        health: Arc<Mutex<Health>>,
+    ) -> io::Result<Self> {
+        let (tx, _) = broadcast::channel(QUEUE_SIZE);
+        let mut tasks = JoinSet::new();
+        // all consumers subscribe before the first sentence is sent
+        for (name, sink_config) in &config.sinks {
+            let stream = subscribe(&tx, config, &sink_config.route);
+            let sink = sink_config
+                .endpoint
+                .open_sink()
+                .await
+                .map_err(|error| context("sinks", name, error))?;
+            match &sink_config.limit {
+                Some(limit) => {
+                    let priorities = sink_config
+                        .priority
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<Vec<_>>();
+                    let sink = RateLimit::for_serial(sink, limit).with_priorities(&priorities);
+                    tasks.spawn(forward(stream, sink));
+                }
+                None => {
+                    tasks.spawn(forward(stream, sink));
+                }
+            }
+        }
+        if let Some(record_config) = &config.recorder {
+            let stream = subscribe(&tx, config, &record_config.route);
+            let mut recorder = Recorder::new(record_config.recorder.clone());
+            tasks.spawn(async move {
+                recorder.record(stream).await?;
+                recorder.close().await.map(|_| ())
+            });
+        }
+        for (name, server_config) in &config.servers {
+            let stream = subscribe(&tx, config, &server_config.route);
+            let address = with_host(&server_config.address, "0.0.0.0");
+            let bind_error = |error| context("servers", name, error);
+            match &server_config.kind {
+                ServerKind::Tcp => {
+                    let server = TcpServer::bind(address).await.map_err(bind_error)?;
+                    tasks.spawn(server.serve(stream));
+                }
+                ServerKind::WebSocket => {
+                    let server = WebSocketServer::bind(address).await.map_err(bind_error)?;
+                    // no sentences are accepted from clients
+                    let sink = futures::sink::drain().sink_map_err(|never| match never {});
+                    tasks.spawn(server.serve(stream, sink));
+                }
+                ServerKind::SignalK => {
+                    let server = SignalKServer::bind_tcp(address).await.map_err(bind_error)?;
+                    tasks.spawn(server.serve(stream));
+                }
+                ServerKind::SignalKWebSocket => {
+                    let server = SignalKServer::bind_websocket(address)
+                        .await
+                        .map_err(bind_error)?;
+                    tasks.spawn(server.serve(stream));
+                }
+                ServerKind::Gpsd(device) => {
+                    let server = GpsdServer::bind(address, device)
+                        .await
+                        .map_err(bind_error)?;
+                    tasks.spawn(server.serve(stream));
+                }
+            }
+        }
+
+        let mut mux = NmeaMux::new();
+        for (name, source_config) in &config.sources {
+            let endpoint = &source_config.endpoint;
+            let stream = if source_config.lenient {
+                endpoint.open_lenient_stream().await
+            } else {
+                endpoint.open_stream().await
+            };
+            mux.add_source(
+                name,
+                stream.map_err(|error| context("sources", name, error))?,
+            );
+        }
+        health
+            .lock()
+            .unwrap()
+            .set_sources(config.sources.keys().map(String::as_str));
+        let mux = tokio::spawn(async move {
+            // decode errors are skipped, no consumers is not an error
+            while let Some(result) = mux.next().await {
+                match result {
+                    Ok(mux_msg) => {
+                        health.lock().unwrap().message(&mux_msg.source);
+                        let _ = tx.send(mux_msg);
+                    }
+                    Err(MuxError { source, error }) => {
+                        health.lock().unwrap().error(&source, &error);
+                        if error.kind() == io::ErrorKind::Other {
+                            log::debug!(source = source.as_str(); "{}", error);
+                        } else {
+                            log::warn!(source = source.as_str(); "{}", error);
+                        }
+                    }
+                }
+            }
+        });
+        Ok(Self { mux, tasks })
+    }
+
+    /// Waits until all sources have ended and the consumers are done, or the first
+    /// consumer fails.
+    pub async fn run(&mut self) -> io::Result<()> {
+        while let Some(result) = self.tasks.join_next().await {
+            result.map_err(io::Error::other)??;
+        }
+        Ok(())
+    }
+
+    /// Closes the sources and lets the consumers finish, e.g. the recorder its file.
+    /// Sockets and listeners are closed when this returns, so a new service can bind the
+    /// same addresses.
+    pub async fn stop(mut self) -> io::Result<()> {
+        self.mux.abort();
+        // the sources are dropped with the task
+        let _ = (&mut self.mux).await;
+        let result = tokio::time::timeout(STOP_TIMEOUT, self.run()).await;
+        self.tasks.shutdown().await;
+        result.unwrap_or(Ok(()))
+    }
+}
+
+fn context(section: &str, name: &str, error: io::Error) -> io::Error {
+    io::Error::new(error.kind(), format!("{}.{}: {}", section, name, error))
+}
+
+/// The sentences of the sources of `route` passed through its filters.
+fn subscribe(tx: &broadcast::Sender<MuxMsg>, config: &Config, route: &Route) -> MsgStream {
+    let state = (
+        tx.subscribe(),
+        route.sources.clone(),
+        config.pipeline(route),
+    );
+    Box::pin(futures::stream::unfold(
+        state,
+        |(mut rx, sources, mut pipeline)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(mux_msg) => {
+                        if !sources.is_empty() && !sources.contains(&mux_msg.source) {
+                            continue;
+                        }
+                        if let Some(msg) = pipeline.apply(mux_msg.msg) {
+                            return Some((Ok(msg), (rx, sources, pipeline)));
+                        }
+                    }
+                    Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    ))
+}
+
+async fn forward<K>(mut stream: MsgStream, mut sink: K) -> io::Result<()>
+where
+    K: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+{
+    sink.send_all(&mut stream).await?;
+    sink.close().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SourceConfig;
+    use crate::endpoint::Endpoint;
+
+    #[test]
+    fn test_service() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let input = dir.path().join("in.log");
+            tokio::fs::write(
+                &input,
+                concat!(
+                    "$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n",
+                    "$GPGSV,3,1,11,07,79,048,42,02,51,062,43,26,36,256,42,27,27,138,42*71\r\n",
+                    "$GPZDA,160013.71,11,03,2004,-1,00*00\n",
+                ),
+            )
+            .await
+            .unwrap();
+            let config = format!(
+                r#"
+[sources.gps]
+type = "file"
+path = "{}"
+lenient = true
+
+[filters.nav]
+stages = ["drop sentence=GSV"]
+
+[sinks.all]
+type = "file"
+path = "{}"
+
+[sinks.nav]
+type = "file"
+path = "{}"
+filters = ["nav"]
+"#,
+                input.display(),
+                dir.path().join("all.log").display(),
+                dir.path().join("nav.log").display(),
+            );
+            let config: Config = config.parse().unwrap();
+            let health = Arc::new(Mutex::new(Health::new()));
+            let mut service = Service::start_with_health(&config, Arc::clone(&health))
+                .await
+                .unwrap();
+            service.run().await.unwrap();
+            assert_eq!(health.lock().unwrap().source("gps").unwrap().messages, 3);
+
+            let read = |name: &str| {
+                let path = dir.path().join(name);
+                async move {
+                    tokio::fs::read_to_string(path)
+                        .await
+                        .unwrap()
+                        .lines()
+                        .map(|line| line.parse::<Nmea0183Msg>().unwrap())
+                        .collect::<Vec<_>>()
+                }
+            };
+            let all = read("all.log").await;
+            assert_eq!(all.len(), 3);
+            let nav = read("nav.log").await;
+            assert_eq!(nav.len(), 2);
+            assert!(nav.iter().all(|msg| msg.msgtype() == "ZDA"));
+
+            let missing = Config {
+                sources: [(
+                    "gps".to_string(),
+                    SourceConfig {
+                        endpoint: Endpoint::File(dir.path().join("missing")),
+                        lenient: false,
+                    },
+                )]
+                .into(),
+                ..Config::default()
+            };
+            let error = Service::start(&missing).await.err().unwrap();
+            assert!(error.to_string().starts_with("sources.gps: "), "{}", error);
+        })
+    }
+
+    #[test]
+    fn test_restart() {
+        tokio_test::block_on(async {
+            let source = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let sink = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+            let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let config = format!(
+                r#"
+[sources.ais]
+type = "udp"
+address = "{}"
+
+[sinks.plotter]
+type = "tcp"
+address = ":{}"
+
+[servers.opencpn]
+type = "tcp"
+address = "{}"
+"#,
+                source.local_addr().unwrap(),
+                sink.local_addr().unwrap().port(),
+                server.local_addr().unwrap(),
+            );
+            drop((source, sink, server));
+            let config: Config = config.parse().unwrap();
+            // e.g. a reload with an unchanged configuration
+            for _ in 0..3 {
+                let service = Service::start(&config).await.unwrap();
+                service.stop().await.unwrap();
+            }
+        })
+    }
+}
@@ -0,0 +1,609 @@
+use crate::ais::{AisDecoder, AisMessage, StaticDataReport};
+use crate::json::{array, quote, JsonObject};
+use crate::sentence::{iso8601, Dbt, Dpt, Gga, Rmc, Rot, Rsa, Vhw, Vlw, Zda};
+use crate::tcp::accept_while;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{SinkExt, Stream, StreamExt};
+use std::io;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::Message;
+
+const SELF_CONTEXT: &str = "vessels.self";
+const SERVER_QUEUE_SIZE: usize = 1024;
+const KNOTS_TO_MPS: f64 = 1852.0 / 3600.0;
+const NAUTICAL_MILE: f64 = 1852.0;
+
+/// Navigation status of AIS position reports as Signal K `navigation.state`.
+const NAVIGATION_STATES: [&str; 9] = [
+    "motoring",
+    "anchored",
+    "not under command",
+    "restricted manouverability",
+    "constrained by draft",
+    "moored",
+    "aground",
+    "fishing",
+    "sailing",
+];
+
+fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+fn position(latitude: Option<f64>, longitude: Option<f64>) -> Option<String> {
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Some(
+            JsonObject::new()
+                .num("latitude", latitude)
+                .num("longitude", longitude)
+                .finish(),
+        ),
+        _ => None,
+    }
+}
+
+/// Collects the path value pairs of one update.
+#[derive(Default)]
+struct Values(Vec<(&'static str, String)>);
+
+impl Values {
+    fn json(&mut self, path: &'static str, json: Option<String>) {
+        if let Some(json) = json {
+            self.0.push((path, json));
+        }
+    }
+
+    fn num(&mut self, path: &'static str, value: Option<f64>) {
+        self.json(
+            path,
+            value
+                .filter(|value| value.is_finite())
+                .map(|value| value.to_string()),
+        );
+    }
+
+    fn str(&mut self, path: &'static str, value: &str) {
+        if !value.is_empty() {
+            self.0.push((path, quote(value)));
+        }
+    }
+
+    fn radians(&mut self, path: &'static str, degrees: Option<f64>) {
+        self.num(path, degrees.map(f64::to_radians));
+    }
+}
+
+/// Converts decoded sentences to Signal K delta messages. Values are converted to SI
+/// units, the source is labelled with the talker id, e.g. `nmea0183.GP`.
+pub struct SignalKConverter {
+    label: String,
+    self_context: String,
+    ais: AisDecoder,
+}
+
+impl Default for SignalKConverter {
+    fn default() -> Self {
+        Self::new("nmea0183")
+    }
+}
+
+impl SignalKConverter {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            self_context: SELF_CONTEXT.to_string(),
+            ais: AisDecoder::new(),
+        }
+    }
+
+    /// Context of the own vessel, e.g. `vessels.urn:mrn:imo:mmsi:211234560`.
+    pub fn with_self_context(mut self, context: &str) -> Self {
+        self.self_context = context.to_string();
+        self
+    }
+
+    pub fn self_context(&self) -> &str {
+        &self.self_context
+    }
+
+    /// `None` if the sentence carries nothing that maps to Signal K or its checksum is
+    /// invalid.
+    pub fn convert(&mut self, msg: &Nmea0183Msg) -> Option<String> {
+        self.convert_at(msg, now())
+    }
+
+    /// `received` in seconds since the unix epoch is used as timestamp unless the
+    /// sentence has a tag block timestamp.
+    pub fn convert_at(&mut self, msg: &Nmea0183Msg, received: f64) -> Option<String> {
+        if msg.chksum_valid() == Some(false) {
+            return None;
+        }
+        let mut values = Values::default();
+        let mut context = self.self_context.clone();
+        match msg.msgtype() {
+            "RMC" => {
+                let rmc = Rmc::try_from(msg).ok()?;
+                if rmc.is_valid() {
+                    values.json("navigation.position", position(rmc.latitude, rmc.longitude));
+                    values.num(
+                        "navigation.speedOverGround",
+                        rmc.speed_knots.map(|speed| speed * KNOTS_TO_MPS),
+                    );
+                    values.radians("navigation.courseOverGroundTrue", rmc.course);
+                    values.radians("navigation.magneticVariation", rmc.magnetic_variation);
+                }
+                if let Some(time) = rmc.unix_time() {
+                    values.str("navigation.datetime", &iso8601(time));
+                }
+            }
+            "GGA" => {
+                let gga = Gga::try_from(msg).ok()?;
+                if gga.is_valid() {
+                    values.json("navigation.position", position(gga.latitude, gga.longitude));
+                    values.num("navigation.gnss.antennaAltitude", gga.altitude);
+                }
+                values.num("navigation.gnss.satellites", gga.satellites.map(f64::from));
+                values.num("navigation.gnss.horizontalDilution", gga.hdop);
+            }
+            "ZDA" => {
+                let time = Zda::try_from(msg).ok()?.unix_time()?;
+                values.str("navigation.datetime", &iso8601(time));
+            }
+            "DBT" => {
+                let dbt = Dbt::try_from(msg).ok()?;
+                values.num("environment.depth.belowTransducer", dbt.depth());
+            }
+            "DPT" => {
+                let dpt = Dpt::try_from(msg).ok()?;
+                values.num("environment.depth.belowTransducer", dpt.depth);
+                values.num("environment.depth.belowSurface", dpt.below_surface());
+                values.num("environment.depth.belowKeel", dpt.below_keel());
+            }
+            "ROT" => {
+                let rot = Rot::try_from(msg).ok()?;
+                if rot.is_valid() {
+                    // degrees per minute
+                    values.radians("navigation.rateOfTurn", rot.rate.map(|rate| rate / 60.0));
+                }
+            }
+            "RSA" => {
+                let rsa = Rsa::try_from(msg).ok()?;
+                if rsa.starboard_status == Some('A') {
+                    values.radians("steering.rudderAngle", rsa.starboard);
+                }
+            }
+            "VHW" => {
+                let vhw = Vhw::try_from(msg).ok()?;
+                values.radians("navigation.headingTrue", vhw.heading_true);
+                values.radians("navigation.headingMagnetic", vhw.heading_magnetic);
+                values.num(
+                    "navigation.speedThroughWater",
+                    vhw.speed_knots.map(|speed| speed * KNOTS_TO_MPS),
+                );
+            }
+            "VLW" => {
+                let vlw = Vlw::try_from(msg).ok()?;
+                values.num(
+                    "navigation.log",
+                    vlw.total_distance.map(|distance| distance * NAUTICAL_MILE),
+                );
+                values.num(
+                    "navigation.trip.log",
+                    vlw.trip_distance.map(|distance| distance * NAUTICAL_MILE),
+                );
+            }
+            "VDM" | "VDO" => {
+                let ais = self.ais.decode(msg).ok()??;
+                if msg.msgtype() == "VDM" {
+                    context = format!("vessels.urn:mrn:imo:mmsi:{}", ais.mmsi());
+                }
+                ais_values(&ais, &mut values);
+            }
+            _ => return None,
+        }
+        if values.0.is_empty() {
+            return None;
+        }
+
+        let timestamp = msg
+            .tag_block()
+            .and_then(|tag_block| tag_block.timestamp())
+            .unwrap_or(received);
+        let source = JsonObject::new()
+            .str("label", &self.label)
+            .str("type", "NMEA0183")
+            .str("talker", msg.talker())
+            .str("sentence", msg.msgtype())
+            .finish();
+        let values = values.0.into_iter().map(|(path, value)| {
+            JsonObject::new()
+                .str("path", path)
+                .raw("value", &value)
+                .finish()
+        });
+        let update = JsonObject::new()
+            .raw("source", &source)
+            .str("$source", &format!("{}.{}", self.label, msg.talker()))
+            .str("timestamp", &iso8601(timestamp))
+            .raw("values", &array(values))
+            .finish();
+        Some(
+            JsonObject::new()
+                .str("context", &context)
+                .raw("updates", &array([update]))
+                .finish(),
+        )
+    }
+
+    /// The hello message sent to stream clients on connect.
+    pub fn hello(&self) -> String {
+        JsonObject::new()
+            .str("name", env!("CARGO_PKG_NAME"))
+            .str("version", env!("CARGO_PKG_VERSION"))
+            .str("self", &self.self_context)
+            .raw("roles", r#"["master","main"]"#)
+            .str("timestamp", &iso8601(now()))
+            .finish()
+    }
+}
+
+fn ais_values(ais: &AisMessage, values: &mut Values) {
+    let mmsi = ais.mmsi().to_string();
+    match ais {
+        AisMessage::PositionReport(report) => {
+            values.json(
+                "navigation.position",
+                position(report.latitude, report.longitude),
+            );
+            values.num(
+                "navigation.speedOverGround",
+                report.speed.map(|speed| speed * KNOTS_TO_MPS),
+            );
+            values.radians("navigation.courseOverGroundTrue", report.course);
+            values.radians("navigation.headingTrue", report.heading.map(f64::from));
+            values.radians(
+                "navigation.rateOfTurn",
+                report.rate_of_turn().map(|rate| rate / 60.0),
+            );
+            if let Some(state) = NAVIGATION_STATES.get(usize::from(report.status)) {
+                values.str("navigation.state", state);
+            }
+        }
+        AisMessage::ClassBPosition(report) => {
+            values.json(
+                "navigation.position",
+                position(report.latitude, report.longitude),
+            );
+            values.num(
+                "navigation.speedOverGround",
+                report.speed.map(|speed| speed * KNOTS_TO_MPS),
+            );
+            values.radians("navigation.courseOverGroundTrue", report.course);
+            values.radians("navigation.headingTrue", report.heading.map(f64::from));
+        }
+        AisMessage::StaticAndVoyage(voyage) => {
+            values.json(
+                "",
+                Some(
+                    JsonObject::new()
+                        .str("mmsi", &mmsi)
+                        .opt_str(
+                            "name",
+                            Some(voyage.shipname.as_str()).filter(|name| !name.is_empty()),
+                        )
+                        .finish(),
+                ),
+            );
+            values.str("communication.callsignVhf", &voyage.callsign);
+            if voyage.imo != 0 {
+                values.str("registrations.imo", &format!("IMO {}", voyage.imo));
+            }
+            dimensions(
+                values,
+                voyage.shiptype,
+                voyage.to_bow,
+                voyage.to_stern,
+                voyage.to_port,
+                voyage.to_starboard,
+            );
+            if voyage.draught > 0.0 {
+                values.json(
+                    "design.draft",
+                    Some(JsonObject::new().num("current", voyage.draught).finish()),
+                );
+            }
+            values.str("navigation.destination.commonName", &voyage.destination);
+        }
+        AisMessage::StaticDataReport(StaticDataReport::PartA { shipname, .. }) => {
+            values.json(
+                "",
+                Some(
+                    JsonObject::new()
+                        .str("mmsi", &mmsi)
+                        .str("name", shipname)
+                        .finish(),
+                ),
+            );
+        }
+        AisMessage::StaticDataReport(StaticDataReport::PartB {
+            shiptype,
+            callsign,
+            to_bow,
+            to_stern,
+            to_port,
+            to_starboard,
+            ..
+        }) => {
+            values.str("communication.callsignVhf", callsign);
+            dimensions(
+                values,
+                *shiptype,
+                *to_bow,
+                *to_stern,
+                *to_port,
+                *to_starboard,
+            );
+        }
+        AisMessage::Other { .. } => {}
+    }
+}
+
+fn dimensions(
+    values: &mut Values,
+    shiptype: u8,
+    to_bow: u16,
+    to_stern: u16,
+    to_port: u8,
+    to_starboard: u8,
+) {
+    if shiptype != 0 {
+        values.json(
+            "design.aisShipType",
+            Some(JsonObject::new().int("id", i64::from(shiptype)).finish()),
+        );
+    }
+    if to_bow + to_stern > 0 {
+        values.json(
+            "design.length",
+            Some(
+                JsonObject::new()
+                    .num("overall", f64::from(to_bow + to_stern))
+                    .finish(),
+            ),
+        );
+    }
+    if to_port + to_starboard > 0 {
+        values.num(
+            "design.beam",
+            Some(f64::from(to_port) + f64::from(to_starboard)),
+        );
+    }
+}
+
+/// Streams Signal K deltas to TCP clients, one delta per line, or to WebSocket clients
+/// connecting to any path, e.g. `/signalk/v1/stream`. Clients receive the hello message
+/// first, data sent by clients is ignored.
+pub struct SignalKServer {
+    listener: TcpListener,
+    websocket: bool,
+    converter: SignalKConverter,
+    tx: broadcast::Sender<String>,
+}
+
+impl SignalKServer {
+    pub async fn bind_tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::bind(addr, false).await
+    }
+
+    pub async fn bind_websocket<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Self::bind(addr, true).await
+    }
+
+    async fn bind<A: ToSocketAddrs>(addr: A, websocket: bool) -> io::Result<Self> {
+        let (tx, _) = broadcast::channel(SERVER_QUEUE_SIZE);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            websocket,
+            converter: SignalKConverter::default(),
+            tx,
+        })
+    }
+
+    pub fn with_converter(mut self, converter: SignalKConverter) -> Self {
+        self.converter = converter;
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Accepts clients and streams the deltas of the sentences of `stream`, e.g. from
+    /// [`crate::get_codec`], until it ends. Decode errors are skipped.
+    pub async fn serve<S>(self, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let Self {
+            listener,
+            websocket,
+            mut converter,
+            tx,
+        } = self;
+        let accept = run(listener, websocket, converter.hello(), tx.clone());
+        let forward = async move {
+            let mut stream = ResumeOnError::new(stream);
+            while let Some(result) = stream.next().await {
+                if let Some(delta) = result.ok().and_then(|msg| converter.convert(&msg)) {
+                    // no receivers is not an error, clients come and go
+                    let _ = tx.send(delta);
+                }
+            }
+            Ok(())
+        };
+        accept_while(accept, forward).await
+    }
+}
+
+async fn run(
+    listener: TcpListener,
+    websocket: bool,
+    hello: String,
+    tx: broadcast::Sender<String>,
+) -> io::Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let rx = tx.subscribe();
+        if websocket {
+            tokio::spawn(serve_websocket(socket, hello.clone(), rx));
+        } else {
+            tokio::spawn(serve_tcp(socket, hello.clone(), rx));
+        }
+    }
+}
+
+async fn serve_tcp(mut socket: TcpStream, hello: String, mut rx: broadcast::Receiver<String>) {
+    let mut line = hello;
+    loop {
+        line.push_str("\r\n");
+        if socket.write_all(line.as_bytes()).await.is_err() {
+            break;
+        }
+        line = match rx.recv().await {
+            Ok(delta) => delta,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+    }
+}
+
+async fn serve_websocket(socket: TcpStream, hello: String, mut rx: broadcast::Receiver<String>) {
+    let (mut writer, mut reader) = match tokio_tungstenite::accept_async(socket).await {
+        Ok(websocket) => websocket.split(),
+        Err(_) => return,
+    };
+    if writer.send(Message::text(hello)).await.is_err() {
+        return;
+    }
+    loop {
+        tokio::select! {
+            message = reader.next() => match message {
+                // subscriptions are not supported, pings are answered by tungstenite
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            delta = rx.recv() => match delta {
+                Ok(delta) => {
+                    if writer.send(Message::text(delta)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E";
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_convert() {
+        let mut converter = SignalKConverter::default();
+        let corrupted = RMC.replace("*7E", "*00");
+        assert_eq!(converter.convert_at(&msg(&corrupted), 1396205346.5), None);
+        let delta = converter.convert_at(&msg(RMC), 1396205346.5).unwrap();
+        assert!(delta.starts_with(
+            r#"{"context":"vessels.self","updates":[{"source":{"label":"nmea0183","type":"NMEA0183","talker":"GP","sentence":"RMC"},"$source":"nmea0183.GP","timestamp":"2014-03-30T18:49:06.500Z","values":[{"path":"navigation.position","value":{"latitude":8.93660"#
+        ), "{}", delta);
+        assert!(delta.contains(r#"{"path":"navigation.speedOverGround","value":0.15433"#));
+        assert!(
+            delta.contains(r#"{"path":"navigation.datetime","value":"2014-03-30T18:49:06.000Z"}"#)
+        );
+
+        let delta = converter
+            .convert_at(&msg("$SDDPT,10.7,-1.2*4F"), 0.0)
+            .unwrap();
+        assert!(delta.contains(r#"{"path":"environment.depth.belowTransducer","value":10.7}"#));
+        assert!(delta.contains(r#""path":"environment.depth.belowKeel""#));
+
+        let delta = converter
+            .convert_at(&msg("!AIVDM,1,1,,B,15M67FC000G?ufbE`FepT@3n00Sa,0*5C"), 0.0)
+            .unwrap();
+        assert!(delta.starts_with(r#"{"context":"vessels.urn:mrn:imo:mmsi:366053209","#));
+        assert!(
+            delta.contains(r#"{"path":"navigation.state","value":"restricted manouverability"}"#)
+        );
+
+        assert!(converter
+            .convert_at(&msg("$GPVTG,222.30,T,,M,0.30,N,0.6,K,A*09"), 0.0)
+            .is_none());
+    }
+
+    #[test]
+    fn test_tcp_server() {
+        tokio_test::block_on(async {
+            let server = SignalKServer::bind_tcp("127.0.0.1:0").await.unwrap();
+            let addr = server.local_addr().unwrap();
+            let (tx, rx) = mpsc::unbounded::<Result<Nmea0183Msg, io::Error>>();
+            tokio::spawn(server.serve(rx));
+
+            let mut lines = BufReader::new(TcpStream::connect(addr).await.unwrap()).lines();
+            let hello = lines.next_line().await.unwrap().unwrap();
+            assert!(hello.starts_with(r#"{"name":"nmea0183_feed""#), "{}", hello);
+            tx.unbounded_send(Ok(msg(RMC))).unwrap();
+            let delta = lines.next_line().await.unwrap().unwrap();
+            assert!(
+                delta.starts_with(r#"{"context":"vessels.self""#),
+                "{}",
+                delta
+            );
+        })
+    }
+
+    #[test]
+    fn test_websocket_server() {
+        tokio_test::block_on(async {
+            let server = SignalKServer::bind_websocket("127.0.0.1:0").await.unwrap();
+            let addr = server.local_addr().unwrap();
+            let (tx, rx) = mpsc::unbounded::<Result<Nmea0183Msg, io::Error>>();
+            tokio::spawn(server.serve(rx));
+
+            let url = format!("ws://{}/signalk/v1/stream", addr);
+            let (mut websocket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            let hello = websocket.next().await.unwrap().unwrap();
+            assert!(hello
+                .to_text()
+                .unwrap()
+                .contains(r#""self":"vessels.self""#));
+            tx.unbounded_send(Ok(msg(RMC))).unwrap();
+            let delta = websocket.next().await.unwrap().unwrap();
+            assert!(delta
+                .to_text()
+                .unwrap()
+                .contains(r#""path":"navigation.position""#));
+        })
+    }
+}
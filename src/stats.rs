@@ -0,0 +1,184 @@
+use crate::Nmea0183Msg;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Counter {
+    pub count: u64,
+    pub invalid_checksum: u64,
+}
+
+/// Counts sentences per sentence id, e.g. `GPRMC`, and per talker together with decode
+/// errors and invalid checksums.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    started: Instant,
+    sentences: BTreeMap<String, Counter>,
+    talkers: BTreeMap<String, Counter>,
+    errors: u64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            sentences: BTreeMap::new(),
+            talkers: BTreeMap::new(),
+            errors: 0,
+        }
+    }
+
+    pub fn update(&mut self, result: &Result<Nmea0183Msg, io::Error>) {
+        match result {
+            Ok(msg) => {
+                let invalid = u64::from(msg.chksum_valid() == Some(false));
+                let id = format!("{}{}", msg.talker(), msg.msgtype());
+                for counter in [
+                    self.sentences.entry(id).or_default(),
+                    self.talkers.entry(msg.talker().to_string()).or_default(),
+                ] {
+                    counter.count += 1;
+                    counter.invalid_checksum += invalid;
+                }
+            }
+            Err(_) => self.errors += 1,
+        }
+    }
+
+    /// Starts counting from zero.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub fn sentences(&self) -> impl Iterator<Item = (&str, &Counter)> {
+        self.sentences
+            .iter()
+            .map(|(id, counter)| (id.as_str(), counter))
+    }
+
+    pub fn talkers(&self) -> impl Iterator<Item = (&str, &Counter)> {
+        self.talkers
+            .iter()
+            .map(|(id, counter)| (id.as_str(), counter))
+    }
+
+    /// Decoded sentences including those with an invalid checksum.
+    pub fn total(&self) -> u64 {
+        self.talkers.values().map(|counter| counter.count).sum()
+    }
+
+    pub fn invalid_checksums(&self) -> u64 {
+        self.talkers
+            .values()
+            .map(|counter| counter.invalid_checksum)
+            .sum()
+    }
+
+    /// Decode errors, e.g. garbled lines.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Share of decode errors and invalid checksums among all received lines.
+    pub fn error_rate(&self) -> f64 {
+        let lines = self.total() + self.errors;
+        if lines == 0 {
+            0.0
+        } else {
+            (self.errors + self.invalid_checksums()) as f64 / lines as f64
+        }
+    }
+
+    /// Average rate per second since the start.
+    pub fn rate(&self, count: u64) -> f64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            count as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<12} {:>10} {:>10} {:>8}",
+            "sentence", "count", "invalid", "rate/s"
+        )?;
+        for (id, counter) in self.sentences() {
+            writeln!(
+                f,
+                "{:<12} {:>10} {:>10} {:>8.2}",
+                id,
+                counter.count,
+                counter.invalid_checksum,
+                self.rate(counter.count)
+            )?;
+        }
+        write!(
+            f,
+            "{:<12} {:>10} {:>10} {:>8.2}\nerrors {}, error rate {:.2}%",
+            "total",
+            self.total(),
+            self.invalid_checksums(),
+            self.rate(self.total()),
+            self.errors,
+            self.error_rate() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut stats = Stats::new();
+        for sentence in [
+            "$GPZDA,160012.71,11,03,2004,-1,00*7D",
+            // invalid checksum
+            concat!("$GPZDA,160012.71,11,03,2004,-1,00*", "00"),
+            "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E",
+            "$IIDPT,10.7,-1.2*58",
+        ] {
+            stats.update(&sentence.parse::<Nmea0183Msg>().map_err(io::Error::other));
+        }
+        stats.update(&Err(io::Error::other("Invalid event")));
+
+        let sentences = stats.sentences().collect::<Vec<_>>();
+        assert_eq!(sentences.len(), 3);
+        assert_eq!(
+            sentences[1],
+            (
+                "GPZDA",
+                &Counter {
+                    count: 2,
+                    invalid_checksum: 1
+                }
+            )
+        );
+        assert_eq!(
+            stats.talkers().map(|(id, _)| id).collect::<Vec<_>>(),
+            ["GP", "II"]
+        );
+        assert_eq!(stats.total(), 4);
+        assert_eq!(stats.errors(), 1);
+        assert_eq!(stats.error_rate(), 0.4);
+        assert!(stats.to_string().ends_with("errors 1, error rate 40.00%"));
+    }
+}
@@ -0,0 +1,670 @@
+use crate::sentence::{
+    Dbt, Dpt, Gga, Gll, Gsa, Gsv, Hsc, Osd, Rmc, Rot, Rpm, Rsa, Ttm, UtcDate, UtcTime, Vbw, Vhw,
+    Vlw, Xdr, Zda,
+};
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn time(time: Option<UtcTime>) -> String {
+    time.map(|time| format!("{:02}:{:02}:{:06.3}", time.hour, time.minute, time.second))
+        .unwrap_or_default()
+}
+
+fn date(date: Option<UtcDate>) -> String {
+    date.map(|date| format!("{:04}-{:02}-{:02}", date.year, date.month, date.day))
+        .unwrap_or_default()
+}
+
+const GGA: &[&str] = &[
+    "time",
+    "lat",
+    "lon",
+    "quality",
+    "sats",
+    "hdop",
+    "alt",
+    "geoid_sep",
+    "dgps_age",
+    "dgps_station",
+];
+const GLL: &[&str] = &["time", "lat", "lon", "status", "mode"];
+const GSA: &[&str] = &[
+    "selection_mode",
+    "fix_mode",
+    "sats",
+    "pdop",
+    "hdop",
+    "vdop",
+    "system_id",
+];
+const GSV: &[&str] = &["in_view", "prn", "elevation", "azimuth", "snr"];
+const RMC: &[&str] = &[
+    "date",
+    "time",
+    "status",
+    "lat",
+    "lon",
+    "speed_knots",
+    "course",
+    "mag_var",
+    "mode",
+];
+const ZDA: &[&str] = &["date", "time", "zone_hours", "zone_minutes"];
+const DBT: &[&str] = &["depth_feet", "depth_meters", "depth_fathoms"];
+const DPT: &[&str] = &["depth", "offset", "range"];
+const HSC: &[&str] = &["heading_true", "heading_magnetic"];
+const OSD: &[&str] = &[
+    "heading",
+    "status",
+    "course",
+    "course_ref",
+    "speed",
+    "speed_ref",
+    "set",
+    "drift",
+    "speed_units",
+];
+const ROT: &[&str] = &["rate", "status"];
+const RPM: &[&str] = &["source", "number", "speed", "pitch", "status"];
+const RSA: &[&str] = &["starboard", "starboard_status", "port", "port_status"];
+const TTM: &[&str] = &[
+    "time",
+    "target",
+    "distance",
+    "bearing",
+    "bearing_units",
+    "speed",
+    "course",
+    "course_units",
+    "cpa_distance",
+    "cpa_time",
+    "units",
+    "name",
+    "status",
+    "reference",
+    "acquisition",
+];
+const VBW: &[&str] = &[
+    "water_longitudinal",
+    "water_transverse",
+    "water_status",
+    "ground_longitudinal",
+    "ground_transverse",
+    "ground_status",
+];
+const VHW: &[&str] = &[
+    "heading_true",
+    "heading_magnetic",
+    "speed_knots",
+    "speed_kmh",
+];
+const VLW: &[&str] = &["total_distance", "trip_distance"];
+const XDR: &[&str] = &["type", "value", "unit", "name", "si_value", "si_unit"];
+
+type Rows = (&'static [&'static str], Vec<Vec<String>>);
+
+/// The typed columns and rows of a sentence, GSV has a row per satellite and XDR per
+/// measurement. `None` for message types without typed decoder.
+fn rows(msg: &Nmea0183Msg) -> Option<Result<Rows, String>> {
+    fn row<T, F>(msg: &Nmea0183Msg, columns: &'static [&'static str], f: F) -> Result<Rows, String>
+    where
+        T: for<'a> TryFrom<&'a Nmea0183Msg, Error = String>,
+        F: FnOnce(T) -> Vec<Vec<String>>,
+    {
+        T::try_from(msg).map(|sentence| (columns, f(sentence)))
+    }
+
+    Some(match msg.msgtype() {
+        "GGA" => row(msg, GGA, |gga: Gga| {
+            vec![vec![
+                time(gga.time),
+                opt(gga.latitude),
+                opt(gga.longitude),
+                opt(gga.quality),
+                opt(gga.satellites),
+                opt(gga.hdop),
+                opt(gga.altitude),
+                opt(gga.geoid_separation),
+                opt(gga.dgps_age),
+                opt(gga.dgps_station),
+            ]]
+        }),
+        "GLL" => row(msg, GLL, |gll: Gll| {
+            vec![vec![
+                time(gll.time),
+                opt(gll.latitude),
+                opt(gll.longitude),
+                opt(gll.status),
+                opt(gll.mode),
+            ]]
+        }),
+        "GSA" => row(msg, GSA, |gsa: Gsa| {
+            let satellites = gsa.satellites.iter().map(u16::to_string);
+            vec![vec![
+                opt(gsa.selection_mode),
+                opt(gsa.fix_mode),
+                satellites.collect::<Vec<_>>().join(" "),
+                opt(gsa.pdop),
+                opt(gsa.hdop),
+                opt(gsa.vdop),
+                opt(gsa.system_id),
+            ]]
+        }),
+        "GSV" => row(msg, GSV, |gsv: Gsv| {
+            gsv.satellites
+                .iter()
+                .map(|satellite| {
+                    vec![
+                        opt(gsv.satellites_in_view),
+                        opt(satellite.prn),
+                        opt(satellite.elevation),
+                        opt(satellite.azimuth),
+                        opt(satellite.snr),
+                    ]
+                })
+                .collect()
+        }),
+        "RMC" => row(msg, RMC, |rmc: Rmc| {
+            vec![vec![
+                date(rmc.date),
+                time(rmc.time),
+                opt(rmc.status),
+                opt(rmc.latitude),
+                opt(rmc.longitude),
+                opt(rmc.speed_knots),
+                opt(rmc.course),
+                opt(rmc.magnetic_variation),
+                opt(rmc.mode),
+            ]]
+        }),
+        "ZDA" => row(msg, ZDA, |zda: Zda| {
+            vec![vec![
+                date(zda.date),
+                time(zda.time),
+                opt(zda.zone_hours),
+                opt(zda.zone_minutes),
+            ]]
+        }),
+        "DBT" => row(msg, DBT, |dbt: Dbt| {
+            vec![vec![
+                opt(dbt.depth_feet),
+                opt(dbt.depth_meters),
+                opt(dbt.depth_fathoms),
+            ]]
+        }),
+        "DPT" => row(msg, DPT, |dpt: Dpt| {
+            vec![vec![opt(dpt.depth), opt(dpt.offset), opt(dpt.range)]]
+        }),
+        "HSC" => row(msg, HSC, |hsc: Hsc| {
+            vec![vec![opt(hsc.heading_true), opt(hsc.heading_magnetic)]]
+        }),
+        "OSD" => row(msg, OSD, |osd: Osd| {
+            vec![vec![
+                opt(osd.heading),
+                opt(osd.status),
+                opt(osd.course),
+                opt(osd.course_reference),
+                opt(osd.speed),
+                opt(osd.speed_reference),
+                opt(osd.set),
+                opt(osd.drift),
+                opt(osd.speed_units),
+            ]]
+        }),
+        "ROT" => row(msg, ROT, |rot: Rot| {
+            vec![vec![opt(rot.rate), opt(rot.status)]]
+        }),
+        "RPM" => row(msg, RPM, |rpm: Rpm| {
+            vec![vec![
+                opt(rpm.source),
+                opt(rpm.number),
+                opt(rpm.speed),
+                opt(rpm.pitch),
+                opt(rpm.status),
+            ]]
+        }),
+        "RSA" => row(msg, RSA, |rsa: Rsa| {
+            vec![vec![
+                opt(rsa.starboard),
+                opt(rsa.starboard_status),
+                opt(rsa.port),
+                opt(rsa.port_status),
+            ]]
+        }),
+        "TTM" => row(msg, TTM, |ttm: Ttm| {
+            vec![vec![
+                time(ttm.time),
+                opt(ttm.target_number),
+                opt(ttm.distance),
+                opt(ttm.bearing),
+                opt(ttm.bearing_units),
+                opt(ttm.speed),
+                opt(ttm.course),
+                opt(ttm.course_units),
+                opt(ttm.cpa_distance),
+                opt(ttm.cpa_time),
+                opt(ttm.units),
+                opt(ttm.name),
+                opt(ttm.status),
+                opt(ttm.reference),
+                opt(ttm.acquisition),
+            ]]
+        }),
+        "VBW" => row(msg, VBW, |vbw: Vbw| {
+            vec![vec![
+                opt(vbw.water_longitudinal),
+                opt(vbw.water_transverse),
+                opt(vbw.water_status),
+                opt(vbw.ground_longitudinal),
+                opt(vbw.ground_transverse),
+                opt(vbw.ground_status),
+            ]]
+        }),
+        "VHW" => row(msg, VHW, |vhw: Vhw| {
+            vec![vec![
+                opt(vhw.heading_true),
+                opt(vhw.heading_magnetic),
+                opt(vhw.speed_knots),
+                opt(vhw.speed_kmh),
+            ]]
+        }),
+        "VLW" => row(msg, VLW, |vlw: Vlw| {
+            vec![vec![opt(vlw.total_distance), opt(vlw.trip_distance)]]
+        }),
+        "XDR" => row(msg, XDR, |xdr: Xdr| {
+            xdr.measurements
+                .iter()
+                .map(|measurement| {
+                    vec![
+                        char::from(measurement.transducer).to_string(),
+                        opt(measurement.value),
+                        measurement.unit.clone(),
+                        measurement.name.clone(),
+                        opt(measurement.si_value()),
+                        measurement.si_unit().to_string(),
+                    ]
+                })
+                .collect()
+        }),
+        _ => return None,
+    })
+}
+
+const EPOCH: &[&str] = &[
+    "date",
+    "time",
+    "lat",
+    "lon",
+    "alt",
+    "quality",
+    "sats",
+    "hdop",
+    "pdop",
+    "vdop",
+    "fix_mode",
+    "speed_knots",
+    "course",
+    "heading_true",
+    "heading_magnetic",
+    "water_speed_knots",
+    "depth",
+    "rate_of_turn",
+    "rudder",
+];
+
+/// Values of all sentences received between two fix times.
+#[derive(Default)]
+struct Epoch {
+    time: Option<UtcTime>,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    altitude: Option<f64>,
+    quality: Option<u8>,
+    satellites: Option<u8>,
+    hdop: Option<f64>,
+    pdop: Option<f64>,
+    vdop: Option<f64>,
+    fix_mode: Option<u8>,
+    speed_knots: Option<f64>,
+    course: Option<f64>,
+    heading_true: Option<f64>,
+    heading_magnetic: Option<f64>,
+    water_speed_knots: Option<f64>,
+    depth: Option<f64>,
+    rate_of_turn: Option<f64>,
+    rudder: Option<f64>,
+}
+
+impl Epoch {
+    fn row(&self, date: Option<UtcDate>) -> Vec<String> {
+        vec![
+            self::date(date),
+            time(self.time),
+            opt(self.latitude),
+            opt(self.longitude),
+            opt(self.altitude),
+            opt(self.quality),
+            opt(self.satellites),
+            opt(self.hdop),
+            opt(self.pdop),
+            opt(self.vdop),
+            opt(self.fix_mode),
+            opt(self.speed_knots),
+            opt(self.course),
+            opt(self.heading_true),
+            opt(self.heading_magnetic),
+            opt(self.water_speed_knots),
+            opt(self.depth),
+            opt(self.rate_of_turn),
+            opt(self.rudder),
+        ]
+    }
+}
+
+fn line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|field| csv_field(field.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+struct Table {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    rows: u64,
+}
+
+impl Table {
+    /// Creates the file with the header line.
+    async fn create(path: PathBuf, columns: &[&str]) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(&path).await?);
+        writer.write_all(line(columns).as_bytes()).await?;
+        Ok(Self {
+            path,
+            writer,
+            rows: 0,
+        })
+    }
+
+    async fn write<S: AsRef<str>>(&mut self, fields: &[S]) -> io::Result<()> {
+        self.rows += 1;
+        self.writer.write_all(line(fields).as_bytes()).await
+    }
+}
+
+/// Writes a CSV file per sentence type, e.g. `gga.csv`, with typed columns into a
+/// directory. Every row starts with the receive time from the tag block in seconds since
+/// the unix epoch, if any, and the talker. Sentences with an invalid checksum or that
+/// fail to decode are skipped.
+///
+/// `epochs.csv` merges the sentences into a row per fix time of GGA, RMC, GLL or ZDA,
+/// sentences without time are added to the current epoch. The date is the last one
+/// received with RMC or ZDA.
+pub struct CsvExporter {
+    dir: PathBuf,
+    tables: BTreeMap<String, Table>,
+    epochs: Option<Table>,
+    epoch: Option<Epoch>,
+    date: Option<UtcDate>,
+}
+
+impl CsvExporter {
+    /// The files are created on the first row.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            tables: BTreeMap::new(),
+            epochs: None,
+            epoch: None,
+            date: None,
+        }
+    }
+
+    pub async fn write_msg(&mut self, msg: &Nmea0183Msg) -> io::Result<()> {
+        if msg.chksum_valid() == Some(false) {
+            return Ok(());
+        }
+        let (columns, rows) = match rows(msg) {
+            Some(Ok(rows)) => rows,
+            _ => return Ok(()),
+        };
+        if !self.tables.contains_key(msg.msgtype()) {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            let path = self
+                .dir
+                .join(format!("{}.csv", msg.msgtype().to_lowercase()));
+            let header = [&["received", "talker"], columns].concat();
+            let table = Table::create(path, &header).await?;
+            self.tables.insert(msg.msgtype().to_string(), table);
+        }
+        let table = self.tables.get_mut(msg.msgtype()).expect("table");
+        let received = opt(msg.tag_block().and_then(|tag_block| tag_block.timestamp()));
+        for row in rows {
+            let prefix = [received.clone(), msg.talker().to_string()];
+            table.write(&[&prefix[..], &row].concat()).await?;
+        }
+        self.update_epoch(msg).await
+    }
+
+    async fn update_epoch(&mut self, msg: &Nmea0183Msg) -> io::Result<()> {
+        let fix_time = match msg.msgtype() {
+            "GGA" => Gga::try_from(msg).ok().and_then(|gga| gga.time),
+            "RMC" => Rmc::try_from(msg).ok().and_then(|rmc| rmc.time),
+            "GLL" => Gll::try_from(msg).ok().and_then(|gll| gll.time),
+            "ZDA" => Zda::try_from(msg).ok().and_then(|zda| zda.time),
+            _ => None,
+        };
+        if let Some(fix_time) = fix_time {
+            if matches!(&self.epoch, Some(Epoch { time: Some(time), .. }) if *time != fix_time) {
+                self.write_epoch().await?;
+            }
+        }
+        let epoch = self.epoch.get_or_insert_with(Epoch::default);
+        if fix_time.is_some() {
+            epoch.time = fix_time;
+        }
+        match msg.msgtype() {
+            "GGA" => {
+                if let Ok(gga) = Gga::try_from(msg) {
+                    epoch.latitude = gga.latitude.or(epoch.latitude);
+                    epoch.longitude = gga.longitude.or(epoch.longitude);
+                    epoch.altitude = gga.altitude;
+                    epoch.quality = gga.quality;
+                    epoch.satellites = gga.satellites;
+                    epoch.hdop = gga.hdop.or(epoch.hdop);
+                }
+            }
+            "RMC" => {
+                if let Ok(rmc) = Rmc::try_from(msg) {
+                    self.date = rmc.date.or(self.date);
+                    epoch.latitude = rmc.latitude.or(epoch.latitude);
+                    epoch.longitude = rmc.longitude.or(epoch.longitude);
+                    epoch.speed_knots = rmc.speed_knots;
+                    epoch.course = rmc.course;
+                }
+            }
+            "GLL" => {
+                if let Ok(gll) = Gll::try_from(msg) {
+                    epoch.latitude = gll.latitude.or(epoch.latitude);
+                    epoch.longitude = gll.longitude.or(epoch.longitude);
+                }
+            }
+            "ZDA" => {
+                if let Ok(zda) = Zda::try_from(msg) {
+                    self.date = zda.date.or(self.date);
+                }
+            }
+            "GSA" => {
+                if let Ok(gsa) = Gsa::try_from(msg) {
+                    epoch.fix_mode = gsa.fix_mode;
+                    epoch.pdop = gsa.pdop;
+                    epoch.hdop = gsa.hdop.or(epoch.hdop);
+                    epoch.vdop = gsa.vdop;
+                }
+            }
+            "HSC" => {
+                if let Ok(hsc) = Hsc::try_from(msg) {
+                    epoch.heading_true = hsc.heading_true.or(epoch.heading_true);
+                    epoch.heading_magnetic = hsc.heading_magnetic.or(epoch.heading_magnetic);
+                }
+            }
+            "VHW" => {
+                if let Ok(vhw) = Vhw::try_from(msg) {
+                    epoch.heading_true = vhw.heading_true.or(epoch.heading_true);
+                    epoch.heading_magnetic = vhw.heading_magnetic.or(epoch.heading_magnetic);
+                    epoch.water_speed_knots = vhw.speed_knots;
+                }
+            }
+            "DBT" => {
+                if let Ok(dbt) = Dbt::try_from(msg) {
+                    epoch.depth = dbt.depth().or(epoch.depth);
+                }
+            }
+            "DPT" => {
+                if let Ok(dpt) = Dpt::try_from(msg) {
+                    epoch.depth = dpt.depth.or(epoch.depth);
+                }
+            }
+            "ROT" => {
+                if let Ok(rot) = Rot::try_from(msg) {
+                    epoch.rate_of_turn = rot.rate;
+                }
+            }
+            "RSA" => {
+                if let Ok(rsa) = Rsa::try_from(msg) {
+                    epoch.rudder = rsa.starboard;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn write_epoch(&mut self) -> io::Result<()> {
+        let epoch = match self.epoch.take() {
+            Some(epoch) => epoch,
+            None => return Ok(()),
+        };
+        if self.epochs.is_none() {
+            tokio::fs::create_dir_all(&self.dir).await?;
+            self.epochs = Some(Table::create(self.dir.join("epochs.csv"), EPOCH).await?);
+        }
+        let date = self.date;
+        let epochs = self.epochs.as_mut().expect("epoch table");
+        epochs.write(&epoch.row(date)).await
+    }
+
+    /// Writes the last epoch and flushes all files. Returns the written files with their
+    /// number of rows.
+    pub async fn close(&mut self) -> io::Result<Vec<(PathBuf, u64)>> {
+        self.write_epoch().await?;
+        let mut files = Vec::new();
+        for table in self.tables.values_mut().chain(self.epochs.as_mut()) {
+            table.writer.flush().await?;
+            files.push((table.path.clone(), table.rows));
+        }
+        Ok(files)
+    }
+
+    /// Exports a decoded stream, e.g. from [`crate::get_codec`], until it ends.
+    pub async fn export<S>(&mut self, stream: S) -> io::Result<Vec<(PathBuf, u64)>>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        while let Some(result) = stream.next().await {
+            if let Ok(msg) = result {
+                self.write_msg(&msg).await?;
+            }
+        }
+        self.close().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag_block::TagBlock;
+
+    #[test]
+    fn test_export() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let mut exporter = CsvExporter::new(dir.path());
+            let mut gga: Nmea0183Msg =
+                "$GPGGA,184906.000,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*43"
+                    .parse()
+                    .unwrap();
+            gga.set_tag_block(Some(TagBlock::new().with("c", "1396205346")));
+            exporter.write_msg(&gga).await.unwrap();
+            for sentence in [
+                "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E",
+                "$GPGSA,A,3,29,31,01,27,16,32,22,20,14,18,03,,1.4,0.7,1.2*35",
+                "$SDDPT,10.7,-1.2*4F",
+                // invalid checksum
+                concat!("$SDDPT,12.7,-1.2*", "4F"),
+                "$GPGSV,3,2,12,14,35,065,45,06,19,181,,27,13,188,38,20,09,319,30*72",
+                "$GPRMC,184907.000,V,,,,,,,300314,,,N*4B",
+                "$IIXDR,C,19.5,C,AirTemp,P,1.013,B,Baro*2A",
+            ] {
+                exporter
+                    .write_msg(&sentence.parse().unwrap())
+                    .await
+                    .unwrap();
+            }
+            let files = exporter.close().await.unwrap();
+            let names = files
+                .iter()
+                .map(|(path, rows)| (path.file_name().unwrap().to_str().unwrap(), *rows))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                names,
+                [
+                    ("dpt.csv", 1),
+                    ("gga.csv", 1),
+                    ("gsa.csv", 1),
+                    ("gsv.csv", 4),
+                    ("rmc.csv", 2),
+                    ("xdr.csv", 2),
+                    ("epochs.csv", 2)
+                ]
+            );
+
+            let read = |name: &str| std::fs::read_to_string(dir.path().join(name)).unwrap();
+            assert_eq!(
+                read("gga.csv"),
+                "received,talker,time,lat,lon,quality,sats,hdop,alt,geoid_sep,dgps_age,dgps_station\n\
+                1396205346,GP,18:49:06.000,8.936606666666668,-79.55546833333332,1,11,0.7,50.8,1.3,,0000\n"
+            );
+            assert!(read("xdr.csv").contains("\n,II,C,19.5,C,AirTemp,292.65,K\n"));
+            assert_eq!(
+                read("epochs.csv").lines().skip(1).collect::<Vec<_>>(),
+                [
+                    "2014-03-30,18:49:06.000,8.936606666666668,-79.55546833333332,50.8,1,11,0.7,1.4,1.2,3,0.3,222.3,,,,10.7,,",
+                    "2014-03-30,18:49:07.000,,,,,,,,,,,,,,,,,",
+                ]
+            );
+        })
+    }
+}
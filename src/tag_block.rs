@@ -0,0 +1,191 @@
+use std::fmt;
+use std::str::FromStr;
+
+// the c: parameter is specified in seconds, values above are milliseconds
+const MAX_SECONDS: f64 = 100_000_000_000.0;
+
+/// NMEA 4.x / IEC 61162-450 tag block preceding a sentence, e.g. `\s:GP0001,n:12*3E\`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagBlock {
+    entries: Vec<(String, String)>,
+    chksum_valid: Option<bool>,
+}
+
+impl TagBlock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(entry_key, _)| entry_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces the value of an existing key or appends a new entry.
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self
+            .entries
+            .iter_mut()
+            .find(|(entry_key, _)| entry_key == key)
+        {
+            Some((_, entry_value)) => *entry_value = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    pub fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|(entry_key, _)| entry_key == key)?;
+        Some(self.entries.remove(idx).1)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// `None` if the tag block had no checksum.
+    pub fn chksum_valid(&self) -> Option<bool> {
+        self.chksum_valid
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.get("s")
+    }
+
+    pub fn destination(&self) -> Option<&str> {
+        self.get("d")
+    }
+
+    pub fn line_count(&self) -> Option<u32> {
+        self.get("n")?.parse().ok()
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.get("t")
+    }
+
+    /// The `c` parameter in seconds since the unix epoch, millisecond values sent by some
+    /// devices are converted.
+    pub fn timestamp(&self) -> Option<f64> {
+        let value: f64 = self.get("c")?.parse().ok()?;
+        if value > MAX_SECONDS {
+            Some(value / 1000.0)
+        } else {
+            Some(value)
+        }
+    }
+
+    pub fn set_timestamp(&mut self, seconds: u64) {
+        self.set("c", seconds.to_string());
+    }
+
+    fn content(&self) -> String {
+        self.entries
+            .iter()
+            .map(|(key, value)| format!("{}:{}", key, value))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Formats the tag block including the enclosing backslashes, the checksum is always
+/// recalculated.
+impl fmt::Display for TagBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = self.content();
+        let chksum = content.bytes().fold(0, |chksum, byte| chksum ^ byte);
+        write!(f, "\\{}*{:02X}\\", content, chksum)
+    }
+}
+
+/// Parses the content between the backslashes, the checksum is optional.
+impl FromStr for TagBlock {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim_matches('\\');
+        let (content, chksum_valid) = match value.rsplit_once('*') {
+            Some((content, chksum)) => {
+                let calculated = content.bytes().fold(0, |chksum, byte| chksum ^ byte);
+                let chksum = u8::from_str_radix(chksum, 16)
+                    .map_err(|_| format!("Invalid tag block checksum '{}'", chksum))?;
+                (content, Some(chksum == calculated))
+            }
+            None => (value, None),
+        };
+        let mut entries = Vec::new();
+        if !content.is_empty() {
+            for entry in content.split(',') {
+                match entry.split_once(':') {
+                    Some((key, value)) if !key.is_empty() => {
+                        entries.push((key.to_string(), value.to_string()))
+                    }
+                    _ => return Err(format!("Invalid tag block entry '{}'", entry)),
+                }
+            }
+        }
+        Ok(Self {
+            entries,
+            chksum_valid,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Nmea0183Msg;
+
+    #[test]
+    fn test_tag_block() {
+        let tag_block: TagBlock = "\\g:1-2-73874,n:157036,s:r003669945,c:1241544035*4A\\"
+            .parse()
+            .unwrap();
+        assert_eq!(tag_block.chksum_valid(), Some(true));
+        assert_eq!(tag_block.source(), Some("r003669945"));
+        assert_eq!(tag_block.line_count(), Some(157036));
+        assert_eq!(tag_block.get("g"), Some("1-2-73874"));
+        assert_eq!(tag_block.timestamp(), Some(1241544035.0));
+        assert!("s:GP0001,n".parse::<TagBlock>().is_err());
+
+        let tag_block = TagBlock::new()
+            .with("c", "1577836800000")
+            .with("s", "GP0001");
+        assert_eq!(tag_block.timestamp(), Some(1577836800.0));
+        let mut tag_block = tag_block;
+        tag_block.set_timestamp(1577836800);
+        assert_eq!(tag_block.to_string(), "\\c:1577836800,s:GP0001*2B\\");
+    }
+
+    #[test]
+    fn test_msg_with_tag_block() {
+        let sentence = "\\c:1577836800,s:GP0001*2B\\$GPZDA,160012.71,11,03,2004,-1,00*7D";
+        let msg: Nmea0183Msg = sentence.parse().unwrap();
+        assert_eq!(msg.talker(), "GP");
+        assert_eq!(msg.msgtype(), "ZDA");
+        assert_eq!(msg.tag_block().unwrap().timestamp(), Some(1577836800.0));
+        assert_eq!(msg.to_string(), sentence);
+        assert!(
+            "\\s:GP0001\\\\s:GP0002\\$GPZDA,160012.71,11,03,2004,-1,00*7D"
+                .parse::<Nmea0183Msg>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{Nmea0183Codec, Nmea0183Msg, ResumeOnError};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio_util::codec::{Decoder, FramedWrite};
+
+/// Port commonly used by NMEA 0183 gateways.
+pub const NMEA_PORT: u16 = 10110;
+
+const CLIENT_QUEUE_SIZE: usize = 64;
+const SERVER_QUEUE_SIZE: usize = 1024;
+
+/// Exponential backoff between reconnect attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub factor: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            factor: 2,
+        }
+    }
+}
+
+impl Backoff {
+    fn next(&self, delay: Duration) -> Duration {
+        (delay * self.factor).min(self.max)
+    }
+}
+
+/// TCP client that reconnects whenever the connection fails or is closed by the peer.
+/// Failed connection attempts and lost connections are yielded as errors, so each error
+/// other than a decode error is followed by a reconnect attempt. The stream itself never
+/// ends.
+pub struct TcpClient {
+    rx: mpsc::Receiver<Result<Nmea0183Msg, io::Error>>,
+    task: JoinHandle<()>,
+}
+
+impl TcpClient {
+    /// Starts connecting to `addr` in a background task, must be called within a tokio runtime.
+    pub fn connect(addr: &str, backoff: Backoff) -> Self {
+        Self::spawn(addr, backoff, false)
+    }
+
+    /// Like [`TcpClient::connect`] with a lenient codec, see [`Nmea0183Codec::with_lenient`].
+    pub fn connect_lenient(addr: &str, backoff: Backoff) -> Self {
+        Self::spawn(addr, backoff, true)
+    }
+
+    fn spawn(addr: &str, backoff: Backoff, lenient: bool) -> Self {
+        let (tx, rx) = mpsc::channel(CLIENT_QUEUE_SIZE);
+        let task = tokio::spawn(run_client(addr.to_string(), backoff, lenient, tx));
+        Self { rx, task }
+    }
+}
+
+impl Drop for TcpClient {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Stream for TcpClient {
+    type Item = Result<Nmea0183Msg, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+async fn run_client(
+    addr: String,
+    backoff: Backoff,
+    lenient: bool,
+    tx: mpsc::Sender<Result<Nmea0183Msg, io::Error>>,
+) {
+    let mut delay = backoff.initial;
+    loop {
+        match TcpStream::connect(addr.as_str()).await {
+            Ok(stream) => {
+                delay = backoff.initial;
+                let codec = Nmea0183Codec::new().with_lenient(lenient);
+                let mut reader = ResumeOnError::new(codec.framed(stream));
+                let mut failed = false;
+                while let Some(result) = reader.next().await {
+                    // decode errors are `Other`, everything else ends the connection
+                    failed = matches!(&result, Err(error) if error.kind() != io::ErrorKind::Other);
+                    if tx.send(result).await.is_err() {
+                        return;
+                    }
+                }
+                if !failed {
+                    let error = io::Error::new(
+                        io::ErrorKind::ConnectionAborted,
+                        format!("Connection to {} closed", addr),
+                    );
+                    if tx.send(Err(error)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(error) => {
+                if tx.send(Err(error)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        tokio::time::sleep(delay).await;
+        delay = backoff.next(delay);
+    }
+}
+
+/// Serves the re-encoded messages to any number of TCP clients. Clients that cannot keep
+/// up skip messages, data sent by clients is ignored.
+pub struct TcpServer {
+    listener: TcpListener,
+    tx: broadcast::Sender<Nmea0183Msg>,
+}
+
+impl TcpServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let (tx, _) = broadcast::channel(SERVER_QUEUE_SIZE);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            tx,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Messages sent here are forwarded to all connected clients.
+    pub fn sender(&self) -> broadcast::Sender<Nmea0183Msg> {
+        self.tx.clone()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Accepts clients until accepting fails, messages are published through
+    /// [`TcpServer::sender`].
+    pub async fn run(self) -> io::Result<()> {
+        loop {
+            let (socket, _) = self.listener.accept().await?;
+            tokio::spawn(serve_client(socket, self.tx.subscribe()));
+        }
+    }
+
+    /// Accepts clients and forwards the messages of `stream`, e.g. from
+    /// [`crate::get_codec`], until it ends. Decode errors are skipped.
+    pub async fn serve<S>(self, stream: S) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let tx = self.sender();
+        let forward = async move {
+            let mut stream = ResumeOnError::new(stream);
+            while let Some(result) = stream.next().await {
+                if let Ok(msg) = result {
+                    // no receivers is not an error, clients come and go
+                    let _ = tx.send(msg);
+                }
+            }
+            Ok(())
+        };
+        accept_while(self.run(), forward).await
+    }
+
+    /// Spawns a task for each waiting client.
+    fn poll_accept(&mut self, cx: &mut Context<'_>) -> io::Result<()> {
+        while let Poll::Ready(result) = self.listener.poll_accept(cx) {
+            let (socket, _) = result?;
+            tokio::spawn(serve_client(socket, self.tx.subscribe()));
+        }
+        Ok(())
+    }
+}
+
+/// Forwards the messages to all connected clients. Clients are accepted whenever the sink
+/// is polled, e.g. by [`SinkExt::send_all`] while it waits for the next message, and the
+/// listener is closed when the sink is dropped.
+impl Sink<Nmea0183Msg> for TcpServer {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.get_mut().poll_accept(cx))
+    }
+
+    fn start_send(self: Pin<&mut Self>, msg: Nmea0183Msg) -> Result<(), Self::Error> {
+        // no receivers is not an error, clients come and go
+        let _ = self.tx.send(msg);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(self.get_mut().poll_accept(cx))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Runs the accept loop of a server next to `forward`, which passes a stream on to the
+/// clients, until either ends. Both run in the calling task, so the listener is closed
+/// when this returns or is dropped and its address can be bound again right away.
+pub(crate) async fn accept_while<A, F>(accept: A, forward: F) -> io::Result<()>
+where
+    A: Future<Output = io::Result<()>>,
+    F: Future<Output = io::Result<()>>,
+{
+    tokio::select! {
+        result = accept => result,
+        result = forward => result,
+    }
+}
+
+async fn serve_client(socket: TcpStream, mut rx: broadcast::Receiver<Nmea0183Msg>) {
+    let mut writer = FramedWrite::new(socket, Nmea0183Codec::default());
+    loop {
+        match rx.recv().await {
+            Ok(msg) => {
+                if writer.send(msg).await.is_err() {
+                    break;
+                }
+            }
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    const ZDA: &str = "$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n";
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E\r\n";
+
+    #[test]
+    fn test_client_reconnect() {
+        tokio_test::block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            tokio::spawn(async move {
+                for sentence in [ZDA, RMC] {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    // the codec ignores the first error only
+                    socket.write_all(b"garbage\r\ngarbage\r\n").await.unwrap();
+                    socket.write_all(sentence.as_bytes()).await.unwrap();
+                    // closing the connection forces a reconnect
+                }
+            });
+
+            let backoff = Backoff {
+                initial: Duration::from_millis(10),
+                ..Default::default()
+            };
+            let mut client = TcpClient::connect(addr.as_str(), backoff);
+            let mut msgtypes = Vec::new();
+            while msgtypes.len() < 3 {
+                match client.next().await.unwrap() {
+                    Ok(msg) => msgtypes.push(msg.msgtype().to_string()),
+                    Err(error) if error.kind() == io::ErrorKind::ConnectionAborted => {
+                        msgtypes.push("closed".to_string())
+                    }
+                    Err(_) => {}
+                }
+            }
+            assert_eq!(msgtypes, ["ZDA", "closed", "RMC"]);
+        })
+    }
+
+    #[test]
+    fn test_server() {
+        tokio_test::block_on(async {
+            let server = TcpServer::bind("127.0.0.1:0").await.unwrap();
+            let addr = server.local_addr().unwrap();
+            let sender = server.sender();
+            tokio::spawn(server.run());
+
+            let socket = TcpStream::connect(addr).await.unwrap();
+            let mut lines = BufReader::new(socket).lines();
+            while sender.receiver_count() == 0 {
+                tokio::task::yield_now().await;
+            }
+            for sentence in [ZDA, RMC] {
+                sender.send(sentence.parse().unwrap()).unwrap();
+            }
+            for sentence in [ZDA, RMC] {
+                assert_eq!(
+                    lines.next_line().await.unwrap().unwrap(),
+                    sentence.trim_end()
+                );
+            }
+        })
+    }
+
+    #[test]
+    fn test_serve_releases_listener() {
+        tokio_test::block_on(async {
+            let server = TcpServer::bind("127.0.0.1:0").await.unwrap();
+            let addr = server.local_addr().unwrap();
+            let serve = server.serve(futures::stream::pending());
+            let cancelled = tokio::time::timeout(Duration::from_millis(10), serve).await;
+            assert!(cancelled.is_err());
+            // dropping the serve future closes the listener
+            TcpListener::bind(addr).await.unwrap();
+        })
+    }
+
+    #[test]
+    fn test_server_sink() {
+        tokio_test::block_on(async {
+            let mut server = TcpServer::bind("127.0.0.1:0").await.unwrap();
+            let addr = server.local_addr().unwrap();
+            let socket = TcpStream::connect(addr).await.unwrap();
+            let mut lines = BufReader::new(socket).lines();
+            // the client is accepted when the sink is polled
+            while server.client_count() == 0 {
+                server.flush().await.unwrap();
+                tokio::task::yield_now().await;
+            }
+            server.send(ZDA.parse().unwrap()).await.unwrap();
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), ZDA.trim_end());
+
+            drop(server);
+            TcpListener::bind(addr).await.unwrap();
+        })
+    }
+}
@@ -0,0 +1,612 @@
+use crate::ais::{AisDecoder, AisMessage, StaticDataReport};
+use crate::json::{array, quote, JsonObject};
+use crate::sentence::{iso8601, Gga, Gll, Rmc, UtcDate, Zda};
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Stream, StreamExt};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+
+const MPS_PER_KNOT: f64 = 1852.0 / 3600.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackPoint {
+    /// Seconds since the unix epoch.
+    pub time: Option<f64>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Altitude above mean sea level in meters.
+    pub elevation: Option<f64>,
+    /// GGA quality indicator.
+    pub quality: Option<u8>,
+    pub hdop: Option<f64>,
+    pub satellites: Option<u8>,
+    pub speed_knots: Option<f64>,
+    pub course: Option<f64>,
+}
+
+impl TrackPoint {
+    fn new(latitude: f64, longitude: f64) -> Self {
+        Self {
+            time: None,
+            latitude,
+            longitude,
+            elevation: None,
+            quality: None,
+            hdop: None,
+            satellites: None,
+            speed_knots: None,
+            course: None,
+        }
+    }
+
+    /// The GPX fix type, GGA does not tell 2D from 3D fixes, a missing elevation is taken
+    /// as 2D.
+    fn fix(&self) -> Option<&'static str> {
+        Some(match self.quality? {
+            0 => "none",
+            2 | 4 | 5 => "dgps",
+            3 => "pps",
+            _ if self.elevation.is_some() => "3d",
+            _ => "2d",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub name: String,
+    /// Set for AIS targets.
+    pub mmsi: Option<u32>,
+    pub segments: Vec<Vec<TrackPoint>>,
+}
+
+impl Track {
+    fn new(name: &str, mmsi: Option<u32>) -> Self {
+        Self {
+            name: name.to_string(),
+            mmsi,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Appends to the last segment unless `split` is set or the time gap to the last point
+    /// exceeds `max_gap`.
+    fn push(&mut self, point: TrackPoint, split: bool, max_gap: Option<f64>) {
+        let last_time = self
+            .segments
+            .last()
+            .and_then(|segment| segment.last())
+            .and_then(|last| last.time);
+        let gap = match (last_time, point.time, max_gap) {
+            (Some(last), Some(time), Some(max_gap)) => (time - last).abs() > max_gap,
+            _ => false,
+        };
+        match self.segments.last_mut() {
+            Some(segment) if !split && !gap => segment.push(point),
+            _ => self.segments.push(vec![point]),
+        }
+    }
+
+    pub fn points(&self) -> impl Iterator<Item = &TrackPoint> {
+        self.segments.iter().flatten()
+    }
+}
+
+/// Builds the own track from GGA, RMC and GLL and optionally AIS target tracks from
+/// VDM / VDO. Sentences with the same UTC time are merged into one point. A new segment is
+/// started after a fix loss or a time gap.
+///
+/// The date is taken from RMC or ZDA, until one was received the tag block timestamp is
+/// used as time.
+pub struct TrackBuilder {
+    max_gap: Option<f64>,
+    own: Track,
+    // seconds of day and point of the current epoch
+    pending: Option<(Option<f64>, TrackPoint)>,
+    split: bool,
+    date: Option<UtcDate>,
+    last_time: Option<f64>,
+    ais: Option<AisDecoder>,
+    names: BTreeMap<u32, String>,
+    targets: BTreeMap<u32, Track>,
+}
+
+impl Default for TrackBuilder {
+    fn default() -> Self {
+        Self::new("track")
+    }
+}
+
+impl TrackBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            max_gap: Some(10.0),
+            own: Track::new(name, None),
+            pending: None,
+            split: false,
+            date: None,
+            last_time: None,
+            ais: None,
+            names: BTreeMap::new(),
+            targets: BTreeMap::new(),
+        }
+    }
+
+    /// Maximum time between two points of a segment in seconds, 10 by default. `None`
+    /// only splits on fix loss.
+    pub fn with_max_gap(mut self, max_gap: Option<f64>) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Also builds a track per MMSI from AIS position reports.
+    pub fn with_ais(mut self, ais: bool) -> Self {
+        self.ais = ais.then(AisDecoder::new);
+        self
+    }
+
+    /// Sentences with an invalid checksum are ignored.
+    pub fn update(&mut self, msg: &Nmea0183Msg) {
+        if msg.chksum_valid() == Some(false) {
+            return;
+        }
+        let received = msg.tag_block().and_then(|tag_block| tag_block.timestamp());
+        match msg.msgtype() {
+            "GGA" => {
+                if let Ok(gga) = Gga::try_from(msg) {
+                    let time = gga.time.map(|time| time.seconds_of_day());
+                    match (gga.is_valid(), gga.latitude, gga.longitude) {
+                        (true, Some(latitude), Some(longitude)) => {
+                            let point = self.epoch(time, received, latitude, longitude);
+                            point.elevation = gga.altitude;
+                            point.quality = gga.quality;
+                            point.hdop = gga.hdop;
+                            point.satellites = gga.satellites;
+                        }
+                        _ => self.fix_lost(),
+                    }
+                }
+            }
+            "RMC" => {
+                if let Ok(rmc) = Rmc::try_from(msg) {
+                    if rmc.date.is_some() {
+                        self.date = rmc.date;
+                    }
+                    let time = rmc.time.map(|time| time.seconds_of_day());
+                    match (rmc.is_valid(), rmc.latitude, rmc.longitude) {
+                        (true, Some(latitude), Some(longitude)) => {
+                            let point = self.epoch(time, received, latitude, longitude);
+                            point.speed_knots = rmc.speed_knots;
+                            point.course = rmc.course;
+                        }
+                        _ => self.fix_lost(),
+                    }
+                }
+            }
+            "GLL" => {
+                if let Ok(gll) = Gll::try_from(msg) {
+                    let time = gll.time.map(|time| time.seconds_of_day());
+                    match (gll.is_valid(), gll.latitude, gll.longitude) {
+                        (true, Some(latitude), Some(longitude)) => {
+                            self.epoch(time, received, latitude, longitude);
+                        }
+                        _ => self.fix_lost(),
+                    }
+                }
+            }
+            "ZDA" => {
+                if let Some(date) = Zda::try_from(msg).ok().and_then(|zda| zda.date) {
+                    self.date = Some(date);
+                }
+            }
+            "VDM" | "VDO" => {
+                if let Some(Ok(Some(message))) = self.ais.as_mut().map(|ais| ais.decode(msg)) {
+                    self.update_ais(message, received);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The point of the epoch at `time`, completes the previous point if the time differs.
+    fn epoch(
+        &mut self,
+        time: Option<f64>,
+        received: Option<f64>,
+        latitude: f64,
+        longitude: f64,
+    ) -> &mut TrackPoint {
+        if !matches!(&self.pending, Some((pending, _)) if time.is_some() && *pending == time) {
+            self.flush();
+            let mut point = TrackPoint::new(latitude, longitude);
+            point.time = received;
+            self.pending = Some((time, point));
+        }
+        let (_, point) = self.pending.as_mut().expect("pending point");
+        if let (Some(date), Some(time)) = (self.date, time) {
+            let mut unix_time = date.days_since_epoch() as f64 * 86400.0 + time;
+            // the date of the previous day until RMC of the new day arrives
+            if matches!(self.last_time, Some(last) if unix_time < last - 43200.0) {
+                unix_time += 86400.0;
+            }
+            point.time = Some(unix_time);
+        }
+        point
+    }
+
+    fn fix_lost(&mut self) {
+        self.flush();
+        self.split = true;
+    }
+
+    fn flush(&mut self) {
+        if let Some((_, point)) = self.pending.take() {
+            if point.time.is_some() {
+                self.last_time = point.time;
+            }
+            self.own.push(point, self.split, self.max_gap);
+            self.split = false;
+        }
+    }
+
+    /// Time of the latest own position.
+    fn current_time(&self) -> Option<f64> {
+        self.pending
+            .as_ref()
+            .and_then(|(_, point)| point.time)
+            .or(self.last_time)
+    }
+
+    fn update_ais(&mut self, message: AisMessage, received: Option<f64>) {
+        let (mmsi, latitude, longitude, speed_knots, course) = match message {
+            AisMessage::PositionReport(report) => (
+                report.mmsi,
+                report.latitude,
+                report.longitude,
+                report.speed,
+                report.course,
+            ),
+            AisMessage::ClassBPosition(report) => (
+                report.mmsi,
+                report.latitude,
+                report.longitude,
+                report.speed,
+                report.course,
+            ),
+            AisMessage::StaticAndVoyage(report) => {
+                self.set_name(report.mmsi, &report.shipname);
+                return;
+            }
+            AisMessage::StaticDataReport(StaticDataReport::PartA { mmsi, shipname, .. }) => {
+                self.set_name(mmsi, &shipname);
+                return;
+            }
+            _ => return,
+        };
+        if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+            let mut point = TrackPoint::new(latitude, longitude);
+            point.time = received.or_else(|| self.current_time());
+            point.speed_knots = speed_knots;
+            point.course = course;
+            self.targets
+                .entry(mmsi)
+                .or_insert_with(|| Track::new(&mmsi.to_string(), Some(mmsi)))
+                .push(point, false, self.max_gap);
+        }
+    }
+
+    fn set_name(&mut self, mmsi: u32, shipname: &str) {
+        if !shipname.is_empty() {
+            self.names.insert(mmsi, shipname.to_string());
+        }
+    }
+
+    /// The own track followed by the AIS targets ordered by MMSI, tracks without points are
+    /// left out.
+    pub fn finish(mut self) -> Vec<Track> {
+        self.flush();
+        let mut tracks = vec![self.own];
+        for (mmsi, mut track) in self.targets {
+            if let Some(name) = self.names.get(&mmsi) {
+                track.name = format!("{} ({})", name, mmsi);
+            }
+            tracks.push(track);
+        }
+        tracks.retain(|track| !track.segments.is_empty());
+        tracks
+    }
+
+    /// Builds the tracks from all sentences of `stream`, e.g. from [`crate::get_codec`].
+    pub async fn read<S>(mut self, stream: S) -> Vec<Track>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+    {
+        let mut stream = ResumeOnError::new(stream);
+        while let Some(result) = stream.next().await {
+            if let Ok(msg) = result {
+                self.update(&msg);
+            }
+        }
+        self.finish()
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// GPX 1.1 with a track per [`Track`], speed in m/s and course are written as Garmin
+/// TrackPointExtension.
+pub fn to_gpx(tracks: &[Track]) -> String {
+    let mut gpx = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <gpx version=\"1.1\" creator=\"nmea0183_feed\" \
+        xmlns=\"http://www.topografix.com/GPX/1/1\" \
+        xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v2\">\n",
+    );
+    for track in tracks {
+        let _ = writeln!(gpx, "<trk><name>{}</name>", escape(&track.name));
+        for segment in &track.segments {
+            gpx.push_str("<trkseg>\n");
+            for point in segment {
+                let _ = write!(
+                    gpx,
+                    "<trkpt lat=\"{}\" lon=\"{}\">",
+                    point.latitude, point.longitude
+                );
+                if let Some(elevation) = point.elevation {
+                    let _ = write!(gpx, "<ele>{}</ele>", elevation);
+                }
+                if let Some(time) = point.time {
+                    let _ = write!(gpx, "<time>{}</time>", iso8601(time));
+                }
+                if let Some(fix) = point.fix() {
+                    let _ = write!(gpx, "<fix>{}</fix>", fix);
+                }
+                if let Some(satellites) = point.satellites {
+                    let _ = write!(gpx, "<sat>{}</sat>", satellites);
+                }
+                if let Some(hdop) = point.hdop {
+                    let _ = write!(gpx, "<hdop>{}</hdop>", hdop);
+                }
+                if point.speed_knots.is_some() || point.course.is_some() {
+                    gpx.push_str("<extensions><gpxtpx:TrackPointExtension>");
+                    if let Some(speed) = point.speed_knots {
+                        let _ =
+                            write!(gpx, "<gpxtpx:speed>{}</gpxtpx:speed>", speed * MPS_PER_KNOT);
+                    }
+                    if let Some(course) = point.course {
+                        let _ = write!(gpx, "<gpxtpx:course>{}</gpxtpx:course>", course);
+                    }
+                    gpx.push_str("</gpxtpx:TrackPointExtension></extensions>");
+                }
+                gpx.push_str("</trkpt>\n");
+            }
+            gpx.push_str("</trkseg>\n");
+        }
+        gpx.push_str("</trk>\n");
+    }
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+fn coordinates(point: &TrackPoint) -> Vec<String> {
+    let mut coordinates = vec![point.longitude.to_string(), point.latitude.to_string()];
+    if let Some(elevation) = point.elevation {
+        coordinates.push(elevation.to_string());
+    }
+    coordinates
+}
+
+/// KML with a folder per [`Track`] and a LineString placemark per segment, the time span of
+/// the segment is set if known.
+pub fn to_kml(tracks: &[Track]) -> String {
+    let mut kml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <kml xmlns=\"http://www.opengis.net/kml/2.2\"><Document>\n",
+    );
+    for track in tracks {
+        let _ = writeln!(kml, "<Folder><name>{}</name>", escape(&track.name));
+        for (idx, segment) in track.segments.iter().enumerate() {
+            let _ = write!(kml, "<Placemark><name>Segment {}</name>", idx + 1);
+            let times = segment.iter().filter_map(|point| point.time);
+            if let (Some(begin), Some(end)) =
+                (times.clone().reduce(f64::min), times.reduce(f64::max))
+            {
+                let _ = write!(
+                    kml,
+                    "<TimeSpan><begin>{}</begin><end>{}</end></TimeSpan>",
+                    iso8601(begin),
+                    iso8601(end)
+                );
+            }
+            let coordinates = segment
+                .iter()
+                .map(|point| coordinates(point).join(","))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let _ = writeln!(
+                kml,
+                "<LineString><coordinates>{}</coordinates></LineString></Placemark>",
+                coordinates
+            );
+        }
+        kml.push_str("</Folder>\n");
+    }
+    kml.push_str("</Document></kml>\n");
+    kml
+}
+
+/// A GeoJSON FeatureCollection with a MultiLineString feature per [`Track`]. The point times
+/// are in the `coordTimes` property with the same nesting as the coordinates, unknown times
+/// are `null`.
+pub fn to_geojson(tracks: &[Track]) -> String {
+    let features = tracks.iter().map(|track| {
+        let lines = track
+            .segments
+            .iter()
+            .map(|segment| array(segment.iter().map(|point| array(coordinates(point)))));
+        let times = track.segments.iter().map(|segment| {
+            array(segment.iter().map(|point| match point.time {
+                Some(time) => quote(&iso8601(time)),
+                None => "null".to_string(),
+            }))
+        });
+        let mut properties = JsonObject::new().str("name", &track.name);
+        if let Some(mmsi) = track.mmsi {
+            properties = properties.int("mmsi", i64::from(mmsi));
+        }
+        JsonObject::new()
+            .str("type", "Feature")
+            .raw(
+                "geometry",
+                &JsonObject::new()
+                    .str("type", "MultiLineString")
+                    .raw("coordinates", &array(lines))
+                    .finish(),
+            )
+            .raw(
+                "properties",
+                &properties.raw("coordTimes", &array(times)).finish(),
+            )
+            .finish()
+    });
+    JsonObject::new()
+        .str("type", "FeatureCollection")
+        .raw("features", &array(features))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sentence::Sentence;
+    use crate::tag_block::TagBlock;
+
+    fn update(builder: &mut TrackBuilder, sentences: &[&str]) {
+        for sentence in sentences {
+            builder.update(&sentence.parse().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_segments() {
+        let mut builder = TrackBuilder::new("own").with_ais(true);
+        update(
+            &mut builder,
+            &[
+                // time only, no date yet
+                "$GPGLL,0856.1964,N,07933.3281,W,184906.000,A,A*4E",
+                "$GPRMC,184907.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7F",
+                "$GPGGA,184907.000,0856.1964,N,07933.3281,W,1,11,0.7,50.8,M,1.3,M,,0000*42",
+                "$GPGGA,184908.000,0856.1943,N,07933.3291,W,0,00,,,M,,M,,*5E",
+                "$GPGGA,184909.000,0856.1942,N,07933.3292,W,2,09,0.9,51.1,M,1.3,M,,0000*46",
+                // 30s gap
+                "$GPGLL,0856.1941,N,07933.3293,W,184939.000,A,A*46",
+                // corrupted
+                "$GPGLL,0856.1940,N,07933.3294,W,184940.000,A,A*00",
+                "!AIVDM,1,1,,B,15TPq@0Oj0rClEv53P9HWVn<283C,0*51",
+            ],
+        );
+        let mut msg: Nmea0183Msg = "!AIVDM,1,1,,B,15TPq@0Oj0rClEv53P9HWVn<283C,0*51"
+            .parse()
+            .unwrap();
+        msg.set_tag_block(Some(TagBlock::new().with("c", "1396205385")));
+        builder.update(&msg);
+
+        let tracks = builder.finish();
+        assert_eq!(tracks.len(), 2);
+        let own = &tracks[0];
+        assert_eq!(own.name, "own");
+        assert_eq!(
+            own.segments.iter().map(Vec::len).collect::<Vec<_>>(),
+            [2, 1, 1]
+        );
+        assert_eq!(own.segments[0][0].time, None);
+        let point = &own.segments[0][1];
+        assert_eq!(point.time, Some(1396205347.0));
+        assert_eq!(point.elevation, Some(50.8));
+        assert_eq!(point.speed_knots, Some(0.3));
+        assert_eq!(point.fix(), Some("3d"));
+        assert_eq!(own.segments[1][0].fix(), Some("dgps"));
+        assert_eq!(own.segments[2][0].time, Some(1396205379.0));
+
+        let target = &tracks[1];
+        assert_eq!(target.mmsi, Some(target.name.parse().unwrap()));
+        assert_eq!(target.segments.len(), 1);
+        let times = target.points().map(|point| point.time).collect::<Vec<_>>();
+        assert_eq!(times, [Some(1396205379.0), Some(1396205385.0)]);
+    }
+
+    #[test]
+    fn test_date_rollover() {
+        let mut builder = TrackBuilder::new("own").with_max_gap(None);
+        for (time, date) in [("235959", "300314"), ("000000", "300314")] {
+            let rmc = Rmc {
+                time: Some(time.parse().unwrap()),
+                status: Some('A'),
+                latitude: Some(8.9),
+                longitude: Some(-79.5),
+                speed_knots: None,
+                course: None,
+                date: Some(UtcDate::from_ddmmyy(date).unwrap()),
+                magnetic_variation: None,
+                mode: None,
+            };
+            builder.update(&rmc.to_msg("GP"));
+        }
+        let tracks = builder.finish();
+        let times = tracks[0]
+            .points()
+            .map(|point| point.time)
+            .collect::<Vec<_>>();
+        assert_eq!(times, [Some(1396223999.0), Some(1396224000.0)]);
+    }
+
+    #[test]
+    fn test_export() {
+        tokio_test::block_on(async {
+            let file = tokio::fs::File::open("test_data/nmea0183_1000.log")
+                .await
+                .unwrap();
+            let tracks = TrackBuilder::new("log")
+                .with_ais(true)
+                .read(crate::get_codec(file))
+                .await;
+            assert!(tracks.len() > 1);
+            assert_eq!(tracks[0].name, "log");
+            assert!(tracks[0].segments.len() > 1);
+
+            let gpx = to_gpx(&tracks);
+            assert!(gpx.contains(
+                "<trkpt lat=\"8.936606666666668\" lon=\"-79.55546833333332\"><ele>50.8</ele>\
+                <time>2014-03-30T18:49:06.000Z</time><fix>3d</fix><sat>11</sat><hdop>0.7</hdop>\
+                <extensions><gpxtpx:TrackPointExtension><gpxtpx:speed>0.15433333333333335</gpxtpx:speed>\
+                <gpxtpx:course>222.3</gpxtpx:course></gpxtpx:TrackPointExtension></extensions></trkpt>"
+            ), "{}", &gpx[..600]);
+            assert_eq!(gpx.matches("<trk>").count(), tracks.len());
+
+            let kml = to_kml(&tracks);
+            assert!(kml.contains(
+                "<Placemark><name>Segment 1</name><TimeSpan><begin>2014-03-30T18:49:06.000Z</begin>\
+                <end>2014-03-30T18:49:06.000Z</end></TimeSpan><LineString><coordinates>\
+                -79.55546833333332,8.936606666666668,50.8</coordinates>"
+            ), "{}", &kml[..600]);
+
+            let geojson = to_geojson(&tracks);
+            assert!(geojson.starts_with(
+                "{\"type\":\"FeatureCollection\",\"features\":[{\"type\":\"Feature\",\
+                \"geometry\":{\"type\":\"MultiLineString\",\"coordinates\":[[[-79.55546833333332,8.936606666666668,50.8]],"
+            ), "{}", &geojson[..600]);
+            assert!(geojson.contains(
+                "\"properties\":{\"name\":\"log\",\"coordTimes\":[[\"2014-03-30T18:49:06.000Z\"],"
+            ));
+            assert_eq!(geojson.matches("\"mmsi\":").count(), tracks.len() - 1);
+        })
+    }
+}
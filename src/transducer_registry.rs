@@ -0,0 +1,98 @@
+use crate::sentence::{Measurement, Xdr};
+use crate::Nmea0183Msg;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub measurement: Measurement,
+    pub timestamp: Instant,
+}
+
+/// Keeps the latest XDR measurement for each transducer name.
+#[derive(Debug, Default)]
+pub struct TransducerRegistry {
+    readings: HashMap<String, Reading>,
+}
+
+impl TransducerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, msg: &Nmea0183Msg) -> Result<usize, String> {
+        self.update_at(msg, Instant::now())
+    }
+
+    /// Stores all measurements of an XDR sentence received at `timestamp`, other sentences
+    /// are ignored. Returns the number of measurements stored.
+    pub fn update_at(&mut self, msg: &Nmea0183Msg, timestamp: Instant) -> Result<usize, String> {
+        if msg.msgtype() != "XDR" {
+            return Ok(0);
+        }
+        let xdr = Xdr::try_from(msg)?;
+        let count = xdr.measurements.len();
+        for measurement in xdr.measurements {
+            self.readings.insert(
+                measurement.name.clone(),
+                Reading {
+                    measurement,
+                    timestamp,
+                },
+            );
+        }
+        Ok(count)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Reading> {
+        self.readings.get(name)
+    }
+
+    /// The latest value of a transducer in SI units.
+    pub fn si_value(&self, name: &str) -> Option<f64> {
+        self.get(name)?.measurement.si_value()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Reading)> {
+        self.readings
+            .iter()
+            .map(|(name, reading)| (name.as_str(), reading))
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_latest_value() {
+        let mut registry = TransducerRegistry::new();
+        let start = Instant::now();
+        let msg: Nmea0183Msg = "$IIXDR,P,1.02481,B,Barometer,A,-3.5,D,HEEL*2D"
+            .parse()
+            .unwrap();
+        assert_eq!(registry.update_at(&msg, start), Ok(2));
+        let msg: Nmea0183Msg = "$IIXDR,A,4.0,D,HEEL*65".parse().unwrap();
+        let later = start + Duration::from_secs(1);
+        assert_eq!(registry.update_at(&msg, later), Ok(1));
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get("Barometer").unwrap().timestamp, start);
+        let heel = registry.get("HEEL").unwrap();
+        assert_eq!(heel.timestamp, later);
+        assert_eq!(heel.measurement.value, Some(4.0));
+        assert!((registry.si_value("Barometer").unwrap() - 102481.0).abs() < 1e-6);
+
+        let msg: Nmea0183Msg = "$GPZDA,160012.71,11,03,2004,-1,00*7D".parse().unwrap();
+        assert_eq!(registry.update(&msg), Ok(0));
+    }
+}
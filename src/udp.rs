@@ -0,0 +1,342 @@
+use crate::tag_block::TagBlock;
+use crate::{Nmea0183Codec, Nmea0183Msg};
+use bytes::BytesMut;
+use futures::{ready, Sink, Stream};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::ReadBuf;
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::time::Instant;
+use tokio_util::codec::{Decoder, Encoder};
+
+/// IEC 61162-450 header of datagrams carrying sentences.
+pub const IEC_450_HEADER: &[u8] = b"UdPbC\0";
+// other IEC 61162-450 headers (binary image, retransmittable binary, ...) carry no sentences
+const IEC_450_HEADER_LEN: usize = 6;
+const IEC_450_MAX_LINE_COUNT: u32 = 999;
+
+const MAX_DATAGRAM_SIZE: usize = 65_536;
+// decoder state is kept per sender, senders are forgotten when idle or when too many send
+const MAX_PEERS: usize = 256;
+const PEER_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+struct Peer {
+    codec: Nmea0183Codec,
+    buffer: BytesMut,
+    last_seen: Instant,
+}
+
+/// Decodes sentences from UDP datagrams. A datagram may contain several sentences and lines
+/// may be split across datagrams of the same sender. IEC 61162-450 `UdPbC` headers are
+/// stripped, datagrams with other 450 headers are ignored. The partial line of a sender
+/// that is idle for a minute is dropped, as is that of the longest idle sender once 256
+/// senders are known.
+pub struct UdpSource {
+    socket: UdpSocket,
+    datagram: Vec<u8>,
+    peers: HashMap<SocketAddr, Peer>,
+    pending: VecDeque<Result<Nmea0183Msg, io::Error>>,
+    lenient: bool,
+}
+
+impl UdpSource {
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self::from_socket(UdpSocket::bind(addr).await?))
+    }
+
+    pub fn from_socket(socket: UdpSocket) -> Self {
+        Self {
+            socket,
+            datagram: vec![0; MAX_DATAGRAM_SIZE],
+            peers: HashMap::new(),
+            pending: VecDeque::new(),
+            lenient: false,
+        }
+    }
+
+    /// See [`Nmea0183Codec::with_lenient`].
+    pub fn with_lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Joins a multicast group, e.g. `239.192.0.1` for IEC 61162-450 `MISC`.
+    pub fn join_multicast_v4(&self, group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+        self.socket.join_multicast_v4(group, interface)
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    fn handle_datagram(&mut self, peer: SocketAddr, len: usize) {
+        let mut data = &self.datagram[..len];
+        if data.starts_with(IEC_450_HEADER) {
+            data = &data[IEC_450_HEADER_LEN..];
+        } else if is_iec_450_header(data) {
+            return;
+        }
+
+        let now = Instant::now();
+        if !self.peers.contains_key(&peer) {
+            self.peers
+                .retain(|_, peer| now.duration_since(peer.last_seen) < PEER_IDLE_TIMEOUT);
+            if self.peers.len() >= MAX_PEERS {
+                let idle = self
+                    .peers
+                    .iter()
+                    .min_by_key(|(_, peer)| peer.last_seen)
+                    .map(|(addr, _)| *addr);
+                if let Some(idle) = idle {
+                    self.peers.remove(&idle);
+                }
+            }
+        }
+        let lenient = self.lenient;
+        let peer = self.peers.entry(peer).or_insert_with(|| Peer {
+            codec: Nmea0183Codec::new().with_lenient(lenient),
+            buffer: BytesMut::new(),
+            last_seen: now,
+        });
+        peer.last_seen = now;
+        peer.buffer.extend_from_slice(data);
+        loop {
+            match peer.codec.decode(&mut peer.buffer) {
+                Ok(Some(msg)) => self.pending.push_back(Ok(msg)),
+                Ok(None) => break,
+                Err(error) => self.pending.push_back(Err(error)),
+            }
+        }
+    }
+}
+
+fn is_iec_450_header(data: &[u8]) -> bool {
+    data.len() >= IEC_450_HEADER_LEN
+        && data[IEC_450_HEADER_LEN - 1] == 0
+        && data[..IEC_450_HEADER_LEN - 1]
+            .iter()
+            .all(|byte| byte.is_ascii_alphabetic())
+}
+
+/// Never ends, receive errors are yielded and decode errors do not terminate the stream.
+impl Stream for UdpSource {
+    type Item = Result<Nmea0183Msg, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // all fields are Unpin
+        let this = self.get_mut();
+        loop {
+            if let Some(result) = this.pending.pop_front() {
+                return Poll::Ready(Some(result));
+            }
+            let mut buf = ReadBuf::new(&mut this.datagram);
+            let peer = match ready!(this.socket.poll_recv_from(cx, &mut buf)) {
+                Ok(peer) => peer,
+                Err(error) => return Poll::Ready(Some(Err(error))),
+            };
+            let len = buf.filled().len();
+            this.handle_datagram(peer, len);
+        }
+    }
+}
+
+/// Sends each sentence as a datagram to a unicast, broadcast or multicast address.
+pub struct UdpSink {
+    socket: UdpSocket,
+    target: SocketAddr,
+    codec: Nmea0183Codec,
+    source_id: Option<String>,
+    line_count: u32,
+    datagram: BytesMut,
+}
+
+impl UdpSink {
+    /// Binds to `local`, e.g. `0.0.0.0:0`, broadcast is enabled.
+    pub async fn bind<A: ToSocketAddrs>(local: A, target: SocketAddr) -> io::Result<Self> {
+        let socket = UdpSocket::bind(local).await?;
+        socket.set_broadcast(true)?;
+        Ok(Self::from_socket(socket, target))
+    }
+
+    pub fn from_socket(socket: UdpSocket, target: SocketAddr) -> Self {
+        Self {
+            socket,
+            target,
+            codec: Nmea0183Codec::default(),
+            source_id: None,
+            line_count: 0,
+            datagram: BytesMut::new(),
+        }
+    }
+
+    /// Prefixes each datagram with the IEC 61162-450 `UdPbC` header and a tag block
+    /// carrying `source_id`, e.g. `GP0001`, and a line count.
+    pub fn with_iec_450(mut self, source_id: &str) -> Self {
+        self.source_id = Some(source_id.to_string());
+        self
+    }
+
+    /// For socket options like the multicast TTL.
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    fn encode(&mut self, msg: &Nmea0183Msg) -> io::Result<()> {
+        match &self.source_id {
+            Some(source_id) => {
+                self.line_count = self.line_count % IEC_450_MAX_LINE_COUNT + 1;
+                let tag_block = msg
+                    .tag_block()
+                    .cloned()
+                    .unwrap_or_else(TagBlock::new)
+                    .with("s", source_id.as_str())
+                    .with("n", self.line_count.to_string());
+                let mut msg = msg.clone();
+                msg.set_tag_block(Some(tag_block));
+                self.datagram.extend_from_slice(IEC_450_HEADER);
+                self.codec.encode(&msg, &mut self.datagram)
+            }
+            None => self.codec.encode(msg, &mut self.datagram),
+        }
+    }
+}
+
+impl Sink<Nmea0183Msg> for UdpSink {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Nmea0183Msg) -> Result<(), Self::Error> {
+        self.get_mut().encode(&item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if !this.datagram.is_empty() {
+            ready!(this.socket.poll_send_to(cx, &this.datagram, this.target))?;
+            this.datagram.clear();
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_flush(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+
+    const ZDA: &str = "$GPZDA,160012.71,11,03,2004,-1,00*7D\r\n";
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E\r\n";
+
+    #[test]
+    fn test_source() {
+        tokio_test::block_on(async {
+            let mut source = UdpSource::bind("127.0.0.1:0").await.unwrap();
+            let addr = source.local_addr().unwrap();
+            let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+            // two sentences in one datagram, then a line split across two datagrams
+            let datagram = format!("{}{}", ZDA, RMC);
+            socket.send_to(datagram.as_bytes(), addr).await.unwrap();
+            let (head, tail) = RMC.split_at(30);
+            socket.send_to(head.as_bytes(), addr).await.unwrap();
+            socket.send_to(tail.as_bytes(), addr).await.unwrap();
+            // binary image datagrams are ignored
+            socket.send_to(b"RaUdP\0\x01\x02", addr).await.unwrap();
+            let datagram = format!("UdPbC\0\\s:GP0001,n:7*10\\{}", ZDA);
+            socket.send_to(datagram.as_bytes(), addr).await.unwrap();
+
+            let mut msgtypes = Vec::new();
+            for _ in 0..3 {
+                let msg = source.next().await.unwrap().unwrap();
+                assert!(msg.tag_block().is_none());
+                msgtypes.push(msg.msgtype().to_string());
+            }
+            assert_eq!(msgtypes, ["ZDA", "RMC", "RMC"]);
+
+            let msg = source.next().await.unwrap().unwrap();
+            assert_eq!(msg.msgtype(), "ZDA");
+            let tag_block = msg.tag_block().unwrap();
+            assert_eq!(tag_block.chksum_valid(), Some(true));
+            assert_eq!(tag_block.source(), Some("GP0001"));
+            assert_eq!(tag_block.line_count(), Some(7));
+        })
+    }
+
+    fn receive(source: &mut UdpSource, port: u16, data: &str) {
+        source.datagram[..data.len()].copy_from_slice(data.as_bytes());
+        source.handle_datagram(SocketAddr::from(([10, 0, 0, 1], port)), data.len());
+    }
+
+    #[test]
+    fn test_peer_eviction() {
+        tokio_test::block_on(async {
+            tokio::time::pause();
+            let mut source = UdpSource::bind("127.0.0.1:0").await.unwrap();
+            let (head, tail) = RMC.split_at(30);
+            receive(&mut source, 1, head);
+            tokio::time::advance(Duration::from_secs(1)).await;
+            for port in 2..=MAX_PEERS as u16 {
+                receive(&mut source, port, head);
+            }
+            // the longest idle sender is forgotten
+            receive(&mut source, 1000, head);
+            assert_eq!(source.peers.len(), MAX_PEERS);
+            assert!(!source
+                .peers
+                .contains_key(&SocketAddr::from(([10, 0, 0, 1], 1))));
+
+            tokio::time::advance(PEER_IDLE_TIMEOUT).await;
+            receive(&mut source, 2000, ZDA);
+            assert_eq!(source.peers.len(), 1);
+            // a sender that was idle starts over, its partial line is lost
+            receive(&mut source, 2, tail);
+            let msgs = source
+                .pending
+                .iter()
+                .filter_map(|result| result.as_ref().ok())
+                .collect::<Vec<_>>();
+            assert_eq!(msgs.len(), 1);
+            assert_eq!(msgs[0].msgtype(), "ZDA");
+        })
+    }
+
+    #[test]
+    fn test_sink_iec_450() {
+        tokio_test::block_on(async {
+            let receiver = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let addr = receiver.local_addr().unwrap();
+            let mut sink = UdpSink::bind("127.0.0.1:0", addr)
+                .await
+                .unwrap()
+                .with_iec_450("GP0001");
+            let mut source = UdpSource::from_socket(receiver);
+
+            for sentence in [ZDA, RMC] {
+                sink.send(sentence.parse().unwrap()).await.unwrap();
+            }
+            for (line_count, sentence) in [(1, ZDA), (2, RMC)] {
+                let msg = source.next().await.unwrap().unwrap();
+                assert_eq!(msg.tag_block().unwrap().source(), Some("GP0001"));
+                assert_eq!(msg.tag_block().unwrap().line_count(), Some(line_count));
+                let mut expected: Nmea0183Msg = sentence.parse().unwrap();
+                expected.set_tag_block(msg.tag_block().cloned());
+                assert_eq!(msg, expected);
+            }
+        })
+    }
+}
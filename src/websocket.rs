@@ -0,0 +1,331 @@
+use crate::json::{array, quote, JsonObject};
+use crate::tcp::accept_while;
+use crate::{Nmea0183Msg, ResumeOnError};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+
+const CLIENT_QUEUE_SIZE: usize = 64;
+const SERVER_QUEUE_SIZE: usize = 1024;
+
+/// How sentences are sent to a client.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// One sentence per text message, e.g. `$GPZDA,...*7D`, formatted from the decoded
+    /// fields with the checksum as received.
+    Raw,
+    /// One object per text message, see [`to_json`].
+    Json,
+}
+
+/// Encodes a sentence as
+/// `{"talker":"GP","msgtype":"ZDA","params":["160012.71",...],"checksum_valid":true}`,
+/// the tag block is added as string if present.
+pub fn to_json(msg: &Nmea0183Msg) -> String {
+    let tag_block = msg.tag_block().map(|tag_block| tag_block.to_string());
+    let mut json = JsonObject::new()
+        .opt_str("tag_block", tag_block.as_deref())
+        .bool("encapsulation", msg.is_encapsulation())
+        .str("talker", msg.talker())
+        .str("msgtype", msg.msgtype())
+        .raw(
+            "params",
+            &array(msg.params().iter().map(|param| quote(param))),
+        );
+    if let Some(valid) = msg.chksum_valid() {
+        json = json.bool("checksum_valid", valid);
+    }
+    json.finish()
+}
+
+/// Per client settings taken from the query string of the request, e.g.
+/// `ws://host:port/?format=json&sentences=RMC,GGA&talkers=GP`. Without `sentences` or
+/// `talkers` all sentences are sent.
+#[derive(Debug, Clone, PartialEq)]
+struct ClientFilter {
+    format: Format,
+    sentences: Vec<String>,
+    talkers: Vec<String>,
+}
+
+impl Default for ClientFilter {
+    fn default() -> Self {
+        Self {
+            format: Format::Raw,
+            sentences: Vec::new(),
+            talkers: Vec::new(),
+        }
+    }
+}
+
+impl ClientFilter {
+    fn from_query(query: Option<&str>) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for pair in query.unwrap_or_default().split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let values = || {
+                value
+                    .split(',')
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            };
+            match key {
+                "" => {}
+                "format" => {
+                    filter.format = match value {
+                        "raw" => Format::Raw,
+                        "json" => Format::Json,
+                        _ => return Err(format!("Invalid format '{}'", value)),
+                    }
+                }
+                "sentences" => filter.sentences = values(),
+                "talkers" => filter.talkers = values(),
+                _ => return Err(format!("Invalid parameter '{}'", key)),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, msg: &Nmea0183Msg) -> bool {
+        (self.sentences.is_empty() || self.sentences.iter().any(|id| id == msg.msgtype()))
+            && (self.talkers.is_empty() || self.talkers.iter().any(|id| id == msg.talker()))
+    }
+
+    fn encode(&self, msg: &Nmea0183Msg) -> String {
+        match self.format {
+            Format::Raw => msg.to_string(),
+            Format::Json => to_json(msg),
+        }
+    }
+}
+
+/// Streams sentences to WebSocket clients, e.g. browser based displays, and forwards
+/// sentences sent by clients if they are on the allow-list. Clients that cannot keep up
+/// skip messages.
+pub struct WebSocketServer {
+    listener: TcpListener,
+    allowed: Vec<String>,
+    tx: broadcast::Sender<Nmea0183Msg>,
+}
+
+impl WebSocketServer {
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let (tx, _) = broadcast::channel(SERVER_QUEUE_SIZE);
+        Ok(Self {
+            listener: TcpListener::bind(addr).await?,
+            allowed: Vec::new(),
+            tx,
+        })
+    }
+
+    /// Sentences from clients are forwarded if their message type or the start of
+    /// talker and message type matches an entry, e.g. `APB` or `PMTK`. Sentences with
+    /// an invalid checksum are never forwarded.
+    pub fn with_allowed(mut self, ids: &[&str]) -> Self {
+        self.allowed = ids.iter().map(|id| id.to_string()).collect();
+        self
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.tx.receiver_count()
+    }
+
+    /// Accepts clients and forwards the messages of `stream`, e.g. from
+    /// [`crate::get_codec`], until it ends. Allowed client sentences are written to
+    /// `sink`. Decode errors are skipped.
+    pub async fn serve<S, K>(self, stream: S, mut sink: K) -> io::Result<()>
+    where
+        S: Stream<Item = Result<Nmea0183Msg, io::Error>> + Unpin,
+        K: Sink<Nmea0183Msg, Error = io::Error> + Unpin,
+    {
+        let Self {
+            listener,
+            allowed,
+            tx,
+        } = self;
+        let (incoming_tx, mut incoming) = mpsc::channel(CLIENT_QUEUE_SIZE);
+        let accept = run(listener, Arc::new(allowed), tx.clone(), incoming_tx);
+        let forward = async move {
+            let mut stream = ResumeOnError::new(stream);
+            loop {
+                tokio::select! {
+                    result = stream.next() => match result {
+                        // no receivers is not an error, clients come and go
+                        Some(Ok(msg)) => { let _ = tx.send(msg); }
+                        Some(Err(_)) => {}
+                        None => return Ok(()),
+                    },
+                    Some(msg) = incoming.recv() => sink.send(msg).await?,
+                }
+            }
+        };
+        accept_while(accept, forward).await
+    }
+}
+
+async fn run(
+    listener: TcpListener,
+    allowed: Arc<Vec<String>>,
+    tx: broadcast::Sender<Nmea0183Msg>,
+    incoming: mpsc::Sender<Nmea0183Msg>,
+) -> io::Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        tokio::spawn(serve_client(
+            socket,
+            allowed.clone(),
+            tx.subscribe(),
+            incoming.clone(),
+        ));
+    }
+}
+
+fn is_allowed(allowed: &[String], msg: &Nmea0183Msg) -> bool {
+    let id = format!("{}{}", msg.talker(), msg.msgtype());
+    msg.chksum_valid() != Some(false)
+        && allowed
+            .iter()
+            .any(|entry| entry == msg.msgtype() || id.starts_with(entry.as_str()))
+}
+
+async fn serve_client(
+    socket: TcpStream,
+    allowed: Arc<Vec<String>>,
+    mut rx: broadcast::Receiver<Nmea0183Msg>,
+    incoming: mpsc::Sender<Nmea0183Msg>,
+) {
+    let mut filter = ClientFilter::default();
+    // the signature is given by tungstenite
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| match ClientFilter::from_query(
+        request.uri().query(),
+    ) {
+        Ok(client_filter) => {
+            filter = client_filter;
+            Ok(response)
+        }
+        Err(error) => {
+            let mut response = ErrorResponse::new(Some(error));
+            *response.status_mut() = StatusCode::BAD_REQUEST;
+            Err(response)
+        }
+    };
+    let (mut writer, mut reader) = match tokio_tungstenite::accept_hdr_async(socket, callback).await
+    {
+        Ok(websocket) => websocket.split(),
+        Err(_) => return,
+    };
+    loop {
+        tokio::select! {
+            message = reader.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    // other lines are dropped, the client gets no feedback
+                    let msgs = text
+                        .lines()
+                        .filter_map(|line| line.trim().parse::<Nmea0183Msg>().ok())
+                        .filter(|msg| is_allowed(&allowed, msg));
+                    for msg in msgs {
+                        if incoming.send(msg).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            msg = rx.recv() => match msg {
+                Ok(msg) => {
+                    if filter.matches(&msg)
+                        && writer.send(Message::text(filter.encode(&msg))).await.is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc as channel;
+
+    const ZDA: &str = "$GPZDA,160012.71,11,03,2004,-1,00*7D";
+    const RMC: &str = "$GPRMC,184906.000,A,0856.1964,N,07933.3281,W,0.30,222.30,300314,,,A*7E";
+
+    fn msg(sentence: &str) -> Nmea0183Msg {
+        sentence.parse().expect("failed to parse message")
+    }
+
+    #[test]
+    fn test_client_filter() {
+        let filter = ClientFilter::from_query(Some("format=json&sentences=RMC,GGA")).unwrap();
+        assert_eq!(filter.format, Format::Json);
+        assert!(filter.matches(&msg(RMC)));
+        assert!(!filter.matches(&msg(ZDA)));
+        assert_eq!(
+            filter.encode(&msg(ZDA)),
+            r#"{"encapsulation":false,"talker":"GP","msgtype":"ZDA","params":["160012.71","11","03","2004","-1","00"],"checksum_valid":true}"#
+        );
+        assert_eq!(
+            ClientFilter::from_query(None).unwrap(),
+            ClientFilter::default()
+        );
+        assert!(ClientFilter::from_query(Some("format=xml")).is_err());
+    }
+
+    #[test]
+    fn test_server() {
+        tokio_test::block_on(async {
+            let server = WebSocketServer::bind("127.0.0.1:0")
+                .await
+                .unwrap()
+                .with_allowed(&["PMTK"]);
+            let addr = server.local_addr().unwrap();
+            let (tx, rx) = channel::unbounded::<Result<Nmea0183Msg, io::Error>>();
+            let (sink, mut forwarded) = channel::unbounded::<Nmea0183Msg>();
+            tokio::spawn(server.serve(rx, sink.sink_map_err(io::Error::other)));
+
+            let url = format!("ws://{}/?format=json&sentences=RMC", addr);
+            let (mut json_client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+            let url = format!("ws://{}/", addr);
+            let (mut raw_client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+            tx.unbounded_send(Ok(msg(ZDA))).unwrap();
+            tx.unbounded_send(Ok(msg(RMC))).unwrap();
+            let text = |message: Option<Result<Message, _>>| {
+                message.unwrap().unwrap().into_text().unwrap()
+            };
+            assert!(text(json_client.next().await).contains(r#""msgtype":"RMC""#));
+            assert_eq!(text(raw_client.next().await).as_str(), ZDA);
+            assert_eq!(text(raw_client.next().await).as_str(), RMC);
+
+            raw_client
+                .send(Message::text(format!("{}\r\n$PMTK220,1000*1F", ZDA)))
+                .await
+                .unwrap();
+            assert_eq!(
+                forwarded.next().await.unwrap().to_string(),
+                "$PMTK220,1000*1F"
+            );
+
+            let url = format!("ws://{}/?format=xml", addr);
+            assert!(tokio_tungstenite::connect_async(url).await.is_err());
+        })
+    }
+}